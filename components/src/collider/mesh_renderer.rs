@@ -17,6 +17,11 @@ pub fn mesh_renderer_draw(
     mut engine: ResMut<GgezInterface>,
     _camera: Res<Camera>,
 ) {
+    // headless roots step frames without a canvas
+    if engine.get_canvas().is_none() {
+        return;
+    }
+
     for (renderer, collider) in query.iter() {
         // initial param before applying camera offset, and maybe shaders later
         let initial_param = &renderer.draw_param;
@@ -25,9 +30,13 @@ pub fn mesh_renderer_draw(
         let final_param = initial_param.clone();
 
         for (_mesh_id, mesh) in &collider.meshes {
+            let Some(context) = engine.try_get_context() else {
+                return;
+            };
+
             let drawable = match mesh {
                 super::MeshType::Convex(convex_mesh) => {
-                    convex_mesh.into_graphics_mesh(&engine.get_context().gfx)
+                    convex_mesh.into_graphics_mesh(&context.gfx)
                 }
             };
 
@@ -15,7 +15,7 @@ use bevy_ecs::system::Query;
 use bevy_reflect::{Reflect, ReflectSerialize};
 use engine::assets::SceneAssetID;
 use engine::scene::Scene;
-use engine::space::Position;
+use engine::space::GlobalTransform;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -81,8 +81,8 @@ impl Collider {
     }
 }
 
-pub fn collider_update(mut query: Query<(&mut Collider, &Position)>) {
-    for (mut collider, _position) in query.iter_mut() {
+pub fn collider_update(mut query: Query<(&mut Collider, &GlobalTransform)>) {
+    for (mut collider, _transform) in query.iter_mut() {
         for _mesh in &mut collider.meshes {}
     }
 }
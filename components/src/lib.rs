@@ -13,6 +13,8 @@ use bevy_ecs::prelude::*;
 use bevy_reflect::ReflectSerialize;
 use collider::MeshType;
 use engine::assets::SceneAssetID;
+use engine::schedule::ScheduleBuilder;
+use engine::EnginePlugin;
 use engine::{register_component, scene::SceneManager};
 use engine::{register_custom_inspection, register_enum};
 use std::collections::HashMap;
@@ -41,8 +43,38 @@ pub mod schedules {
     }
 }
 
+/// Registers every component type and system in this crate. Doesn't place any systems into a schedule, that's left up to the game.
+pub struct ComponentsPlugin;
+
+impl EnginePlugin for ComponentsPlugin {
+    fn build(&self, world: &mut World) {
+        initialize_component_types(world);
+        register_systems(world);
+        log::trace!("Registered component types and systems");
+    }
+}
+
+/// Registers every system in this crate into the [`ScheduleBuilder`] by name, so that schedule definitions can use them.
+///
+/// This only makes the systems available, it doesn't add them to any schedule.
+pub fn register_systems(world: &mut World) {
+    let mut builder = world.resource_mut::<ScheduleBuilder>();
+
+    builder.register_system("protag_init", schedules::tick::protag_init);
+    builder.register_system("protag_update", schedules::tick::protag_update);
+    builder.register_system("collider_update", schedules::tick::collider_update);
+    builder.register_system("renderer_update", schedules::tick::renderer_update);
+
+    builder.register_system("renderer_draw", schedules::frame::renderer_draw);
+    builder.register_system("mesh_renderer_draw", schedules::frame::mesh_renderer_draw);
+    builder.register_system(
+        "render_text_renderers",
+        schedules::frame::render_text_renderers,
+    );
+}
+
 /// Adds all of the components to the type registry.
-pub fn initialize_component_types(world: &mut World) -> () {
+pub fn initialize_component_types(world: &mut World) {
     world.resource_scope(|world: &mut World, mut manager: Mut<SceneManager>| {
         let type_registry = &mut manager.type_registry;
         register_component::<render::Renderer>(world, type_registry);
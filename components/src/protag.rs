@@ -7,7 +7,7 @@ use bevy_reflect::Reflect;
 use engine::scene::Scene;
 use engine::space::{Position, Transform, TransformSettings, Velocity};
 use engine::GgezInterface;
-use engine::{space, Input};
+use engine::{space, Input, Time};
 use ggez::graphics::{self, Color, DrawParam, Image, Rect};
 use serde::{Deserialize, Serialize};
 
@@ -49,32 +49,36 @@ pub struct ProtagController {
 pub fn protag_update(
     mut query: Query<(&mut Position, &mut Velocity, &ProtagController)>,
     input: Res<Input>,
-
+    time: Res<Time>,
     ggez: Res<GgezInterface>,
 ) {
-    let screen_height = ggez.get_context().gfx.drawable_size().1;
+    let screen_height = ggez.drawable_size().1;
+    let scale = time.time_scale();
 
     for (mut pos, mut velocity, controller) in query.iter_mut() {
         // if controller.speed_cap < 0
 
         if velocity.x.abs() > controller.speed_cap {
-            velocity.x -= (controller.decel) * (velocity.x / velocity.x.abs());
+            velocity.x -= (controller.decel * scale) * (velocity.x / velocity.x.abs());
         }
 
         if is_moving(WASD::D, &input) && velocity.x < controller.speed_cap {
-            velocity.x += controller.acc;
+            velocity.x += controller.acc * scale;
         } else if is_moving(WASD::A, &input) && velocity.x > -controller.speed_cap {
-            velocity.x -= controller.acc;
+            velocity.x -= controller.acc * scale;
         } else {
-            velocity.x *= 1.0 - controller.decel
+            velocity.x *= 1.0 - controller.decel * scale
         }
         if is_moving(WASD::W, &input) {
-            velocity.y -= 0.3;
+            velocity.y -= 0.3 * scale;
         }
         if is_moving(WASD::S, &input) {
-            velocity.y += 0.3;
+            velocity.y += 0.3 * scale;
         }
-        if (pos.y > 370.0 || true) && input.get_action("Click").unwrap().is_just_pressed() {
+        let clicked = input
+            .get_action("Click")
+            .is_some_and(|action| action.is_just_pressed());
+        if (pos.y > 370.0 || true) && clicked {
             velocity.y = -controller.jump_power;
             velocity.x *= 1.2;
             velocity.x = velocity
@@ -83,7 +87,7 @@ pub fn protag_update(
             //panics if speed_cap is negative
         }
         if velocity.y < controller.max_fall_speed && pos.y < screen_height - 110.0 {
-            velocity.y += controller.fall_acc;
+            velocity.y += controller.fall_acc * scale;
         } else if pos.y > screen_height - 100.0 && velocity.y >= 0.0 {
             velocity.y = 0.0;
             pos.y = screen_height - 100.0;
@@ -106,9 +110,7 @@ fn is_moving(direction: WASD, input: &Input) -> bool {
             WASD::S => "Down",
             WASD::D => "Right",
         })
-        .unwrap()
-        .status()
-        .is_held()
+        .is_some_and(|action| action.status().is_held())
 }
 
 impl ProtagBundle {
@@ -123,16 +125,11 @@ impl ProtagBundle {
             settings: TransformSettings { auto_update: true },
         };
 
-        let gfx = &GgezInterface::get_context(&engine).gfx;
-        let mut renderer = Renderer::new(
-            Some(RenderType::Image(Image::from_color(
-                gfx,
-                100,
-                100,
-                Some(Color::RED),
-            ))),
-            transform.into(),
-        );
+        // headless roots have no graphics context to create the image with
+        let image = engine.try_get_context().map(|context| {
+            RenderType::Image(Image::from_color(&context.gfx, 100, 100, Some(Color::RED)))
+        });
+        let mut renderer = Renderer::new(image, transform.into());
         renderer.set(
             DrawParam {
                 src: Rect::new_i32(10, 10, 1, 1),
@@ -23,24 +23,9 @@ use engine::space::Transform;
 
 use engine::space::*;
 
-type TransformComponentTuple<'a> = (
-    &'a Position,
-    &'a Velocity,
-    &'a Rotation,
-    &'a Scale,
-    &'a TransformSettings,
-);
-
-pub fn renderer_update(mut query: Query<(&mut Renderer, TransformComponentTuple)>) {
+pub fn renderer_update(mut query: Query<(&mut Renderer, &GlobalTransform)>) {
     for (mut renderer, transform) in &mut query {
-        let draw_transform: ggez::graphics::Transform = Transform {
-            position: dbg!(transform.0.to_owned()),
-            velocity: transform.1.to_owned(),
-            rotation: transform.2.to_owned(),
-            scale: transform.3.to_owned(),
-            settings: transform.4.to_owned(),
-        }
-        .into();
+        let draw_transform: ggez::graphics::Transform = transform.to_owned().into();
 
         renderer.draw_param.transform = draw_transform.clone();
 
@@ -51,7 +36,7 @@ pub fn renderer_update(mut query: Query<(&mut Renderer, TransformComponentTuple)
 }
 
 pub fn renderer_draw(
-    query: Query<(&Renderer, TransformComponentTuple)>,
+    query: Query<(&Renderer, &GlobalTransform)>,
     mut main_canvas: ResMut<GgezInterface>,
     camera: Res<Camera>,
 ) {
@@ -75,8 +60,8 @@ pub fn renderer_draw(
                 RenderType::Quad(quad) => canvas.draw(
                     quad,
                     renderer.draw_param.dest::<mint::Point2<f32>>(mint::Point2 {
-                        x: transform.0.x,
-                        y: transform.0.y,
+                        x: transform.position.x,
+                        y: transform.position.y,
                     }),
                 ),
             }
@@ -88,19 +73,15 @@ fn draw_image(
     canvas: &mut Canvas,
     image: &Image,
     renderer: &Renderer,
-    transform: TransformComponentTuple,
+    transform: &GlobalTransform,
     camera: &Camera,
 ) {
-    let mut transformer = DEFAULT_TRANSFORM.clone();
-
-    transformer.position = {
-        (*transform
-            .0
-            .to_owned()
-            .translate(&renderer.offset)
-            .translate(&-*camera.position))
-        .into()
-    };
+    let mut transformer = transform.to_owned();
+
+    transformer
+        .position
+        .translate(&renderer.offset)
+        .translate(&-*camera.position);
 
     let mut draw_param = renderer.draw_param.clone();
 
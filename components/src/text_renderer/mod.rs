@@ -174,11 +174,13 @@ pub fn render_text_renderers(
         }
     }
 
+    // headless roots step frames without a canvas
+    let Some(canvas) = engine.get_canvas_mut() else {
+        return;
+    };
+
     for renderer in query.iter() {
-        engine
-            .get_canvas_mut()
-            .expect("expected the text rendering system to be run only in a draw frame")
-            .draw(&renderer.text_object, renderer.draw_param)
+        canvas.draw(&renderer.text_object, renderer.draw_param)
     }
 }
 
@@ -6,15 +6,17 @@ use bevy_ecs::{
     world::{Mut, World},
 };
 use bevy_reflect::{DynamicStruct, DynamicTupleStruct, ReflectOwned, TypeInfo};
+use engine::crash::PanicReport;
+use engine::profiler::Profiler;
+use engine::save;
+use engine::Input;
 use engine::{
     scene::{self, *},
     LogData,
 };
-use engine::{GameRoot, Input};
 use inquire::{validator::Validation, Confirm, InquireError, Select, Text};
 use log::error;
 use std::any::Any;
-use std::panic::PanicInfo;
 use std::process::exit;
 use std::{collections::HashMap, path::PathBuf, sync::OnceLock};
 
@@ -37,6 +39,11 @@ fn commands() -> &'static Vec<DebugCommand> {
                 "Saves the target scene to the file specified. Will prompt for a file path if the target scene doesn't have one set.",
             ),
             DebugCommand::new("loadscene", load_scene, "Loads the scene from the given path. Will prompt for a file path."),
+            DebugCommand::new("loadsceneasync", load_scene_async, "Loads the scene from the given path in the background, freezing the game until it's done. Will prompt for a file path."),
+            DebugCommand::new("exportscene", export_scene, "Exports the target scene as a compact binary file for shipped games. Will prompt for a file path."),
+            DebugCommand::new("convertscene", convert_scene, "Converts a scene file between JSON, RON and binary, picked by the file extensions. Will prompt for both file paths."),
+            DebugCommand::new("spawnprefab", spawn_prefab, "Spawns an instance of a prefab into the target scene. Will prompt for the prefab's path and the instance's name."),
+            DebugCommand::new("saveprefab", save_prefab, "Saves an entity in the target scene and everything attached to it as a prefab. Will prompt for the entity and a file path."),
             DebugCommand::new("unloadscene", unload_scene, "Unloads the current target scene. Does not save, any unsaved data will be lost."),
             DebugCommand::new("reloadscene", reload_scene, "Reloads the current target scene, pulling in data from the save file. Does not save, any unsaved data will be lost."),
             DebugCommand::new("changescene", change_target_scene, "Sets the target scene to the one specified. Will prompt for a scene name."),
@@ -45,46 +52,46 @@ fn commands() -> &'static Vec<DebugCommand> {
             DebugCommand::new("scoopentities", scoop_entities, "Pulls any isolated entities into the current target scene. "),
             DebugCommand::new("addcomponent", add_component, "Adds a new component to the entity. Advanced feature, many prompts and can be quite confusing. "),
             DebugCommand::new("listcomponents", list_components, "Displays every currently instantiated component, as well as the entity it belongs to."),
+            DebugCommand::new("profile", profile, "Prints the min/avg/max time of every schedule and system, along with a frame time histogram."),
+            DebugCommand::new("toggleprofiler", toggle_profiler, "Enables or disables the profiler."),
+            DebugCommand::new("exporttrace", export_trace, "Exports recent schedule and system timings as a Chrome trace file. Will prompt for a file path."),
+            DebugCommand::new("saveslot", save_slot, "Saves the player's progress into a save slot. Will prompt for a slot name."),
+            DebugCommand::new("loadslot", load_slot, "Reloads the saved scenes and applies a save slot on top of them. Will prompt for a slot name."),
+            DebugCommand::new("listslots", list_slots, "Lists every save slot, most recent first."),
             DebugCommand::new("crash", crash, "Exits the program instantly, without saving.")
             ]
     })
 }
 
-pub fn check_for_debug(input: Res<Input>, mut engine: ResMut<engine::GgezInterface>) {
-    if let Some(action) = input.get_action("debugconsole") {
-        if action.status().is_just_pressed() {
-            debug_cli(unsafe { &mut *SECRET_ROOT_PTR })
-        }
+/// Opens the debug console when the `debugconsole` action is pressed, as long as there is a terminal to type into.
+pub fn check_for_debug(world: &mut World) {
+    let pressed = world
+        .resource::<Input>()
+        .get_action("debugconsole")
+        .is_some_and(|action| action.status().is_just_pressed());
+
+    if !pressed {
+        return;
     }
-}
 
-/// This is to be used purely by the panic handler and debug console, as an escape mechanism to save your data before it is lost.
-/// Do not use this under any other circumstances, doing so will result in behaviour that I don't care to define.
-static mut SECRET_ROOT_PTR: *mut GameRoot = std::ptr::null_mut();
-pub fn hook_emergency_panic_handler(root: &mut GameRoot) {
-    unsafe { SECRET_ROOT_PTR = root };
+    if !engine::crash::is_interactive() {
+        log::warn!("The debug console needs a terminal, launch the editor from one to use it.");
+        return;
+    }
 
-    std::panic::set_hook(Box::new(|panic_info: &std::panic::PanicInfo| {
-        println!(
-            "\n{}\nEntering debug console to rescue data.",
-            // panic_info.location().unwrap(),
-            panic_info.to_string()
-        );
-        panic_debug_cli(unsafe { &mut *SECRET_ROOT_PTR }, panic_info);
-        // panic_info.log();
-    }));
+    debug_cli(world)
 }
 
 struct DebugCommand {
     command_name: &'static str,
-    function: fn(&mut GameRoot) -> Result<(), String>,
+    function: fn(&mut World) -> Result<(), String>,
     help_text: &'static str,
 }
 
 impl DebugCommand {
     pub fn new(
         name: &'static str,
-        func: fn(&mut GameRoot) -> Result<(), String>,
+        func: fn(&mut World) -> Result<(), String>,
         help_text: &'static str,
     ) -> Self {
         Self {
@@ -95,7 +102,12 @@ impl DebugCommand {
     }
 }
 
-pub fn panic_debug_cli(root: &mut GameRoot, panic_info: &PanicInfo) {
+/// The debug console opened after a panic, once the engine has saved its crash recovery files.
+///
+/// Inserted as the engine's [`PanicHandler`](engine::crash::PanicHandler) by the [`EditorPlugin`](crate::EditorPlugin).
+pub fn panic_debug_cli(world: &mut World, report: &PanicReport) {
+    println!("\n{}\nEntering debug console to rescue data.", report);
+
     let char_limit = |input: &str| match input.chars().count() <= 100 {
         true => Ok(Validation::Valid),
         false => Ok(Validation::Invalid(
@@ -103,7 +115,7 @@ pub fn panic_debug_cli(root: &mut GameRoot, panic_info: &PanicInfo) {
         )),
     };
 
-    let mut action_to_function: HashMap<&str, fn(&mut GameRoot) -> Result<(), String>> =
+    let mut action_to_function: HashMap<&str, fn(&mut World) -> Result<(), String>> =
         HashMap::new();
 
     for debug_command in commands() {
@@ -119,7 +131,7 @@ pub fn panic_debug_cli(root: &mut GameRoot, panic_info: &PanicInfo) {
             Err(err) => {
                 let _ = match err {
                     InquireError::OperationCanceled => break,
-                    InquireError::OperationInterrupted => crash(root),
+                    InquireError::OperationInterrupted => crash(world),
                     _ => {
                         println!("Err? [{}]", err);
                         Ok(())
@@ -134,22 +146,11 @@ pub fn panic_debug_cli(root: &mut GameRoot, panic_info: &PanicInfo) {
             break;
         }
         if &user_input == "logbacktrace" {
-            let var = std::env::var("RUST_BACKTRACE");
-            if var == Ok("1".to_owned()) {
-                log::trace!("{}", std::backtrace::Backtrace::capture());
-            } else {
-                std::env::set_var("RUST_BACKTRACE", "1");
-                log::trace!("{}", std::backtrace::Backtrace::capture());
-                if var.is_ok() {
-                    std::env::set_var("RUST_BACKTRACE", "0");
-                } else {
-                    std::env::set_var("RUST_BACKTRACE", "");
-                }
-                continue;
-            }
+            println!("{}", report.backtrace);
+            continue;
         }
         if let Some(f) = action_to_function.get(user_input.as_str()) {
-            if let Err(err) = f(root) {
+            if let Err(err) = f(world) {
                 println!("Operation error");
                 error!("{}", err);
             };
@@ -161,7 +162,7 @@ pub fn panic_debug_cli(root: &mut GameRoot, panic_info: &PanicInfo) {
     println!("Exiting debug console");
 }
 
-pub fn debug_cli(root: &mut GameRoot) {
+pub fn debug_cli(world: &mut World) {
     let char_limit = |input: &str| match input.chars().count() <= 100 {
         true => Ok(Validation::Valid),
         false => Ok(Validation::Invalid(
@@ -169,7 +170,7 @@ pub fn debug_cli(root: &mut GameRoot) {
         )),
     };
 
-    let mut action_to_function: HashMap<&str, fn(&mut GameRoot) -> Result<(), String>> =
+    let mut action_to_function: HashMap<&str, fn(&mut World) -> Result<(), String>> =
         HashMap::new();
 
     for debug_command in commands() {
@@ -182,7 +183,7 @@ pub fn debug_cli(root: &mut GameRoot) {
             Err(err) => {
                 let _ = match err {
                     InquireError::OperationCanceled => break,
-                    InquireError::OperationInterrupted => crash(root),
+                    InquireError::OperationInterrupted => crash(world),
                     _ => {
                         println!("Err? [{}]", err);
                         Ok(())
@@ -197,7 +198,7 @@ pub fn debug_cli(root: &mut GameRoot) {
             break;
         }
         if let Some(f) = action_to_function.get(user_input.as_str()) {
-            if let Err(err) = f(root) {
+            if let Err(err) = f(world) {
                 println!("Operation error");
                 error!("{}", err);
             };
@@ -211,7 +212,7 @@ pub fn debug_cli(root: &mut GameRoot) {
 
 // Misc commands
 
-fn help(_: &mut GameRoot) -> Result<(), String> {
+fn help(_: &mut World) -> Result<(), String> {
     // println!("{}", HOME_HELP_TEXT);
     println!("Commands: ");
     for debug_command in commands() {
@@ -223,19 +224,105 @@ fn help(_: &mut GameRoot) -> Result<(), String> {
     Ok(())
 }
 
-fn crash(_root: &mut GameRoot) -> Result<(), String> {
+fn crash(_world: &mut World) -> Result<(), String> {
     exit(1);
     #[allow(unreachable_code)]
     Err("failed to crash??".to_owned()) // lie to the compiler >:)))
                                         //but if this returns err that means something seriously went wrong
 }
 
+// Profiling
+
+fn profile(world: &mut World) -> Result<(), String> {
+    let profiler = world.resource::<Profiler>();
+
+    if !profiler.is_enabled() {
+        println!("The profiler is disabled, use toggleprofiler to enable it.");
+    }
+
+    println!("{}", profiler.report());
+
+    Ok(())
+}
+
+fn toggle_profiler(world: &mut World) -> Result<(), String> {
+    let mut profiler = world.resource_mut::<Profiler>();
+
+    let enabled = !profiler.is_enabled();
+    profiler.set_enabled(enabled);
+
+    println!("Profiler {}", if enabled { "enabled" } else { "disabled" });
+
+    Ok(())
+}
+
+fn export_trace(world: &mut World) -> Result<(), String> {
+    let path = Text::new("Path of trace file >")
+        .with_default("trace.json")
+        .prompt()
+        .map_err(|err| format!("Aborted exporting trace [{}]", err.to_string()))?;
+
+    world
+        .resource::<Profiler>()
+        .export_chrome_trace(&path)
+        .map_err(|err| err.to_string())?;
+
+    println!("Exported trace to {}", path);
+
+    Ok(())
+}
+
+// Save slots
+
+fn save_slot(world: &mut World) -> Result<(), String> {
+    let slot = Text::new("Slot name >")
+        .prompt()
+        .map_err(|err| format!("Aborted saving slot [{}]", err.to_string()))?;
+
+    let metadata = save::save_slot(world, &slot).map_err(|err| err.to_string())?;
+
+    println!(
+        "Saved slot {} at {:?} of play time",
+        slot, metadata.play_time
+    );
+
+    Ok(())
+}
+
+fn load_slot(world: &mut World) -> Result<(), String> {
+    let slot = Text::new("Slot name >")
+        .prompt()
+        .map_err(|err| format!("Aborted loading slot [{}]", err.to_string()))?;
+
+    save::load_slot(world, &slot).map_err(|err| err.to_string())?;
+
+    println!("Loaded slot {}", slot);
+
+    Ok(())
+}
+
+fn list_slots(world: &mut World) -> Result<(), String> {
+    let slots = save::list_slots(world).map_err(|err| err.to_string())?;
+
+    if slots.is_empty() {
+        println!("No save slots found!");
+    }
+
+    for metadata in slots {
+        println!(
+            "{} - saved at {}, {:?} of play time (version {})",
+            metadata.slot, metadata.saved_at, metadata.play_time, metadata.version
+        );
+    }
+
+    Ok(())
+}
+
 // Scene
 
-fn save_scene(root: &mut GameRoot) -> Result<(), String> {
+fn save_scene(world: &mut World) -> Result<(), String> {
     match Confirm::new("Are you sure? This will overwrite any previous saved data >").prompt() {
-        Ok(_response_yes) if _response_yes => root
-            .world
+        Ok(_response_yes) if _response_yes => world
             .resource_scope(|world, scene_manager: Mut<SceneManager>| {
                 scene_manager.save_scene(world)
             })
@@ -245,13 +332,13 @@ fn save_scene(root: &mut GameRoot) -> Result<(), String> {
     }
 }
 
-fn load_scene(root: &mut GameRoot) -> Result<(), String> {
+fn load_scene(world: &mut World) -> Result<(), String> {
     let path = match Text::new("Path of scene >").prompt() {
         Ok(ok) => PathBuf::from(ok),
         Err(err) => return Err(format!("Aborted loading scene [{}]", err.to_string())),
     };
 
-    root.world.resource_scope(
+    world.resource_scope(
         |world, mut scene_manager: Mut<SceneManager>| -> Result<(), String> {
             scene_manager.target_scene = Some(
                 scene_manager
@@ -264,8 +351,108 @@ fn load_scene(root: &mut GameRoot) -> Result<(), String> {
     )
 }
 
-fn unload_scene(root: &mut GameRoot) -> Result<(), String> {
-    root.world.resource_scope(
+fn load_scene_async(world: &mut World) -> Result<(), String> {
+    let path = match Text::new("Path of scene >").prompt() {
+        Ok(ok) => PathBuf::from(ok),
+        Err(err) => return Err(format!("Aborted loading scene [{}]", err.to_string())),
+    };
+
+    engine::scene::load_scene_async(world, path).map_err(|err| err.to_string())
+}
+
+fn export_scene(world: &mut World) -> Result<(), String> {
+    let path = Text::new("Path of exported scene >")
+        .with_help_message("The format is picked by the extension: .bin, .json or .ron")
+        .prompt()
+        .map_err(|err| format!("Aborted exporting scene [{}]", err.to_string()))?;
+
+    world
+        .resource_scope(|world, scene_manager: Mut<SceneManager>| {
+            scene_manager.export_scene(world, path.as_ref())
+        })
+        .map_err(|err| err.to_string())?;
+
+    println!("Exported scene to {}", path);
+
+    Ok(())
+}
+
+fn convert_scene(_world: &mut World) -> Result<(), String> {
+    let from = Text::new("Path of scene to convert >")
+        .prompt()
+        .map_err(|err| format!("Aborted converting scene [{}]", err.to_string()))?;
+
+    let to = Text::new("Path of converted scene >")
+        .prompt()
+        .map_err(|err| format!("Aborted converting scene [{}]", err.to_string()))?;
+
+    scene::convert_scene_file(from.as_ref(), to.as_ref()).map_err(|err| err.to_string())?;
+
+    println!("Converted {} to {}", from, to);
+
+    Ok(())
+}
+
+fn spawn_prefab(world: &mut World) -> Result<(), String> {
+    let path = Text::new("Path of prefab >")
+        .prompt()
+        .map_err(|err| format!("Aborted spawning prefab [{}]", err.to_string()))?;
+
+    let name = Text::new("Name of the instance >")
+        .prompt()
+        .map_err(|err| format!("Aborted spawning prefab [{}]", err.to_string()))?;
+
+    world
+        .resource_scope(|world, scene_manager: Mut<SceneManager>| {
+            scene_manager.spawn_prefab(world, path.as_ref(), &name)
+        })
+        .map_err(|err| err.to_string())?;
+
+    println!("Spawned {} as {}", path, name);
+
+    Ok(())
+}
+
+fn save_prefab(world: &mut World) -> Result<(), String> {
+    world.resource_scope(
+        |world: &mut World, res: Mut<SceneManager>| -> Result<(), String> {
+            let scene = world
+                .get::<Scene>(res.target_scene.ok_or("No target scene found!")?)
+                .ok_or("No scene component found for the current target scene!")?;
+
+            let entity_names = scene
+                .get_entities()
+                .iter()
+                .filter_map(|entity| world.get::<SceneData>(*entity))
+                .map(|scene_data| scene_data.entity_name.clone())
+                .collect::<Vec<String>>();
+
+            let name = Select::new("Root entity of the prefab >", entity_names)
+                .prompt()
+                .map_err(|err| format!("Aborted saving prefab [{}]", err.to_string()))?;
+
+            let path = Text::new("Path of prefab >")
+                .with_help_message("The format is picked by the extension: .json, .ron or .bin")
+                .prompt()
+                .map_err(|err| format!("Aborted saving prefab [{}]", err.to_string()))?;
+
+            let entity = scene
+                .get_entity(world, name)
+                .ok_or("The entity does not exist!")?;
+
+            Prefab::from_entity(world, entity, &res.type_registry)
+                .and_then(|prefab| prefab.save(path.as_ref()))
+                .map_err(|err| err.to_string())?;
+
+            println!("Saved prefab to {}", path);
+
+            Ok(())
+        },
+    )
+}
+
+fn unload_scene(world: &mut World) -> Result<(), String> {
+    world.resource_scope(
         |world, mut scene_manager: Mut<SceneManager>| -> Result<(), String> {
             scene_manager
                 .unload_scene(world)
@@ -276,9 +463,8 @@ fn unload_scene(root: &mut GameRoot) -> Result<(), String> {
     )
 }
 
-fn reload_scene(root: &mut GameRoot) -> Result<(), String> {
-    let path = root
-        .world
+fn reload_scene(world: &mut World) -> Result<(), String> {
+    let path = world
         .resource_scope(
             |world, scene_manager: Mut<SceneManager>| -> Option<PathBuf> {
                 world
@@ -291,9 +477,9 @@ fn reload_scene(root: &mut GameRoot) -> Result<(), String> {
             SceneError::NoTargetScene.to_string() + " [Error while getting path from target scene]",
         )?;
 
-    unload_scene(root)?;
+    unload_scene(world)?;
 
-    root.world.resource_scope(
+    world.resource_scope(
         |world, mut scene_manager: Mut<SceneManager>| -> Result<(), String> {
             scene_manager.target_scene = Some(
                 scene_manager
@@ -308,23 +494,23 @@ fn reload_scene(root: &mut GameRoot) -> Result<(), String> {
     )
 }
 
-fn new_scene(root: &mut GameRoot) -> Result<(), String> {
+fn new_scene(world: &mut World) -> Result<(), String> {
     let name = Text::new("What will be the name of the scene? >").prompt();
     let name = match name {
         Ok(ok) => ok,
         Err(err) => return Err(err.to_string()),
     };
-    root.world
+    world
         .resource_scope(|world: &mut World, mut resource: Mut<SceneManager>| {
             resource.new_scene(world, name)
         })
         .map_err(|err| err.to_string())
 }
 
-fn change_target_scene(root: &mut GameRoot) -> Result<(), String> {
+fn change_target_scene(world: &mut World) -> Result<(), String> {
     let op = |err: InquireError| -> String { err.to_string() };
 
-    root.world.resource_scope(
+    world.resource_scope(
         |_world: &mut World, mut resource: Mut<SceneManager>| -> Result<(), String> {
             let target = Select::new("message", resource.current_scenes.keys().collect())
                 .prompt()
@@ -337,8 +523,8 @@ fn change_target_scene(root: &mut GameRoot) -> Result<(), String> {
     )
 }
 
-fn list_scenes(root: &mut GameRoot) -> Result<(), String> {
-    root.world.resource_scope(
+fn list_scenes(world: &mut World) -> Result<(), String> {
+    world.resource_scope(
         |_world: &mut World, res: Mut<SceneManager>| -> Result<(), String> {
             let scenestrings = res.current_scenes.keys().collect::<Vec<&String>>();
             if scenestrings.len() == 0 {
@@ -357,8 +543,8 @@ fn list_scenes(root: &mut GameRoot) -> Result<(), String> {
 
 // Entities
 
-fn new_entity(root: &mut GameRoot) -> Result<(), String> {
-    root.world.resource_scope(
+fn new_entity(world: &mut World) -> Result<(), String> {
+    world.resource_scope(
         |world: &mut World, res: Mut<SceneManager>| -> Result<(), String> {
             let name = inquire::Text::new("Name of the entity >")
                 .prompt()
@@ -384,8 +570,8 @@ fn new_entity(root: &mut GameRoot) -> Result<(), String> {
     )
 }
 
-fn list_entities(root: &mut GameRoot) -> Result<(), String> {
-    root.world.resource_scope(
+fn list_entities(world: &mut World) -> Result<(), String> {
+    world.resource_scope(
         |world: &mut World, res: Mut<SceneManager>| -> Result<(), String> {
             let scene = world
                 .get::<Scene>(res.target_scene.ok_or("No target scene found!")?)
@@ -413,11 +599,11 @@ fn list_entities(root: &mut GameRoot) -> Result<(), String> {
     )
 }
 
-fn scoop_entities(root: &mut GameRoot) -> Result<(), String> {
+fn scoop_entities(world: &mut World) -> Result<(), String> {
     let mut entities_to_scoop: Vec<Entity> = Vec::new();
     let mut target_scene: Option<Entity> = None;
 
-    root.world
+    world
         .resource_scope(
             |world: &mut World, res: Mut<SceneManager>| -> Result<(), SceneError> {
                 let scene_id = world
@@ -444,7 +630,7 @@ fn scoop_entities(root: &mut GameRoot) -> Result<(), String> {
         .map_err(|err| err.to_string())?;
 
     for entity in entities_to_scoop {
-        add_entity_to_scene(&mut root.world, target_scene.unwrap(), entity, None)
+        add_entity_to_scene(world, target_scene.unwrap(), entity, None)
             .map_err(|err| format!("Failed to scoop entity [{}]", err.to_string()))?;
     }
 
@@ -453,8 +639,8 @@ fn scoop_entities(root: &mut GameRoot) -> Result<(), String> {
 
 // Components
 
-fn add_component(root: &mut GameRoot) -> Result<(), String> {
-    root.world
+fn add_component(world: &mut World) -> Result<(), String> {
+    world
         .resource_scope(
             |world: &mut World, res: Mut<SceneManager>| -> Result<(), SceneError> {
                 let scene = world
@@ -569,8 +755,8 @@ fn add_component(root: &mut GameRoot) -> Result<(), String> {
         .map_err(|err| err.to_string())
 }
 
-fn list_components(root: &mut GameRoot) -> Result<(), String> {
-    root.world
+fn list_components(world: &mut World) -> Result<(), String> {
+    world
         .resource_scope(|world, res: Mut<SceneManager>| -> Result<(), SceneError> {
             let scene = world
                 .get::<Scene>(res.target_scene.ok_or(SceneError::NoTargetScene)?)
@@ -606,9 +792,9 @@ fn list_components(root: &mut GameRoot) -> Result<(), String> {
         .map_err(|err| err.to_string())
 }
 
-fn log(root: &mut GameRoot) -> Result<(), String> {
-    root.world.resource::<engine::Input>().log();
-    root.world.resource::<scene::SceneManager>().log();
-    root.world.resource::<engine::GgezInterface>().log();
+fn log(world: &mut World) -> Result<(), String> {
+    world.resource::<engine::Input>().log();
+    world.resource::<scene::SceneManager>().log();
+    world.resource::<engine::GgezInterface>().log();
     Ok(())
 }
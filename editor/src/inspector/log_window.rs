@@ -0,0 +1,83 @@
+use engine::logging::LogHistory;
+use log::Level;
+
+/// Shows the most recent records kept in the [`LogHistory`], newest at the bottom.
+#[derive(Debug)]
+pub struct LogViewTab {
+    /// The least important level that is still shown
+    min_level: Level,
+    search: String,
+}
+
+impl Default for LogViewTab {
+    fn default() -> Self {
+        Self {
+            min_level: Level::Info,
+            search: String::new(),
+        }
+    }
+}
+
+impl engine::editor::EditorTab for LogViewTab {
+    fn name() -> &'static str
+    where
+        Self: Sized,
+    {
+        "LogViewTab"
+    }
+
+    fn display_name(&self) -> String {
+        "Log".to_string()
+    }
+
+    fn ui(
+        &mut self,
+        window_state: &mut engine::editor::WindowState,
+        ui: &mut egui::Ui,
+    ) -> Option<engine::editor::TabResponse> {
+        let Some(history) = window_state.world_ref().get_resource::<LogHistory>() else {
+            ui.label("No log history found.");
+            return None;
+        };
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("log_level")
+                .selected_text(self.min_level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in Level::iter() {
+                        ui.selectable_value(&mut self.min_level, level, level.as_str());
+                    }
+                });
+
+            ui.text_edit_singleline(&mut self.search);
+
+            if ui.button("Clear").clicked() {
+                history.clear();
+            }
+        });
+
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .stick_to_bottom(true)
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for record in history.records() {
+                    if record.level > self.min_level || !record.message.contains(&self.search) {
+                        continue;
+                    }
+
+                    let color = match record.level {
+                        Level::Error => egui::Color32::LIGHT_RED,
+                        Level::Warn => egui::Color32::YELLOW,
+                        _ => ui.visuals().text_color(),
+                    };
+
+                    ui.colored_label(color, record.to_string())
+                        .on_hover_text(&record.target);
+                }
+            });
+
+        None
+    }
+}
@@ -9,8 +9,9 @@ use egui::{Pos2, Ui};
 use egui_dock::{DockArea, DockState, Style, SurfaceIndex};
 use engine::editor::*;
 use engine::editor::{EditorTab, InspectableAsField};
+use engine::frame_advance::FrameAdvance;
 use engine::scene::{ReflectTestSuperTrait, SceneData, SceneManager, TestSuperTrait};
-use engine::{GgezInterface, Input};
+use engine::{GgezInterface, Input, Time};
 use ggez::graphics::{Canvas, DrawParam};
 use std::any::{Any, TypeId};
 use std::collections::{HashMap, HashSet};
@@ -22,6 +23,7 @@ pub mod entity_view;
 pub mod field_view;
 pub mod game_view;
 pub mod inspector_view;
+pub mod log_window;
 pub mod scene_window;
 
 type TabResponseTuple = (TabResponse, String, egui::Id);
@@ -102,6 +104,8 @@ pub fn update_windows<'a>(world: &mut World) {
                 ui.checkbox(&mut editor_ui.window_state.debug_mode, "Debug mode");
 
                 ui.add(egui::DragValue::new(&mut editor_ui.window_state.z));
+
+                frame_advance_ui(ui, world_scoped);
             });
         });
 
@@ -139,6 +143,31 @@ pub fn update_windows<'a>(world: &mut World) {
     });
 }
 
+/// Shows the current tick, along with buttons for pausing and stepping through ticks one at a time.
+fn frame_advance_ui(ui: &mut Ui, world: &mut World) {
+    ui.separator();
+
+    ui.label(format!("Tick {}", world.resource::<Time>().ticks()));
+
+    let paused = FrameAdvance::is_paused(world.resource::<GgezInterface>());
+    let mut frame_advance = world.resource_mut::<FrameAdvance>();
+
+    if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
+        frame_advance.request_toggle_pause();
+    }
+
+    if ui.button("Step").clicked() {
+        frame_advance.request_steps(1);
+    }
+
+    let step_many_count = frame_advance.step_many_count;
+    if ui.button(format!("Step {}", step_many_count)).clicked() {
+        frame_advance.request_steps(step_many_count);
+    }
+
+    ui.add(egui::DragValue::new(&mut frame_advance.step_many_count).clamp_range(1..=600));
+}
+
 fn file_managment_ui(ui: &mut Ui) {
     ui.selectable_label(false, "Save scene");
 }
@@ -150,6 +179,7 @@ fn window_managment_ui(ui: &mut Ui, editor_resource: &mut Mut<EditorGUI>) {
     // add_tab_label_ui::<game_view::GameView>(ui, editor_resource); // on hold until I can fix it later
     add_tab_label_ui::<scene_window::SceneEditorTab>(ui, editor_resource);
     add_tab_label_ui::<asset_window::AssetViewTab>(ui, editor_resource);
+    add_tab_label_ui::<log_window::LogViewTab>(ui, editor_resource);
     add_tab_label_ui::<components::editor_windows::MeshEditorTab>(ui, editor_resource);
     // ui.menu_button("Components", |ui| {
     // });
@@ -106,7 +106,7 @@ impl EditorTab for SceneEditorTab {
                     };
 
                     let file_dialog = rfd::FileDialog::new()
-                        .add_filter("json", &["json"])
+                        .add_filter("Scenes", &["json", "ron", "bin"])
                         .set_directory(&path)
                         .pick_file();
 
@@ -129,7 +129,7 @@ impl EditorTab for SceneEditorTab {
                             Some(some) => Some(some.to_owned()),
                             None => rfd::FileDialog::new()
                                 .set_directory(std::env::current_dir().unwrap())
-                                .add_filter("JSON", &["json"])
+                                .add_filter("Scenes", &["json", "ron", "bin"])
                                 .pick_file(),
                         })
                     else {
@@ -5,11 +5,13 @@ pub mod input_debugger;
 pub mod inspector;
 
 use bevy_ecs::prelude::*;
-use bevy_ecs::schedule::{ExecutorKind, LogLevel, ScheduleBuildSettings};
+use engine::crash::PanicHandler;
 use engine::scene::SceneManager;
-use engine::schedule::ScheduleTag;
+use engine::schedule::{
+    EngineSet, ScheduleBuilder, ScheduleDefinition, ScheduleExecutor, ScheduleTag,
+};
 use engine::GgezInterface;
-use engine::{EngineConfig, Input};
+use engine::{EngineConfig, EnginePlugin, Input};
 use input_debugger::InputDebugger;
 use log::trace;
 
@@ -18,6 +20,21 @@ use engine::editor::InspectableAsField;
 
 static DEBUG_ACTION_NAME: &str = "enabledebugmode";
 
+/// Adds the editor's resources and debug schedules on top of whatever game it's built alongside.
+///
+/// Should be listed after the game's own plugins, since the editor acts as a wrapper around the game.
+pub struct EditorPlugin;
+
+impl EnginePlugin for EditorPlugin {
+    fn build(&self, world: &mut World) {
+        init_editor_resources(world);
+        world.insert_resource(PanicHandler(console::panic_debug_cli));
+        add_editor_schedule_definitions(&mut world.resource_mut::<ScheduleBuilder>());
+
+        trace!("Created editor resources and initialized editor schedules");
+    }
+}
+
 pub fn init_editor_resources(world: &mut World) {
     world.insert_resource(InputDebugger::default());
 
@@ -29,72 +46,36 @@ pub fn init_editor_resources(world: &mut World) {
     world.insert_resource(editor_interface);
 }
 
-/// An initialization function for a standard [`ScheduleTag::DebugGUI`] schedule.
-pub fn debug_gui_schedule() -> Schedule {
-    let mut sched = Schedule::new(ScheduleTag::DebugGUI);
-    // Configuration block
-    sched
-        .set_build_settings(DEBUG_SETTINGS.clone())
-        .set_executor_kind(ExecutorKind::Simple)
-        .add_systems(inspector::update_windows);
-
-    // Systems block
-
-    sched
-}
-
-/// An initialization function for a standard [`ScheduleTag::DebugInit`] schedule.
-pub fn debug_init_schedule() -> Schedule {
-    let mut sched = Schedule::new(ScheduleTag::DebugInit);
-    // Configuration block
-    sched
-        .set_build_settings(DEBUG_SETTINGS.clone())
-        .set_executor_kind(ExecutorKind::Simple)
-        // .add_systems(())
-        ;
-
-    // Systems block
-
-    sched
-}
-
-pub fn debug_tick_schedule() -> Schedule {
-    let mut sched = Schedule::new(ScheduleTag::DebugTick);
-    // Configuration block
-    sched
-        .set_build_settings(DEBUG_SETTINGS.clone())
-        .set_executor_kind(ExecutorKind::Simple)
-        .add_systems((console::check_for_debug).chain());
-
-    // Systems block
-
-    sched
-}
-
-pub fn debug_frame_schedule() -> Schedule {
-    let mut sched = Schedule::new(ScheduleTag::DebugFrame);
-    // Configuration block
-    sched
-        .set_build_settings(DEBUG_SETTINGS.clone())
-        .set_executor_kind(ExecutorKind::Simple);
-
-    // Systems block
-    sched.add_systems(
-        (
-            // input_debugger::draw_debug_information,
-            inspector::game_view::draw_editor_views,
-            inspector::draw_editor_gui,
-        )
-            .chain(),
+/// Registers the editor's systems and places them into the debug schedules.
+///
+/// The schedules are built by the engine after the world is initialized, alongside the game's own schedules.
+pub fn add_editor_schedule_definitions(builder: &mut ScheduleBuilder) {
+    builder.register_system("update_windows", inspector::update_windows);
+    builder.register_system("check_for_debug", console::check_for_debug);
+    builder.register_system("draw_editor_views", inspector::game_view::draw_editor_views);
+    builder.register_system("draw_editor_gui", inspector::draw_editor_gui);
+
+    for tag in [
+        ScheduleTag::DebugGUI,
+        ScheduleTag::DebugInit,
+        ScheduleTag::DebugTick,
+        ScheduleTag::DebugFrame,
+    ] {
+        builder.add_definition(ScheduleDefinition::new(tag, ScheduleExecutor::Simple));
+    }
+
+    builder.add_to_schedule_unsorted(ScheduleTag::DebugGUI, "update_windows");
+
+    builder.add_to_schedule_unsorted(ScheduleTag::DebugTick, "check_for_debug");
+
+    builder.add_to_set(
+        ScheduleTag::DebugFrame,
+        EngineSet::Render,
+        "draw_editor_views",
+    );
+    builder.add_to_set(
+        ScheduleTag::DebugFrame,
+        EngineSet::Render,
+        "draw_editor_gui",
     );
-
-    sched
 }
-
-pub static DEBUG_SETTINGS: ScheduleBuildSettings = ScheduleBuildSettings {
-    ambiguity_detection: LogLevel::Warn,
-    hierarchy_detection: LogLevel::Warn,
-    use_shortnames: false,
-    report_sets: true,
-    auto_insert_apply_deferred: true,
-};
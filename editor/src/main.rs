@@ -1,43 +1,27 @@
 //! Creates an application for creating game data that will be serialized into the game binary itself
 //! Can also store data outside of the binary as specified
 
-use bevy_ecs::world::{Mut, World};
-use editor::input_debugger::InputDebugger;
-use editor::inspector::EditorGUI;
-use engine::EngineConfig;
+use editor::EditorPlugin;
+use engine::logging::LoggerConfig;
+use engine::{EngineConfig, EngineConfigError};
 use ggez::conf::WindowMode;
 
-pub static EDITOR_ENGINE_CONFIG: EngineConfig = EngineConfig {
-    external_scene_paths: &[game::INITIAL_SCENE],
-    scenes_folder: Some(game::SCENE_FOLDER),
-    world_init: init_editor_schedules,
-    ticks_per_second: game::ENGINE_CONFIG.ticks_per_second,
-    freeze_on_unfocus: false,
-    freeze_on_minimize: false,
-    run_debug_schedules: true,
-};
-
-// Add new resources here!
-pub fn init_editor_schedules(world: &mut World) {
-    // The editor essentially acts as a wrapper for the game itself, injecting it's own code into the game.
-    // Thus, we run all of the usual functions for the game, then we run our own stuff on top of it.
-    game::init_components_and_resources(world);
-
-    world.insert_resource(InputDebugger::default());
-
-    let editor_interface = world.resource_scope(
-        |world: &mut World, mut engine: Mut<engine::GgezInterface>| {
-            EditorGUI::new(engine.get_context_mut(), world)
-        },
-    );
-    world.insert_resource(editor_interface);
-
-    world.add_schedule(editor::debug_tick_schedule());
-    world.add_schedule(editor::debug_frame_schedule());
-    world.add_schedule(editor::debug_init_schedule());
-    world.add_schedule(editor::debug_gui_schedule());
-
-    log::trace!("Created editor resources and initialized editor schedules");
+/// Loads the game's [`EngineConfig`], so the editor opens the same scenes at the same tick rate,
+/// then changes the few settings the editor needs to be different.
+pub fn editor_engine_config() -> Result<EngineConfig, EngineConfigError> {
+    let mut config = game::engine_config()?.with_plugin(EditorPlugin);
+
+    config.freeze_on_unfocus = false;
+    config.freeze_on_minimize = false;
+    config.run_debug_schedules = true;
+    config.logger = LoggerConfig {
+        filters: "warn,wgpu_core=off,engine=debug,game=debug,editor=debug,components=debug"
+            .to_owned(),
+        file: Some("editor.log".to_owned()),
+        history_size: 1000,
+    };
+
+    Ok(config)
 }
 
 fn main() {
@@ -61,10 +45,12 @@ fn main() {
         .build()
         .expect("aieee, could not create ggez context!");
 
-    let mut root = engine::GameRoot::new(&mut context, &EDITOR_ENGINE_CONFIG)
-        .expect("could not build game root");
+    // The editor essentially acts as a wrapper for the game itself, injecting it's own code into the game.
+    // Thus, we add all of the usual plugins for the game, then we add our own stuff on top of it.
+    let config = editor_engine_config()
+        .unwrap_or_else(|err| panic!("could not load engine config! {}", err.to_string()));
 
-    editor::console::hook_emergency_panic_handler(&mut root);
+    let root = engine::GameRoot::new(&mut context, config).expect("could not build game root");
 
     ggez::event::run(context, event_loop, root);
 }
@@ -0,0 +1,459 @@
+//! Crash recovery, which saves every loaded scene when the engine panics so that no work is lost.
+//!
+//! When a panic escapes one of [`GameRoot`](crate::GameRoot)'s updates, every scene in [`SceneManager::current_scenes`] is serialized into
+//! a new timestamped folder inside of [`CrashRecoveryConfig::folder`], alongside a crash report holding the panic message, backtrace and recent log lines.
+//! The next time a [`GameRoot`](crate::GameRoot) is created, it offers to restore the most recent recovery.
+//!
+//! Nothing here ever prompts for input unless a terminal is attached, so a crash can't leave the process hanging on a prompt nobody can answer.
+
+use std::any::Any;
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::{Mut, World};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::logging::LogHistory;
+use crate::scene::{create_serializable_scene_data, Scene, SceneManager};
+
+/// The name of the crash report written into each recovery folder.
+pub static CRASH_REPORT_FILE_NAME: &str = "crash_report.txt";
+/// The name of the [`RecoveryManifest`] written into each recovery folder.
+pub static MANIFEST_FILE_NAME: &str = "manifest.json";
+/// Written into a recovery folder once it has been restored or dismissed, so it isn't offered again.
+pub static HANDLED_MARKER_FILE_NAME: &str = "handled";
+
+/// Settings for crash recovery.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashRecoveryConfig {
+    /// Whether loaded scenes should be saved when the engine panics.
+    pub enabled: bool,
+    /// The folder that recovery folders are created in.
+    pub folder: String,
+    /// How many of the most recent log lines are included in the crash report.
+    pub recent_log_lines: usize,
+}
+
+impl Default for CrashRecoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            folder: "recovery".to_owned(),
+            recent_log_lines: 200,
+        }
+    }
+}
+
+/// Everything known about a panic, gathered by the panic hook.
+#[derive(Debug, Clone)]
+pub struct PanicReport {
+    pub message: String,
+    /// The file, line and column the panic happened at, if known
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: String,
+}
+
+impl Display for PanicReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "thread '{}' panicked at {}:\n{}",
+            self.thread.as_deref().unwrap_or("<unnamed>"),
+            self.location.as_deref().unwrap_or("<unknown>"),
+            self.message
+        )
+    }
+}
+
+/// Called with the world after recovery files have been written, but only while a terminal is attached.
+///
+/// The editor uses this to open its debug console, so that data can be inspected before the process exits.
+#[derive(Resource, Clone, Copy)]
+pub struct PanicHandler(pub fn(&mut World, &PanicReport));
+
+/// Lists what's inside of a recovery folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryManifest {
+    /// Seconds since the unix epoch
+    pub crashed_at: u64,
+    pub scenes: Vec<RecoveredScene>,
+}
+
+/// A scene saved into a recovery folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveredScene {
+    pub name: String,
+    /// The file name of the saved scene, inside of the recovery folder
+    pub file: String,
+    /// Where the scene was originally loaded from or saved to, which it is restored back to
+    pub original_path: Option<PathBuf>,
+}
+
+static LAST_PANIC: Mutex<Option<PanicReport>> = Mutex::new(None);
+
+/// Installs a panic hook that records the panic message and backtrace for crash reports, then runs the previous hook.
+///
+/// Only installs once, no matter how many times it is called.
+pub(crate) fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+
+    INSTALLED.call_once(|| {
+        let previous_hook = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |panic_info| {
+            let report = PanicReport {
+                message: payload_message(panic_info.payload()),
+                location: panic_info.location().map(|location| location.to_string()),
+                thread: std::thread::current().name().map(str::to_owned),
+                backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            };
+
+            *LAST_PANIC
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(report);
+
+            previous_hook(panic_info);
+        }));
+    });
+}
+
+/// Takes the report recorded by the panic hook, or makes a bare one out of the panic payload if there isn't one.
+pub(crate) fn take_panic_report(payload: &(dyn Any + Send)) -> PanicReport {
+    LAST_PANIC
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take()
+        .unwrap_or_else(|| PanicReport {
+            message: payload_message(payload),
+            location: None,
+            thread: None,
+            backtrace: "<no backtrace captured>".to_owned(),
+        })
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_owned()
+    }
+}
+
+/// Returns true if a user is able to answer prompts.
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+}
+
+/// Saves every loaded scene and a crash report into a new recovery folder, returning the folder's path.
+///
+/// Scenes that fail to serialize are skipped, and the reason is written into the crash report instead.
+pub fn write_recovery(
+    world: &mut World,
+    config: &CrashRecoveryConfig,
+    report: &PanicReport,
+) -> std::io::Result<PathBuf> {
+    let crashed_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let folder = unique_folder(Path::new(&config.folder), &format_timestamp(crashed_at));
+    std::fs::create_dir_all(&folder)?;
+
+    let mut manifest = RecoveryManifest {
+        crashed_at,
+        scenes: Vec::new(),
+    };
+    let mut failures = Vec::new();
+
+    // the scene manager may have been taken out of the world by a resource scope that panicked
+    if world.contains_resource::<SceneManager>() {
+        world.resource_scope(|world, manager: Mut<SceneManager>| {
+            for (name, entity) in &manager.current_scenes {
+                let original_path = world
+                    .get::<Scene>(*entity)
+                    .and_then(|scene| scene.save_data_path().cloned());
+
+                let data =
+                    match create_serializable_scene_data(world, &manager.type_registry, *entity) {
+                        Ok(data) => data,
+                        Err(err) => {
+                            failures.push(format!("{}: {}", name, err));
+                            continue;
+                        }
+                    };
+
+                let file = unique_file_name(&manifest.scenes, &sanitize_file_name(name));
+
+                let result = std::fs::File::create(folder.join(&file))
+                    .map_err(|err| err.to_string())
+                    .and_then(|writer| {
+                        serde_json::to_writer_pretty(writer, &data).map_err(|err| err.to_string())
+                    });
+
+                match result {
+                    Ok(()) => manifest.scenes.push(RecoveredScene {
+                        name: name.clone(),
+                        file,
+                        original_path,
+                    }),
+                    Err(err) => failures.push(format!("{}: {}", name, err)),
+                }
+            }
+        });
+    } else {
+        failures.push("The SceneManager was not in the world when the panic happened".to_owned());
+    }
+
+    std::fs::write(
+        folder.join(MANIFEST_FILE_NAME),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+
+    let recent_logs = world
+        .get_resource::<LogHistory>()
+        .map(|history| {
+            history
+                .recent(config.recent_log_lines)
+                .iter()
+                .map(|record| record.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+
+    let crash_report = format!(
+        "{}\n\nRecovered scenes: {}\nFailed scenes:\n{}\n\nBacktrace:\n{}\n\nRecent logs:\n{}\n",
+        report,
+        manifest
+            .scenes
+            .iter()
+            .map(|scene| scene.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        failures.join("\n"),
+        report.backtrace,
+        recent_logs
+    );
+
+    std::fs::write(folder.join(CRASH_REPORT_FILE_NAME), crash_report)?;
+
+    Ok(folder)
+}
+
+/// Returns every recovery folder that hasn't been restored or dismissed yet, oldest first.
+pub fn pending_recoveries(config: &CrashRecoveryConfig) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(&config.folder) else {
+        return Vec::new();
+    };
+
+    let mut folders = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.join(MANIFEST_FILE_NAME).is_file() && !path.join(HANDLED_MARKER_FILE_NAME).exists()
+        })
+        .collect::<Vec<_>>();
+
+    // folder names start with a sortable timestamp
+    folders.sort();
+    folders
+}
+
+/// Reads the manifest of a recovery folder.
+pub fn read_manifest(folder: &Path) -> std::io::Result<RecoveryManifest> {
+    let file = std::fs::read_to_string(folder.join(MANIFEST_FILE_NAME))?;
+
+    serde_json::from_str(&file).map_err(std::io::Error::from)
+}
+
+/// Loads every scene in a recovery folder, replacing any loaded scene with the same name.
+///
+/// Restored scenes keep their original save path, so saving them overwrites the file they were originally loaded from.
+pub fn restore_recovery(world: &mut World, folder: &Path) -> Result<Vec<String>, String> {
+    let manifest = read_manifest(folder).map_err(|err| err.to_string())?;
+
+    let mut restored = Vec::new();
+
+    world.resource_scope::<SceneManager, Result<(), String>>(|world, mut manager| {
+        for recovered in &manifest.scenes {
+            if manager.current_scenes.contains_key(&recovered.name) {
+                if let Err(err) = manager.unload_scene_by_name(world, &recovered.name) {
+                    warn!(
+                        "Could not unload scene {} before restoring it: {}",
+                        recovered.name, err
+                    );
+                }
+            }
+
+            let entity = manager
+                .load_scene(world, folder.join(&recovered.file))
+                .map_err(|err| format!("{}: {}", recovered.name, err))?;
+
+            world
+                .get_mut::<Scene>(entity)
+                .expect("a scene that was just loaded should have a scene component")
+                .save_data_path = recovered.original_path.clone();
+
+            restored.push(recovered.name.clone());
+        }
+
+        Ok(())
+    })?;
+
+    mark_handled(folder, "restored");
+
+    Ok(restored)
+}
+
+/// Marks a recovery folder so that it isn't offered again. The files are kept.
+pub fn mark_handled(folder: &Path, reason: &str) {
+    if let Err(err) = std::fs::write(folder.join(HANDLED_MARKER_FILE_NAME), reason) {
+        error!(
+            "Could not mark recovery folder {} as {}: {}",
+            folder.display(),
+            reason,
+            err
+        );
+    }
+}
+
+/// Offers to restore the most recent pending recovery. Older pending recoveries are dismissed, but left on disk.
+///
+/// Without a terminal attached, pending recoveries are only logged and are offered again next launch.
+pub(crate) fn offer_restore(world: &mut World, config: &CrashRecoveryConfig) {
+    let mut pending = pending_recoveries(config);
+
+    let Some(latest) = pending.pop() else {
+        return;
+    };
+
+    if !is_interactive() {
+        warn!(
+            "Found unrestored crash recovery at {}. Launch from a terminal to restore it.",
+            latest.display()
+        );
+        return;
+    }
+
+    for older in pending {
+        info!("Dismissing older crash recovery {}", older.display());
+        mark_handled(&older, "superseded");
+    }
+
+    let scene_names = match read_manifest(&latest) {
+        Ok(manifest) => manifest
+            .scenes
+            .iter()
+            .map(|scene| scene.name.clone())
+            .collect::<Vec<_>>()
+            .join(", "),
+        Err(err) => {
+            error!(
+                "Could not read crash recovery {}: {}",
+                latest.display(),
+                err
+            );
+            mark_handled(&latest, "unreadable");
+            return;
+        }
+    };
+
+    let prompt = format!(
+        "The engine crashed last time. Restore [{}] from {}? >",
+        scene_names,
+        latest.display()
+    );
+
+    match inquire::Confirm::new(&prompt).with_default(true).prompt() {
+        Ok(true) => match restore_recovery(world, &latest) {
+            Ok(restored) => info!("Restored scenes {:?} from crash recovery", restored),
+            Err(err) => error!("Could not restore crash recovery: {}", err),
+        },
+        Ok(false) => mark_handled(&latest, "dismissed"),
+        Err(err) => warn!("Skipped restoring crash recovery [{}]", err),
+    }
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|char| match char {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            other => other,
+        })
+        .collect()
+}
+
+/// Picks a scene file name that no scene in the manifest uses yet, since different scene names can sanitize to the same file name.
+///
+/// Compared without case, as the recovery folder may be on a case insensitive file system.
+fn unique_file_name(scenes: &[RecoveredScene], name: &str) -> String {
+    let is_used = |file: &str| {
+        scenes
+            .iter()
+            .any(|scene| scene.file.eq_ignore_ascii_case(file))
+    };
+
+    let mut file = format!("{}.json", name);
+    let mut suffix = 1;
+
+    while is_used(&file) {
+        file = format!("{}_{}.json", name, suffix);
+        suffix += 1;
+    }
+
+    file
+}
+
+/// Picks a folder that doesn't exist yet, in case two crashes happen within the same second.
+fn unique_folder(parent: &Path, name: &str) -> PathBuf {
+    let mut folder = parent.join(name);
+    let mut suffix = 1;
+
+    while folder.exists() {
+        folder = parent.join(format!("{}_{}", name, suffix));
+        suffix += 1;
+    }
+
+    folder
+}
+
+/// Formats seconds since the unix epoch as `YYYY-MM-DD_HH-MM-SS` in UTC, which sorts in chronological order.
+pub(crate) fn format_timestamp(unix_seconds: u64) -> String {
+    let days = (unix_seconds / 86_400) as i64;
+    let seconds_of_day = unix_seconds % 86_400;
+
+    // converts days since the epoch into a civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
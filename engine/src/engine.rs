@@ -3,16 +3,21 @@
 use bevy_ecs::system::Resource;
 use ggez::graphics::Canvas;
 
+use crate::events::FreezeChanged;
 use crate::{freeze::FreezeType, EngineConfig};
 
+/// The drawable size reported by [`GgezInterface::drawable_size`] while running headless. Matches [`ggez`]'s default window size.
+pub const HEADLESS_DRAWABLE_SIZE: (f32, f32) = (800.0, 600.0);
+
 /// A basic resource designed for holding information and sharing access to [`ggez`] through [`bevy_ecs`]'s resource system.
 ///
 /// # Fields
 ///
 /// * `context_ptr` - private raw pointer pointing to the current [`Context`] for the given schedule.
+///   Null when the engine is running headless, see [`GgezInterface::is_headless`].
 ///
 /// * `current_canvas` - private optional holding the current [`Canvas`].
-/// Holds [`None`] if operating during an `Update` frame, or holds `Some(Canvas)` if operating during a `Draw` frame.
+///   Holds [`None`] if operating during an `Update` frame, or holds `Some(Canvas)` if operating during a `Draw` frame.
 
 #[derive(Debug, Resource)]
 pub struct GgezInterface
@@ -28,7 +33,17 @@ where
     /// Whether debug functionality should be enabled or not.
     pub debug_mode: bool,
 
-    pub freeze_mode: FreezeType,
+    /// The freeze state currently in effect. Change it through [`GgezInterface::push_freeze`] and [`GgezInterface::pop_freeze`].
+    freeze_mode: FreezeType,
+
+    /// Previous freeze states, which are returned to as the current one is popped.
+    freeze_stack: Vec<FreezeType>,
+
+    /// Changes to the freeze state in effect that haven't been sent as [`FreezeChanged`] events yet.
+    freeze_changes: Vec<FreezeChanged>,
+
+    /// Set while frame-advance steps a single tick through [`FreezeType::DEBUG`], see [`GgezInterface::freeze_mode`].
+    pub(crate) debug_step: bool,
 
     engine_config: EngineConfig,
 }
@@ -44,10 +59,35 @@ impl GgezInterface {
             context_ptr,
             debug_mode: false,
             freeze_mode: FreezeType::NONE,
+            freeze_stack: Vec::new(),
+            freeze_changes: Vec::new(),
+            debug_step: false,
             engine_config,
             error_log: Vec::new(),
         }
     }
+
+    /// Creates an interface without a [`ggez::Context`], used by [`GameRoot::new_headless`](crate::GameRoot::new_headless).
+    ///
+    /// Any system that calls [`get_context`](GgezInterface::get_context) will panic while headless, so prefer [`try_get_context`](GgezInterface::try_get_context) in code that should also run in tests.
+    pub(crate) fn new_headless(engine_config: EngineConfig) -> Self {
+        Self {
+            current_canvas: None,
+            context_ptr: std::ptr::null_mut(),
+            debug_mode: false,
+            freeze_mode: FreezeType::NONE,
+            freeze_stack: Vec::new(),
+            freeze_changes: Vec::new(),
+            debug_step: false,
+            engine_config,
+            error_log: Vec::new(),
+        }
+    }
+
+    /// Returns true if there is no [`ggez::Context`] attached, which is the case when running through [`GameRoot::new_headless`](crate::GameRoot::new_headless).
+    pub fn is_headless(&self) -> bool {
+        self.context_ptr.is_null()
+    }
     /// Returns a reference to the current canvas [`ggez`] will operate on.
     pub fn get_canvas(&self) -> Option<&Canvas> {
         self.current_canvas.as_ref()
@@ -85,7 +125,7 @@ impl GgezInterface {
     ///
     /// If it returns `true`, the tick schedule will not be run, but delta will still be updated accordingly.
     pub fn is_freeze_frame(&self) -> bool {
-        match self.freeze_mode {
+        match self.freeze_mode() {
             FreezeType::NONE => false,
             FreezeType::UNFOCUSED => self.engine_config.freeze_on_unfocus,
             FreezeType::MINIMIZED => self.engine_config.freeze_on_minimize,
@@ -96,6 +136,128 @@ impl GgezInterface {
         }
     }
 
+    /// Returns the freeze state currently in effect.
+    ///
+    /// While frame-advance is stepping a tick, the [`FreezeType::DEBUG`] state stays on the stack but this returns the state underneath it,
+    /// so that the stepped tick runs the same systems it would have without the debug pause.
+    pub fn freeze_mode(&self) -> &FreezeType {
+        match &self.freeze_mode {
+            FreezeType::DEBUG(_) if self.debug_step => {
+                self.freeze_stack.last().unwrap_or(&FreezeType::NONE)
+            }
+            freeze_mode => freeze_mode,
+        }
+    }
+
+    /// Returns true if the given freeze variant is either in effect or waiting further down the stack.
+    pub fn has_freeze(&self, freeze: &FreezeType) -> bool {
+        self.freeze_mode.variant_eq(freeze)
+            || self
+                .freeze_stack
+                .iter()
+                .any(|other| other.variant_eq(freeze))
+    }
+
+    /// Makes `freeze` the current freeze state, keeping the previous state on a stack to return to later.
+    ///
+    /// Pushing [`FreezeType::IMPACT`] is how gameplay triggers hitstop, as it pops itself once it runs out of ticks.
+    ///
+    /// While the game is paused by [`FreezeType::DEBUG`], `freeze` goes underneath it instead, so the game stays paused until frame-advance is left,
+    /// and the change is recorded as if the debug pause weren't there.
+    pub fn push_freeze(&mut self, freeze: FreezeType) {
+        if matches!(self.freeze_mode, FreezeType::DEBUG(_))
+            && !matches!(freeze, FreezeType::DEBUG(_))
+        {
+            let previous = self.freeze_stack.last().cloned().unwrap_or_default();
+            self.record_freeze_change(previous, freeze.clone());
+            self.freeze_stack.push(freeze);
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.freeze_mode, freeze);
+        self.record_freeze_change(previous.clone(), self.freeze_mode.clone());
+        self.freeze_stack.push(previous);
+    }
+
+    /// Returns to the previous freeze state, and returns the state that was just removed.
+    ///
+    /// If there are no previous states, the freeze state becomes [`FreezeType::NONE`].
+    pub fn pop_freeze(&mut self) -> FreezeType {
+        let previous = self.freeze_stack.pop().unwrap_or_default();
+        let removed = std::mem::replace(&mut self.freeze_mode, previous);
+        self.record_freeze_change(removed.clone(), self.freeze_mode.clone());
+        removed
+    }
+
+    /// Removes the most recent freeze state of the same variant as `freeze`, whether it's in effect or further down the stack.
+    ///
+    /// Returns the removed state, or [`None`] if no state of that variant was found.
+    pub fn remove_freeze(&mut self, freeze: &FreezeType) -> Option<FreezeType> {
+        if self.freeze_mode.variant_eq(freeze) {
+            return Some(self.pop_freeze());
+        }
+
+        let index = self
+            .freeze_stack
+            .iter()
+            .rposition(|other| other.variant_eq(freeze))?;
+
+        Some(self.freeze_stack.remove(index))
+    }
+
+    /// Pauses all game logic by pushing [`FreezeType::DEBUG`], which holds onto the state it replaced.
+    pub fn enter_debug_freeze(&mut self) {
+        let previous = Box::new(self.freeze_mode.clone());
+        self.push_freeze(FreezeType::DEBUG(previous));
+    }
+
+    /// Pops [`FreezeType::DEBUG`] if it's the current state, returning true if it was.
+    pub fn exit_debug_freeze(&mut self) -> bool {
+        if let FreezeType::DEBUG(_) = self.freeze_mode {
+            self.pop_freeze();
+            return true;
+        }
+        false
+    }
+
+    fn record_freeze_change(&mut self, previous: FreezeType, current: FreezeType) {
+        self.freeze_changes
+            .push(FreezeChanged { previous, current });
+    }
+
+    /// Takes every freeze state change since the last call, to be sent as [`FreezeChanged`] events.
+    pub(crate) fn drain_freeze_changes(&mut self) -> Vec<FreezeChanged> {
+        std::mem::take(&mut self.freeze_changes)
+    }
+
+    /// Counts down [`FreezeType::IMPACT`] at the start of a tick, popping it instead once it has no ticks left.
+    ///
+    /// Since the countdown happens before the tick runs, an impact pushed by a system doesn't count down on the tick that pushed it,
+    /// so `IMPACT(n)` freezes exactly `n` ticks.
+    /// While frame-advance is stepping a tick, the impact underneath the debug pause counts down instead.
+    pub(crate) fn tick_freeze(&mut self) {
+        if self.debug_step && matches!(self.freeze_mode, FreezeType::DEBUG(_)) {
+            if let Some(FreezeType::IMPACT(ticks)) = self.freeze_stack.last_mut() {
+                if *ticks == 0 {
+                    let removed = self.freeze_stack.pop().unwrap_or_default();
+                    let current = self.freeze_mode().clone();
+                    self.record_freeze_change(removed, current);
+                } else {
+                    *ticks -= 1;
+                }
+            }
+            return;
+        }
+
+        if let FreezeType::IMPACT(ticks) = &mut self.freeze_mode {
+            if *ticks == 0 {
+                self.pop_freeze();
+            } else {
+                *ticks -= 1;
+            }
+        }
+    }
+
     /// Returns a reference to the value that `self.context_ptr` points to.
     /// Panics if `self.context_ptr` is null or invalid, which should never be the case in normal scenarios. If it is, investigate immediately.
     pub fn get_context(&self) -> &ggez::Context {
@@ -111,6 +273,26 @@ impl GgezInterface {
         }
     }
 
+    /// Returns a reference to the current [`ggez::Context`], or [`None`] if the engine is running headless.
+    pub fn try_get_context(&self) -> Option<&ggez::Context> {
+        unsafe { self.context_ptr.as_ref() }
+    }
+
+    /// Returns a mutable reference to the current [`ggez::Context`], or [`None`] if the engine is running headless.
+    pub fn try_get_context_mut(&mut self) -> Option<&mut ggez::Context> {
+        unsafe { self.context_ptr.as_mut() }
+    }
+
+    /// Returns the size of the drawable area of the window.
+    ///
+    /// While headless there is no window, so [`HEADLESS_DRAWABLE_SIZE`] is returned instead.
+    pub fn drawable_size(&self) -> (f32, f32) {
+        match self.try_get_context() {
+            Some(context) => context.gfx.drawable_size(),
+            None => HEADLESS_DRAWABLE_SIZE,
+        }
+    }
+
     /// Returns a mutable reference to the value that `self.context_ptr` points to.
     /// Panics if `self.context_ptr` is null or invalid, which should never be the case in normal scenarios. If it is, investigate immediately.
     pub fn get_context_mut(&mut self) -> &mut ggez::Context {
@@ -0,0 +1,189 @@
+//! Contains the engine's built-in events, and [`register_event`] for adding more.
+//!
+//! Every registered event type is stored as a bevy [`Events`] resource, and is updated by [`GameRoot`](crate::GameRoot) at the start of each tick.
+//! An event lives for two updates, so every system in [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick) sees it.
+//! Read events using an [`EventReader`](bevy_ecs::event::EventReader), and send them using an [`EventWriter`](bevy_ecs::event::EventWriter) or [`send_event`].
+//!
+//! Systems in [`ScheduleTag::Frame`](crate::schedule::ScheduleTag::Frame) must not read events. A frame can come after up to
+//! [`MAX_TICKS_PER_UPDATE`](crate::time::MAX_TICKS_PER_UPDATE) ticks, by which point the events sent in the earlier ones are gone.
+//! Updating events per frame instead would drop them whenever frames outpace ticks, before any tick has read them.
+//! Instead, have a tick system store what rendering needs in a resource or component.
+//!
+//! Built-in events:
+//! * [`SceneLoaded`] and [`SceneUnloaded`]
+//! * [`ActionPressed`] and [`ActionReleased`]
+//! * [`WindowResized`] and [`FocusChanged`]
+//! * [`FreezeChanged`]
+//! * [`TimerFinished`], [`CooldownReady`] and [`DespawnAfterFinished`], see [`crate::timer`]
+
+use std::any::TypeId;
+use std::path::PathBuf;
+
+use bevy_ecs::entity::Entity;
+use bevy_ecs::event::{Event, Events};
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::{Mut, World};
+
+use crate::freeze::FreezeType;
+use crate::input::action::ActionID;
+use crate::timer::{CooldownReady, DespawnAfterFinished, TimerFinished};
+use crate::Input;
+
+/// Sent once a scene has finished loading and all of its entities have been spawned.
+#[derive(Debug, Clone, Event)]
+pub struct SceneLoaded {
+    /// The entity holding the scene's [`Scene`](crate::scene::Scene) component
+    pub scene: Entity,
+    pub name: String,
+    /// The file the scene was loaded from
+    pub path: PathBuf,
+}
+
+/// Sent once a scene and all of its entities have been despawned.
+#[derive(Debug, Clone, Event)]
+pub struct SceneUnloaded {
+    /// The entity that held the scene's [`Scene`](crate::scene::Scene) component. It no longer exists.
+    pub scene: Entity,
+    pub name: String,
+}
+
+/// Sent on the tick an action becomes pressed, see [`ActionData::is_just_pressed`](crate::ActionData::is_just_pressed).
+#[derive(Debug, Clone, Event)]
+pub struct ActionPressed {
+    pub name: String,
+    pub id: ActionID,
+}
+
+/// Sent on the tick an action stops being pressed, see [`ActionData::is_just_released`](crate::ActionData::is_just_released).
+#[derive(Debug, Clone, Event)]
+pub struct ActionReleased {
+    pub name: String,
+    pub id: ActionID,
+}
+
+/// Sent when the window is resized. A size of zero means the window was minimized.
+#[derive(Debug, Clone, Event)]
+pub struct WindowResized {
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Sent when the window gains or loses focus.
+#[derive(Debug, Clone, Event)]
+pub struct FocusChanged {
+    pub focused: bool,
+}
+
+/// Sent whenever the freeze state in effect changes, see [`GgezInterface::push_freeze`](crate::GgezInterface::push_freeze).
+///
+/// Counting down [`FreezeType::IMPACT`] doesn't count as a change until it runs out.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub struct FreezeChanged {
+    pub previous: FreezeType,
+    pub current: FreezeType,
+}
+
+/// Updates the [`Events`] resource of a single registered event type.
+type EventUpdater = fn(&mut World);
+
+/// Keeps track of every registered event type, so that they can all be updated together.
+///
+/// Inserted by the engine with every built-in event already registered. Use [`register_event`] to add to it.
+#[derive(Resource, Default)]
+pub struct EventRegistry {
+    updaters: Vec<(TypeId, EventUpdater)>,
+}
+
+impl EventRegistry {
+    /// Returns true if `T` has been registered.
+    pub fn contains<T: Event>(&self) -> bool {
+        self.updaters
+            .iter()
+            .any(|(type_id, _)| *type_id == TypeId::of::<T>())
+    }
+}
+
+/// Adds an [`Events<T>`] resource to the world, which the engine updates at the start of every tick.
+///
+/// Registering the same event twice does nothing. Call this from an [`EnginePlugin`](crate::EnginePlugin) to add your own events.
+pub fn register_event<T: Event>(world: &mut World) {
+    let mut registry = world.get_resource_or_insert_with(EventRegistry::default);
+
+    if registry.contains::<T>() {
+        return;
+    }
+
+    registry.updaters.push((TypeId::of::<T>(), |world| {
+        world.resource_mut::<Events<T>>().update()
+    }));
+
+    world.init_resource::<Events<T>>();
+}
+
+/// Sends an event from outside of a system, doing nothing if `T` was never registered.
+pub fn send_event<T: Event>(world: &mut World, event: T) {
+    if let Some(mut events) = world.get_resource_mut::<Events<T>>() {
+        events.send(event);
+    }
+}
+
+/// Registers every built-in event.
+pub(crate) fn register_engine_events(world: &mut World) {
+    register_event::<SceneLoaded>(world);
+    register_event::<SceneUnloaded>(world);
+    register_event::<ActionPressed>(world);
+    register_event::<ActionReleased>(world);
+    register_event::<WindowResized>(world);
+    register_event::<FocusChanged>(world);
+    register_event::<FreezeChanged>(world);
+    register_event::<TimerFinished>(world);
+    register_event::<CooldownReady>(world);
+    register_event::<DespawnAfterFinished>(world);
+}
+
+/// Updates every registered event, dropping events that have already been around for two updates. Run once per tick, see the [module docs](self).
+pub(crate) fn update_events(world: &mut World) {
+    world.resource_scope(|world, registry: Mut<EventRegistry>| {
+        for (_, update) in &registry.updaters {
+            update(world);
+        }
+    });
+}
+
+/// Sends [`ActionPressed`] and [`ActionReleased`] for every action that changed this tick. Should be run after input has been processed.
+pub(crate) fn send_action_events(world: &mut World) {
+    let mut pressed = Vec::new();
+    let mut released = Vec::new();
+
+    for (name, action) in world.resource::<Input>().iter_actions() {
+        if action.is_just_pressed() {
+            pressed.push(ActionPressed {
+                name: name.clone(),
+                id: action.id,
+            });
+        } else if action.is_just_released() {
+            released.push(ActionReleased {
+                name: name.clone(),
+                id: action.id,
+            });
+        }
+    }
+
+    if let Some(mut events) = world.get_resource_mut::<Events<ActionPressed>>() {
+        events.send_batch(pressed);
+    }
+    if let Some(mut events) = world.get_resource_mut::<Events<ActionReleased>>() {
+        events.send_batch(released);
+    }
+}
+
+/// Sends a [`FreezeChanged`] for every freeze state change recorded since the last call.
+pub(crate) fn send_freeze_events(world: &mut World) {
+    let changes = world
+        .resource_mut::<crate::GgezInterface>()
+        .drain_freeze_changes();
+
+    if let Some(mut events) = world.get_resource_mut::<Events<FreezeChanged>>() {
+        events.send_batch(changes);
+    }
+}
@@ -0,0 +1,125 @@
+//! Frame-advance debugging, for pausing game logic and stepping through it one tick at a time.
+//!
+//! Pausing pushes [`FreezeType::DEBUG`], which stops every system in [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick) that doesn't explicitly run during it.
+//! Stepping runs exactly as many ticks as requested while the debug freeze stays in place, so leaving and re-entering it sends no [`FreezeChanged`](crate::events::FreezeChanged) events.
+//! Freeze states pushed while paused, like hitstop during a step or losing focus, go underneath the debug freeze and still send their events.
+//! Rendering and the editor GUI are untouched, since they don't run in the Tick schedule.
+//!
+//! Controlled through the [`PAUSE_ACTION_NAME`], [`STEP_ACTION_NAME`] and [`STEP_MANY_ACTION_NAME`] input actions, or through [`FrameAdvance`] directly.
+
+use crate::freeze::FreezeType;
+use crate::{GgezInterface, Input};
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::World;
+use log::debug;
+
+/// Toggles the debug pause
+pub static PAUSE_ACTION_NAME: &str = "debugpause";
+/// Steps forward a single tick while paused
+pub static STEP_ACTION_NAME: &str = "debugstep";
+/// Steps forward [`FrameAdvance::step_many_count`] ticks while paused
+pub static STEP_MANY_ACTION_NAME: &str = "debugstepmany";
+
+/// Requests for pausing and stepping, which are handled at the start of the next tick.
+#[derive(Debug, Resource)]
+pub struct FrameAdvance {
+    /// How many ticks the step many action advances.
+    pub step_many_count: u32,
+    /// Ticks left to run before pausing again.
+    pending_steps: u32,
+    toggle_requested: bool,
+}
+
+impl Default for FrameAdvance {
+    fn default() -> Self {
+        Self {
+            step_many_count: 10,
+            pending_steps: 0,
+            toggle_requested: false,
+        }
+    }
+}
+
+impl FrameAdvance {
+    /// Pauses if running, or resumes if paused, at the start of the next tick.
+    ///
+    /// Requesting again before the next tick doesn't cancel the request.
+    pub fn request_toggle_pause(&mut self) {
+        self.toggle_requested = true;
+    }
+
+    /// Runs `count` more ticks before pausing again. If the game isn't paused yet, it gets paused first.
+    pub fn request_steps(&mut self, count: u32) {
+        self.pending_steps = self.pending_steps.saturating_add(count);
+    }
+
+    /// The amount of ticks still waiting to be stepped through.
+    pub fn pending_steps(&self) -> u32 {
+        self.pending_steps
+    }
+
+    /// Returns true if game logic is currently paused by frame-advance.
+    pub fn is_paused(engine: &GgezInterface) -> bool {
+        matches!(engine.freeze_mode(), FreezeType::DEBUG(_))
+    }
+}
+
+/// Reads the frame-advance actions, then applies any pause or step requests.
+///
+/// Returns true if this tick is a step, in which case the game is paused and [`GameRoot`](crate::GameRoot) should run the tick through the debug freeze.
+pub(crate) fn update_frame_advance(world: &mut World) -> bool {
+    let (toggle, step, step_many) = {
+        let input = world.resource::<Input>();
+        let just_pressed = |name: &str| {
+            input
+                .get_action(name)
+                .is_some_and(|action| action.status().is_just_pressed())
+        };
+
+        (
+            just_pressed(PAUSE_ACTION_NAME),
+            just_pressed(STEP_ACTION_NAME),
+            just_pressed(STEP_MANY_ACTION_NAME),
+        )
+    };
+
+    world.resource_scope(
+        |world, mut frame_advance: bevy_ecs::world::Mut<FrameAdvance>| {
+            if toggle {
+                frame_advance.request_toggle_pause();
+            }
+            if step {
+                frame_advance.request_steps(1);
+            }
+            if step_many {
+                let count = frame_advance.step_many_count;
+                frame_advance.request_steps(count);
+            }
+
+            let mut engine = world.resource_mut::<GgezInterface>();
+
+            if std::mem::take(&mut frame_advance.toggle_requested) {
+                if FrameAdvance::is_paused(&engine) {
+                    engine.exit_debug_freeze();
+                    frame_advance.pending_steps = 0;
+                    debug!("Frame advance resumed");
+                } else {
+                    engine.enter_debug_freeze();
+                    debug!("Frame advance paused");
+                }
+            }
+
+            if frame_advance.pending_steps == 0 {
+                return false;
+            }
+
+            if !FrameAdvance::is_paused(&engine) {
+                engine.enter_debug_freeze();
+            }
+
+            frame_advance.pending_steps -= 1;
+
+            true
+        },
+    )
+}
@@ -1,9 +1,19 @@
 #![allow(unused)] //TODO: Remove allow after this is developed
 
+//! Contains [`FreezeType`], along with run conditions for deciding which systems keep running while the game is frozen.
+//!
+//! Every system in [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick) declares its own freeze behaviour,
+//! either through a [`FreezeBehaviour`] in its [`SystemEntry`](crate::schedule::SystemEntry), or by adding one of the run conditions here by hand.
+//! Hand-built schedules should use [`not_frozen`] for systems that should pause, since the schedule is run regardless of freeze state.
+
+use crate::GgezInterface;
+use bevy_ecs::system::Res;
+use serde::{Deserialize, Serialize};
+
 /// Declares different kinds of states where the game should stop updating
 ///
 /// Game logic should be disabled if
-#[derive(Debug, Default, PartialEq, Clone)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub enum FreezeType {
     /// The game is in focus, and there isn't anything that should be preventing game logic and rendering from updating.
     #[default]
@@ -16,11 +26,14 @@ pub enum FreezeType {
     PAUSED,
     /// The game is currently loading assets. Stop most rendering and typical game logic
     LOADING,
-    /// The game is paused for some amount of frames ([`u32`]). Keep rendering, stop most game logic temporarily
+    /// The game is paused for some amount of ticks ([`u32`]). Keep rendering, stop most game logic temporarily
+    ///
+    /// Counts down once per tick, and returns to the previous state once it runs out. See [`GgezInterface::push_freeze`](crate::GgezInterface::push_freeze).
     IMPACT(u32),
     /// The game is paused due to debug state. Keep rendering, but stop ALL game logic.
     ///
-    /// Contains the previous state to return to when exiting debug state.
+    /// Contains the state that was active when debug state was entered, for display purposes.
+    /// Leaving debug state pops it off of the freeze stack, see [`GgezInterface::exit_debug_freeze`](crate::GgezInterface::exit_debug_freeze).
     ///
     /// Even FreezeSchedule will not run during this.
     DEBUG(Box<FreezeType>),
@@ -29,14 +42,67 @@ pub enum FreezeType {
 impl FreezeType {
     /// Compares self to other, and checks if they're of the same variant, regardless of variant parameters.
     pub fn variant_eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// How a system in [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick) should behave while the game is frozen.
+///
+/// [`FreezeType::DEBUG`] halts every behaviour except [`FreezeBehaviour::RunsDuring`] with `DEBUG` listed, so that frame-advance stays deterministic.
+///
+/// Only applies to systems built through the [`ScheduleBuilder`](crate::schedule::ScheduleBuilder) into the Tick schedule,
+/// other schedules like [`ScheduleTag::Frame`](crate::schedule::ScheduleTag::Frame) keep running no matter the freeze state.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub enum FreezeBehaviour {
+    /// Pauses during any freeze state that freezes the game, see [`GgezInterface::is_freeze_frame`].
+    #[default]
+    PausedByAny,
+    /// Pauses during any freeze state, except for these variants.
+    RunsDuring(Vec<FreezeType>),
+    /// Only pauses during these variants, and [`FreezeType::DEBUG`].
+    PausedBy(Vec<FreezeType>),
+    /// Never pauses, except during [`FreezeType::DEBUG`].
+    Always,
+}
+
+impl FreezeBehaviour {
+    /// Returns true if a system with this behaviour should run given the engine's current freeze state.
+    pub fn should_run(&self, engine: &GgezInterface) -> bool {
+        if !engine.is_freeze_frame() {
+            return true;
+        }
+
+        let current = engine.freeze_mode();
         match self {
-            FreezeType::NONE => self == other,
-            FreezeType::UNFOCUSED => self == other,
-            FreezeType::MINIMIZED => self == other,
-            FreezeType::PAUSED => self == other,
-            FreezeType::LOADING => self == other,
-            FreezeType::IMPACT(_) => todo!(),
-            FreezeType::DEBUG(_) => todo!(),
+            FreezeBehaviour::PausedByAny => false,
+            FreezeBehaviour::RunsDuring(allowed) => {
+                allowed.iter().any(|freeze| freeze.variant_eq(current))
+            }
+            // the debug pause halts everything that doesn't explicitly run during it
+            _ if matches!(current, FreezeType::DEBUG(_)) => false,
+            FreezeBehaviour::PausedBy(paused) => {
+                !paused.iter().any(|freeze| freeze.variant_eq(current))
+            }
+            FreezeBehaviour::Always => true,
         }
     }
 }
+
+/// Run condition that is true unless the game is frozen.
+pub fn not_frozen(engine: Res<GgezInterface>) -> bool {
+    !engine.is_freeze_frame()
+}
+
+/// Run condition that is true unless the game is frozen by something other than `freeze`'s variant.
+///
+/// Variant parameters are ignored, so `runs_during(FreezeType::IMPACT(0))` keeps a system running through any amount of hitstop.
+pub fn runs_during(freeze: FreezeType) -> impl FnMut(Res<GgezInterface>) -> bool + Clone {
+    let behaviour = FreezeBehaviour::RunsDuring(vec![freeze]);
+    move |engine: Res<GgezInterface>| behaviour.should_run(&engine)
+}
+
+/// Run condition that is only false while the game is frozen by `freeze`'s variant, or paused for debugging.
+pub fn paused_by(freeze: FreezeType) -> impl FnMut(Res<GgezInterface>) -> bool + Clone {
+    let behaviour = FreezeBehaviour::PausedBy(vec![freeze]);
+    move |engine: Res<GgezInterface>| behaviour.should_run(&engine)
+}
@@ -98,6 +98,15 @@ impl Input {
         input
     }
 
+    /// Returns an [`Input`] resource that knows every key but has no actions, for [`GameRoot::new_headless`](crate::GameRoot::new_headless),
+    /// which doesn't load the key file.
+    pub(crate) fn headless() -> Self {
+        Self {
+            key_list: (*input_hashmap::const_key_hashmap()).clone(),
+            ..Default::default()
+        }
+    }
+
     pub(crate) fn update_key_queue(&mut self, key: KeycodeType, is_held: bool) {
         self.key_update_queue.push_front((key, is_held))
     }
@@ -6,11 +6,20 @@
 //! Several components are stored here as well, built directly into the engine.
 //! The [`Transform`] and [`camera::Camera`] are good examples of that.
 pub mod assets;
+pub mod crash;
+pub mod events;
 pub mod input;
 pub mod logging;
+pub mod plugin;
+pub mod profiler;
+pub mod random;
+pub mod save;
 pub mod scene;
 pub mod schedule;
 pub mod space;
+pub mod state;
+pub mod time;
+pub mod timer;
 
 // TODO: Private when finished developing
 pub mod camera;
@@ -21,10 +30,18 @@ pub mod root;
 
 #[cfg(feature = "editor_features")]
 pub mod editor;
+#[cfg(feature = "editor_features")]
+pub mod frame_advance;
+
+#[cfg(test)]
+mod test;
 
 use std::any::TypeId;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
 
-use bevy_ecs::reflect::{ReflectComponent, ReflectFromWorld};
+use bevy_ecs::reflect::{ReflectComponent, ReflectFromWorld, ReflectResource};
 use bevy_ecs::world::{FromWorld, Mut};
 use bevy_reflect::{ReflectDeserialize, ReflectSerialize};
 use bevy_trait_query::RegisterExt as _;
@@ -34,13 +51,16 @@ use editor::FieldWidget;
 pub use engine::GgezInterface;
 pub use input::input_cli_editor;
 pub use input::{ActionData, Input, Key};
-pub use logging::LogData;
+pub use logging::{LogData, LogHistory};
+pub use plugin::EnginePlugin;
 #[cfg(feature = "editor_features")]
 use render::draw_param_ui;
 pub use render::render_type::RenderType;
 pub use root::GameRoot;
 use scene::SceneManager;
 use scene::TestSuperTrait;
+use serde::{Deserialize, Serialize};
+pub use time::Time;
 
 #[cfg(feature = "editor_features")]
 use crate::editor::InspectableAsField;
@@ -49,58 +69,193 @@ use crate::render::DowncastInsert;
 /// A list of settings that the engine needs in order to operate exactly as you want it to.
 ///
 /// See each field's specific documentation for information about what each of them is for.
-#[derive(Debug, Clone)]
+///
+/// Everything but [`plugins`](EngineConfig::plugins) can be loaded from a RON, JSON or TOML file through [`EngineConfig::load`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EngineConfig {
     /// Set a list of file paths that lead to serialized [`Scene`](crate::scene::Scene)'s. The first one will be loaded on startup if given,
     /// and others will automatically be added to the list of immediately accessable scenes.  
-    // TODO: Update when I redo the scene loading system to replace JSON with ron and when this gets replaced with a file path leading to a file containing a list of scenes.
-    pub external_scene_paths: &'static [&'static str],
+    pub external_scene_paths: Vec<String>,
     /// A path to a given directory that stores scenes to automatically load, and where new scenes are stored.
     ///
     /// If given an empty or invalid string, the engine will instead manually prompt the user to where a scene
     /// is stored via the CLI. For best convenience, do pick a location to store new scenes.
-    pub scenes_folder: Option<&'static str>,
-    /// An initiation function that should be run on the world to register/add components, custom resources, and schedules.
+    #[serde(default)]
+    pub scenes_folder: Option<String>,
+    /// The format new scenes are saved in. Scenes that were loaded from a file keep that file's format.
+    #[serde(default)]
+    pub scene_format: scene::SceneFormat,
+    /// The directory that save slots are stored in, see [`save`].
     ///
-    /// You are required to add at least 2 schedules, that being [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick) and
-    /// [`ScheduleTag::Frame`](crate::schedule::ScheduleTag::Frame), which handle game logic and rendering logic respectively.
+    /// Defaults to a `saves` folder inside of [`ggez`]'s user data directory, which is picked from the game's name and author.
+    #[serde(default)]
+    pub save_folder: Option<String>,
+    /// The plugins that register/add components, custom resources, and schedules to the world, built in the order they are listed.
+    ///
+    /// Systems should be registered into the [`ScheduleBuilder`](crate::schedule::ScheduleBuilder) resource, which builds
+    /// [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick), [`ScheduleTag::Frame`](crate::schedule::ScheduleTag::Frame)
+    /// and any other defined schedules once every plugin has been built. Schedules can still be added to the world by hand.
     ///
     /// See also [`ScheduleTag::Init`](crate::schedule::ScheduleTag::Init) and [`ScheduleTag::FreezeTick`](crate::schedule::ScheduleTag::FreezeTick)
     /// which are run in specific cases if given, as well as other debug schedules that run alongside normal schedules for special functionality, if enabled.
-    pub world_init: fn(&mut bevy_ecs::prelude::World) -> (),
+    ///
+    /// Plugins can't be stored in a file, so add them after loading using [`EngineConfig::with_plugin`].
+    #[serde(skip)]
+    pub plugins: Vec<Arc<dyn EnginePlugin>>,
     /// How many times should game logic be updated per second.
     ///
     /// Notice that this is not equal to the amount of frames rendered and displayed to the screen, this handles game logic specifically.
     ///
     /// Also notice that this is simply a cap, and can't force the engine to run faster if the computer is having a hard time processing logic.
     /// You can also leave it uncapped and operate using delta time, if that's what you prefer.
+    #[serde(default = "EngineConfig::default_ticks_per_second")]
     pub ticks_per_second: u32,
     /// Should normal game logic be paused when the window is not in focus?
     ///
     /// Rendering will still apply as normal, though delta will work a little differently.
+    #[serde(default)]
     pub freeze_on_unfocus: bool,
     /// Should normal game logic be paused when the window is hidden from the user?
     ///
     /// Rendering will be paused when minimized.
     ///
     /// Note that minimizing counts as being unfocused, so this rule will override `freeze_on_unfocus` when the window is minimized.
+    #[serde(default)]
     pub freeze_on_minimize: bool,
     /// Should debug schedules be run when debug mode is enabled?
     ///
-    /// Notice that if this is turned on while debug schedules have not been inserted into the world, this will cause a panic.
+    /// Notice that if this is turned on while debug schedules have not been inserted into the world, [`EngineConfig::validate_world`] will fail.
     ///
     /// Do not enable unless you add the various debug schedules.
     /// Any [`ScheduleTag`](crate::schedule::ScheduleTag) variant that starts with `Debug-` must exist and must be added to the world using
     /// [`World::add_schedule()`](bevy_ecs::prelude::World::add_schedule).
+    #[serde(default)]
     pub run_debug_schedules: bool,
+    /// Level filters, an optional log file, and how much history to keep. See [`logging`] for details.
+    #[serde(default)]
+    pub logger: logging::LoggerConfig,
+    /// Where loaded scenes are saved to if the engine panics, and how much of the log goes into the crash report. See [`crash`] for details.
+    #[serde(default)]
+    pub crash_recovery: crash::CrashRecoveryConfig,
+}
+
+/// The highest tick rate [`EngineConfig::validate`] accepts.
+pub const MAX_TICKS_PER_SECOND: u32 = 1000;
+
+impl EngineConfig {
+    /// Loads a config from a RON, JSON or TOML file, picked using the file's extension, then validates it.
+    ///
+    /// The loaded config has no [`plugins`](EngineConfig::plugins), since they can't be stored in a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EngineConfigError> {
+        let path = path.as_ref();
+
+        let file = std::fs::read_to_string(path).map_err(EngineConfigError::IOError)?;
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+
+        let config = match extension {
+            "ron" => ron::from_str::<EngineConfig>(&file)
+                .map_err(|err| EngineConfigError::ParseFailure(err.to_string()))?,
+            "json" => serde_json::from_str::<EngineConfig>(&file)
+                .map_err(|err| EngineConfigError::ParseFailure(err.to_string()))?,
+            "toml" => toml::from_str::<EngineConfig>(&file)
+                .map_err(|err| EngineConfigError::ParseFailure(err.to_string()))?,
+            other => return Err(EngineConfigError::UnsupportedFormat(other.to_owned())),
+        };
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Adds a plugin to the end of the [`plugins`](EngineConfig::plugins) list, after any plugins it depends on.
+    pub fn with_plugin(mut self, plugin: impl EnginePlugin) -> Self {
+        self.plugins.push(Arc::new(plugin));
+        self
+    }
+
+    /// Checks the config's values for anything the engine can't run with.
+    ///
+    /// See also [`EngineConfig::validate_world`], which checks the world after every plugin has been built.
+    pub fn validate(&self) -> Result<(), EngineConfigError> {
+        if self.external_scene_paths.is_empty() {
+            return Err(EngineConfigError::NoScenePaths);
+        }
+
+        if let Some(path) = self
+            .external_scene_paths
+            .iter()
+            .find(|path| path.trim().is_empty())
+        {
+            return Err(EngineConfigError::InvalidScenePath(path.clone()));
+        }
+
+        if self.ticks_per_second == 0 || self.ticks_per_second > MAX_TICKS_PER_SECOND {
+            return Err(EngineConfigError::InvalidTicksPerSecond);
+        }
+
+        logging::LogFilter::from_str(&self.logger.filters)
+            .map_err(EngineConfigError::InvalidLogFilter)?;
+
+        plugin::validate_plugins(&self.plugins)?;
+
+        Ok(())
+    }
+
+    /// Checks that every schedule the engine runs unconditionally exists, which should be called after every plugin has been built.
+    ///
+    /// [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick) and [`ScheduleTag::Frame`](crate::schedule::ScheduleTag::Frame) are always required,
+    /// and the debug schedules are required if [`run_debug_schedules`](EngineConfig::run_debug_schedules) is set.
+    pub fn validate_world(
+        &self,
+        world: &bevy_ecs::prelude::World,
+    ) -> Result<(), EngineConfigError> {
+        use schedule::ScheduleTag;
+
+        let schedules = world.resource::<bevy_ecs::schedule::Schedules>();
+
+        #[cfg_attr(not(feature = "editor_features"), allow(unused_mut))]
+        let mut required = vec![ScheduleTag::Tick, ScheduleTag::Frame];
+
+        #[cfg(feature = "editor_features")]
+        if self.run_debug_schedules {
+            required.extend([
+                ScheduleTag::DebugTick,
+                ScheduleTag::DebugFrame,
+                ScheduleTag::DebugGUI,
+            ]);
+        }
+
+        for tag in required {
+            if !schedules.contains(tag) {
+                return Err(EngineConfigError::MissingSchedule(tag));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn default_ticks_per_second() -> u32 {
+        60
+    }
 }
 
 #[derive(Debug)]
 pub enum EngineConfigError {
     NoScenePaths,
-    InvalidScenePath(&'static str),
+    InvalidScenePath(String),
     InvalidTicksPerSecond,
-    MissingSchedule,
+    MissingSchedule(schedule::ScheduleTag),
+    InvalidLogFilter(String),
+    DuplicatePlugin(String),
+    MissingPluginDependency { plugin: String, dependency: String },
+    PluginOutOfOrder { plugin: String, dependency: String },
+    IOError(std::io::Error),
+    UnsupportedFormat(String),
+    ParseFailure(String),
 }
 
 impl ToString for EngineConfigError {
@@ -112,11 +267,32 @@ impl ToString for EngineConfigError {
             EngineConfigError::InvalidScenePath(path) => {
                 format!("An invalid scene path was given: {}", path)
             }
-            EngineConfigError::InvalidTicksPerSecond => {
-                "The given ticks per second value is invalid".to_string()
+            EngineConfigError::InvalidTicksPerSecond => format!(
+                "The given ticks per second value is invalid, must be between 1 and {}",
+                MAX_TICKS_PER_SECOND
+            ),
+            EngineConfigError::MissingSchedule(tag) => {
+                format!("A schedule required for operation is missing: {:?}", tag)
             }
-            EngineConfigError::MissingSchedule => {
-                "A schedule required for operation is missing".to_string()
+            EngineConfigError::InvalidLogFilter(err) => err.to_owned(),
+            EngineConfigError::DuplicatePlugin(plugin) => {
+                format!("The plugin {} was added more than once", plugin)
+            }
+            EngineConfigError::MissingPluginDependency { plugin, dependency } => format!(
+                "The plugin {} depends on {}, which was never added",
+                plugin, dependency
+            ),
+            EngineConfigError::PluginOutOfOrder { plugin, dependency } => format!(
+                "The plugin {} depends on {}, which must be added before it",
+                plugin, dependency
+            ),
+            EngineConfigError::IOError(err) => err.to_string(),
+            EngineConfigError::UnsupportedFormat(extension) => format!(
+                "Engine config files must be .ron, .json or .toml, got [{}]",
+                extension
+            ),
+            EngineConfigError::ParseFailure(err) => {
+                format!("Could not parse engine config: {}", err)
             }
         }
     }
@@ -130,6 +306,7 @@ pub enum SomeError {
     IO(std::io::Error),
     Misc(String),
     EngineConfig(EngineConfigError),
+    Schedule(schedule::ScheduleError),
 }
 
 impl ToString for SomeError {
@@ -140,12 +317,15 @@ impl ToString for SomeError {
             SomeError::IO(io) => io.to_string(),
             SomeError::Misc(misc) => misc.to_string(),
             SomeError::EngineConfig(engine_config) => engine_config.to_string(),
+            SomeError::Schedule(schedule) => schedule.to_string(),
         }
     }
 }
 
 pub fn register_scene_types(world: &mut bevy_ecs::world::World) {
     world.init_resource::<SceneManager>();
+    world.init_resource::<scene::SceneMigrations>();
+    world.init_resource::<scene::SceneLoads>();
     world.resource_scope(|world, mut res: Mut<SceneManager>| {
         let mut type_registry = &mut res.type_registry;
 
@@ -169,12 +349,16 @@ pub fn register_scene_types(world: &mut bevy_ecs::world::World) {
             register_primitive_value::<String>(type_registry);
             register_primitive_value::<space::Vector2>(type_registry);
         }
+        register_primitive_value::<scene::EntityRef>(type_registry);
         register_tuple_struct::<assets::SceneAssetID>(type_registry);
         register_component::<space::Position>(world, type_registry);
         register_component::<space::Rotation>(world, type_registry);
         register_component::<space::Scale>(world, type_registry);
         register_component::<space::TransformSettings>(world, type_registry);
         register_component::<space::Velocity>(world, type_registry);
+        register_component::<timer::Timer>(world, type_registry);
+        register_component::<timer::Cooldown>(world, type_registry);
+        register_component::<timer::DespawnAfter>(world, type_registry);
     });
 }
 
@@ -326,6 +510,40 @@ pub fn register_component<
     world.register_component_as::<dyn TestSuperTrait, T>(); // TestSuperTrait is used in world queries for iterating over types dynamically
 }
 
+/// Marks a component registered through [`register_component`] to be stored in save slots, see [`save`].
+///
+/// Same as adding `#[reflect(SaveGame)]` to the component.
+pub fn register_saved_component<T>(type_registry: &mut bevy_reflect::TypeRegistry)
+where
+    T: bevy_ecs::component::Component
+        + bevy_reflect::Reflect
+        + bevy_reflect::GetTypeRegistration
+        + bevy_reflect::TypePath,
+{
+    type_registry.register_type_data::<T, save::ReflectSaveGame>();
+}
+
+/// Registers a resource to be stored in save slots, see [`save`].
+///
+/// Saved resources replace the current ones when a slot is loaded, or are inserted if they don't exist yet.
+pub fn register_saved_resource<T>(type_registry: &mut bevy_reflect::TypeRegistry)
+where
+    T: bevy_ecs::system::Resource
+        + bevy_reflect::Reflect
+        + bevy_reflect::GetTypeRegistration
+        + bevy_reflect::FromReflect
+        + bevy_reflect::TypePath
+        + serde::Serialize
+        + for<'a> serde::Deserialize<'a>
+        + FromWorld,
+{
+    type_registry.register::<T>();
+    type_registry.register_type_data::<T, ReflectResource>();
+    type_registry.register_type_data::<T, ReflectSerialize>();
+    type_registry.register_type_data::<T, ReflectDeserialize>();
+    type_registry.register_type_data::<T, save::ReflectSaveGame>();
+}
+
 #[cfg(feature = "editor_features")]
 pub fn register_custom_inspection<
     T: bevy_ecs::component::Component
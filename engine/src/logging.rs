@@ -1,63 +1,323 @@
-use std::fmt::Debug;
+//! The engine's logger, which is configured through [`LoggerConfig`] in the [`EngineConfig`](crate::EngineConfig).
+//!
+//! Every record that passes the level filters is printed to stdout, optionally written to a log file,
+//! and kept in a bounded history that can be read through the [`LogHistory`] resource.
+//!
+//! Level filters use the same syntax as `RUST_LOG`: a comma separated list of `target=level` pairs,
+//! along with an optional bare `level` used for every target that isn't listed. The most specific matching target wins.
+//! The [`LOG_FILTER_ENV_VAR`] environment variable overrides the configured filters when set.
 
-pub struct Logger;
-pub(crate) static LOGGER: Logger = Logger;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
 
-impl log::Log for Logger {
-    fn enabled(&self, _metadata: &log::Metadata) -> bool {
-        // find the crate name, and if calling from a module, use just the name instead of the full module path for checking with the whitelist
-        let caller_module_path = &_metadata.target();
+use bevy_ecs::system::Resource;
+use log::{Level, LevelFilter};
+use serde::{Deserialize, Serialize};
+
+/// When set, this environment variable is used for the level filters instead of [`LoggerConfig::filters`].
+pub static LOG_FILTER_ENV_VAR: &str = "NINJA_LOG";
+
+/// Settings for the engine's logger.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggerConfig {
+    /// Level filters per target, such as `"warn,engine=debug,wgpu_core=off"`. See the [module docs](self) for the syntax.
+    pub filters: String,
+    /// A file that every printed record is also written to. It is created if it doesn't exist, and overwritten if it does.
+    pub file: Option<String>,
+    /// How many of the most recent records are kept in the [`LogHistory`].
+    pub history_size: usize,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            filters: "warn,wgpu_core=off,engine=info,game=info,editor=info,components=info"
+                .to_owned(),
+            file: None,
+            history_size: 500,
+        }
+    }
+}
+
+/// Level filters parsed from a filter string, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// Sorted from longest to shortest target, so that the first match is the most specific.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Returns the level filter for the given target, which is usually a module path.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// The most verbose level any target allows, used for [`log::set_max_level`].
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            default: LevelFilter::Info,
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut filter = LogFilter::default();
+
+        for directive in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    let level = LevelFilter::from_str(level.trim())
+                        .map_err(|_| format!("Invalid level in log filter [{}]", directive))?;
+                    filter.targets.push((target.trim().to_owned(), level));
+                }
+                None => {
+                    filter.default = LevelFilter::from_str(directive)
+                        .map_err(|_| format!("Invalid level in log filter [{}]", directive))?;
+                }
+            }
+        }
+
+        filter
+            .targets
+            .sort_by_key(|(target, _)| Reverse(target.len()));
+
+        Ok(filter)
+    }
+}
+
+/// A single log record, as stored in the [`LogHistory`].
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    /// The file and line the record was logged from, if known
+    pub location: Option<String>,
+    pub time: SystemTime,
+}
+
+impl Display for LogRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefix = match self.level {
+            Level::Error => "! Error",
+            Level::Warn => "~ Warn ",
+            Level::Info => "- Info ",
+            Level::Debug => "? Debug",
+            Level::Trace => "  Trace",
+        };
+
+        write!(
+            f,
+            "{}: [{}]: {}",
+            prefix,
+            self.location.as_deref().unwrap_or(&self.target),
+            self.message
+        )
+    }
+}
+
+type SharedHistory = Arc<Mutex<VecDeque<LogRecord>>>;
+
+/// The most recent log records, oldest first. Inserted by the engine, and shares its records with the logger.
+#[derive(Debug, Clone, Resource)]
+pub struct LogHistory {
+    records: SharedHistory,
+}
+
+impl LogHistory {
+    /// Returns a copy of every record currently kept.
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Returns a copy of the last `count` records.
+    pub fn recent(&self, count: usize) -> Vec<LogRecord> {
+        let records = self.lock();
+        records
+            .iter()
+            .skip(records.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
 
-        let module_break = match caller_module_path.find(':') {
-            Some(some) => some,
-            None => caller_module_path.len(),
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<LogRecord>> {
+        // a panic while logging shouldn't stop the history from being read afterwards, especially for crash reports
+        self.records
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+struct LoggerState {
+    filter: LogFilter,
+    file: Option<Mutex<BufWriter<File>>>,
+    history: SharedHistory,
+    history_size: usize,
+}
+
+pub struct Logger {
+    state: RwLock<Option<LoggerState>>,
+}
+
+pub(crate) static LOGGER: Logger = Logger {
+    state: RwLock::new(None),
+};
+
+impl Logger {
+    /// Applies the config, replacing any previous configuration, and returns the history resource for it.
+    ///
+    /// The env var filters are used over the config's filters if they're valid.
+    pub(crate) fn configure(&self, config: &LoggerConfig) -> Result<LogHistory, String> {
+        let filter = match std::env::var(LOG_FILTER_ENV_VAR) {
+            Ok(env_filters) => LogFilter::from_str(&env_filters).or_else(|err| {
+                eprintln!("Ignoring {}: {}", LOG_FILTER_ENV_VAR, err);
+                LogFilter::from_str(&config.filters)
+            })?,
+            Err(_) => LogFilter::from_str(&config.filters)?,
+        };
+
+        let file = match &config.file {
+            Some(path) => Some(Mutex::new(BufWriter::new(
+                File::create(path)
+                    .map_err(|err| format!("Could not create log file {}: {}", path, err))?,
+            ))),
+            None => None,
         };
 
-        let crate_name = caller_module_path.get(0..module_break).unwrap();
+        let history = LogHistory {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(config.history_size))),
+        };
+
+        log::set_max_level(filter.max_level());
+
+        let mut state = self.state.write().unwrap_or_else(|err| err.into_inner());
+        *state = Some(LoggerState {
+            filter,
+            file,
+            history: history.records.clone(),
+            history_size: config.history_size,
+        });
 
-        if crate_name == "wgpu_core" {
+        Ok(history)
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        let Ok(state) = self.state.read() else {
             return false;
-        }
-        // dbg!(crate_name);
+        };
 
-        ["engine", "game", "editor", "components"].contains(&crate_name)
+        match state.as_ref() {
+            Some(state) => metadata.level() <= state.filter.level_for(metadata.target()),
+            None => false,
+        }
     }
 
     fn log(&self, record: &log::Record) {
-        if !self.enabled(record.metadata()) {
+        let Ok(state) = self.state.read() else {
+            return;
+        };
+        let Some(state) = state.as_ref() else {
+            return;
+        };
+
+        if record.level() > state.filter.level_for(record.target()) {
             return;
         }
-        match record.level() {
-            log::Level::Error => println!(
-                "! Error: [{}]: {}",
-                record.file().unwrap().to_owned() + ":" + &record.line().unwrap().to_string(),
-                record.args()
-            ),
-            log::Level::Warn => println!(
-                "~ Warn : [{}]: {}",
-                record.file().unwrap().to_owned() + ":" + &record.line().unwrap().to_string(),
-                record.args()
-            ),
-            log::Level::Info => println!(
-                "- Info : [{}]: {}",
-                record.file().unwrap().to_owned() + ":" + &record.line().unwrap().to_string(),
-                record.args()
-            ),
-            log::Level::Debug => println!(
-                "? Debug: [{}]: {}",
-                record.file().unwrap().to_owned() + ":" + &record.line().unwrap().to_string(),
-                record.args()
-            ),
-            log::Level::Trace => println!(
-                "  Trace: [{}]: {}",
-                record.file().unwrap().to_owned() + ":" + &record.line().unwrap().to_string(),
-                record.args()
-            ),
+
+        let record = LogRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            location: record
+                .file()
+                .map(|file| format!("{}:{}", file, record.line().unwrap_or_default())),
+            time: SystemTime::now(),
+        };
+
+        let line = record.to_string();
+
+        println!("{}", line);
+
+        if let Some(file) = &state.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = writeln!(file, "{}", line);
+
+                // anything this important should make it into the file even if the engine crashes right after
+                if record.level <= Level::Warn {
+                    let _ = file.flush();
+                }
+            }
+        }
+
+        if state.history_size > 0 {
+            let mut history = state
+                .history
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            if history.len() >= state.history_size {
+                history.pop_front();
+            }
+            history.push_back(record);
         }
     }
 
     fn flush(&self) {
-        todo!()
+        let _ = std::io::stdout().flush();
+
+        let Ok(state) = self.state.read() else {
+            return;
+        };
+
+        if let Some(file) = state.as_ref().and_then(|state| state.file.as_ref()) {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.flush();
+            }
+        }
     }
 }
 
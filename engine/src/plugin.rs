@@ -0,0 +1,84 @@
+//! Contains the [`EnginePlugin`] trait, which is how crates add their types, resources, schedules and systems to the engine.
+//!
+//! Plugins are given to the engine through [`EngineConfig::plugins`](crate::EngineConfig::plugins), and are built in the order they are listed.
+//! A plugin can name other plugins it depends on, which must come before it in that list. This is checked by [`EngineConfig::validate`](crate::EngineConfig::validate)
+//! before anything is built.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use bevy_ecs::world::World;
+
+use crate::EngineConfigError;
+
+/// A bundle of setup that gets run on the world when a [`GameRoot`](crate::GameRoot) is created.
+///
+/// `build` is run after the engine's own resources have been inserted, and before the schedules in
+/// [`ScheduleBuilder`](crate::schedule::ScheduleBuilder) are built, so systems should be registered and placed there.
+pub trait EnginePlugin: Send + Sync + 'static {
+    /// A unique name for the plugin, used for checking dependencies.
+    ///
+    /// Defaults to the plugin's type name, see [`plugin_name`].
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// The names of every plugin that must be built before this one.
+    fn dependencies(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Registers types and adds resources, schedules and systems to the world.
+    fn build(&self, world: &mut World);
+}
+
+impl Debug for dyn EnginePlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Returns the default [`EnginePlugin::name`] of `P`, for listing it in [`EnginePlugin::dependencies`].
+pub fn plugin_name<P: EnginePlugin>() -> &'static str {
+    std::any::type_name::<P>()
+}
+
+/// Checks that no plugin is listed twice, and that every plugin's dependencies are listed before it.
+pub fn validate_plugins(plugins: &[Arc<dyn EnginePlugin>]) -> Result<(), EngineConfigError> {
+    let mut built: HashSet<&'static str> = HashSet::new();
+
+    for plugin in plugins {
+        for dependency in plugin.dependencies() {
+            if built.contains(dependency) {
+                continue;
+            }
+
+            if plugins.iter().any(|other| other.name() == dependency) {
+                return Err(EngineConfigError::PluginOutOfOrder {
+                    plugin: plugin.name().to_owned(),
+                    dependency: dependency.to_owned(),
+                });
+            }
+
+            return Err(EngineConfigError::MissingPluginDependency {
+                plugin: plugin.name().to_owned(),
+                dependency: dependency.to_owned(),
+            });
+        }
+
+        if !built.insert(plugin.name()) {
+            return Err(EngineConfigError::DuplicatePlugin(plugin.name().to_owned()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds every plugin in order. Expects the plugins to have already passed [`validate_plugins`].
+pub(crate) fn build_plugins(plugins: &[Arc<dyn EnginePlugin>], world: &mut World) {
+    for plugin in plugins {
+        plugin.build(world);
+        log::trace!("Built plugin {}", plugin.name());
+    }
+}
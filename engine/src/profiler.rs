@@ -0,0 +1,424 @@
+//! Contains the [`Profiler`] resource, which times every schedule run by [`GameRoot`](crate::GameRoot) and every system built by the
+//! [`ScheduleBuilder`](crate::schedule::ScheduleBuilder).
+//!
+//! Timings are kept as rolling min/avg/max over the last [`Profiler::window`] runs, alongside a histogram of frame times.
+//! Recent runs are also kept as trace events, which can be exported with [`Profiler::export_chrome_trace`] and opened
+//! in `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+//!
+//! Systems added to a schedule by hand aren't timed individually, but still count towards their schedule's time.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy_ecs::system::{Adapt, Resource, System};
+
+use crate::schedule::ScheduleTag;
+
+/// How many runs of each schedule and system are used for the rolling statistics.
+pub const DEFAULT_WINDOW: usize = 120;
+
+/// How many trace events are kept for exporting before the oldest are thrown away.
+pub const DEFAULT_TRACE_CAPACITY: usize = 100_000;
+
+/// The width of each bucket in the [`FrameHistogram`].
+pub const FRAME_HISTOGRAM_BUCKET_WIDTH: Duration = Duration::from_millis(1);
+
+/// How many buckets the [`FrameHistogram`] has, the last of which holds every frame that took longer than the others cover.
+pub const FRAME_HISTOGRAM_BUCKETS: usize = 51;
+
+/// Rolling statistics over the most recent runs of a schedule or system.
+#[derive(Debug, Clone)]
+pub struct TimingStats {
+    recent: VecDeque<Duration>,
+    window: usize,
+    total_runs: u64,
+}
+
+impl TimingStats {
+    fn new(window: usize) -> Self {
+        Self {
+            recent: VecDeque::with_capacity(window),
+            window: window.max(1),
+            total_runs: 0,
+        }
+    }
+
+    fn record(&mut self, duration: Duration) {
+        if self.recent.len() >= self.window {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(duration);
+        self.total_runs += 1;
+    }
+
+    /// The shortest run in the window.
+    pub fn min(&self) -> Duration {
+        self.recent.iter().min().copied().unwrap_or_default()
+    }
+
+    /// The average run in the window.
+    pub fn avg(&self) -> Duration {
+        if self.recent.is_empty() {
+            return Duration::ZERO;
+        }
+        self.recent.iter().sum::<Duration>() / self.recent.len() as u32
+    }
+
+    /// The longest run in the window.
+    pub fn max(&self) -> Duration {
+        self.recent.iter().max().copied().unwrap_or_default()
+    }
+
+    /// The most recent run.
+    pub fn last(&self) -> Duration {
+        self.recent.back().copied().unwrap_or_default()
+    }
+
+    /// How many runs have been recorded in total, including ones that have left the window.
+    pub fn total_runs(&self) -> u64 {
+        self.total_runs
+    }
+}
+
+/// Counts how many frames fell into each [`FRAME_HISTOGRAM_BUCKET_WIDTH`] wide range of frame times.
+#[derive(Debug, Clone)]
+pub struct FrameHistogram {
+    buckets: [u64; FRAME_HISTOGRAM_BUCKETS],
+}
+
+impl Default for FrameHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; FRAME_HISTOGRAM_BUCKETS],
+        }
+    }
+}
+
+impl FrameHistogram {
+    fn record(&mut self, frame_time: Duration) {
+        let index = (frame_time.as_nanos() / FRAME_HISTOGRAM_BUCKET_WIDTH.as_nanos()) as usize;
+        self.buckets[index.min(FRAME_HISTOGRAM_BUCKETS - 1)] += 1;
+    }
+
+    /// The amount of frames in each bucket. Bucket `i` covers frame times from `i` to `i + 1` bucket widths,
+    /// except for the last bucket, which has no upper limit.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// How many frames have been recorded in total.
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+}
+
+/// A single timed run, in the format of a Chrome trace "complete" event.
+#[derive(Debug, Clone)]
+struct TraceEvent {
+    name: Arc<str>,
+    schedule: ScheduleTag,
+    is_system: bool,
+    start: Duration,
+    duration: Duration,
+    thread: u64,
+}
+
+/// State shared between the [`Profiler`] and the systems it times, which may be running on other threads.
+#[derive(Debug)]
+pub struct ProfilerHandle {
+    enabled: AtomicBool,
+    start: Instant,
+    samples: Mutex<Vec<TraceEvent>>,
+}
+
+impl ProfilerHandle {
+    fn record(&self, schedule: ScheduleTag, name: Arc<str>, is_system: bool, start: Instant) {
+        let event = TraceEvent {
+            name,
+            schedule,
+            is_system,
+            start: start.duration_since(self.start),
+            duration: start.elapsed(),
+            thread: thread_id(),
+        };
+
+        self.samples
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(event);
+    }
+}
+
+/// Numbers each thread the first time it records a sample, since [`std::thread::ThreadId`] can't be turned into a number.
+fn thread_id() -> u64 {
+    static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+    thread_local! {
+        static THREAD_ID: u64 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+    }
+
+    THREAD_ID.with(|id| *id)
+}
+
+/// Wraps a system built by the [`ScheduleBuilder`](crate::schedule::ScheduleBuilder), timing every run while the profiler is enabled.
+#[derive(Debug, Clone)]
+pub struct SystemProfiler {
+    handle: Arc<ProfilerHandle>,
+    schedule: ScheduleTag,
+    name: Arc<str>,
+}
+
+impl SystemProfiler {
+    pub(crate) fn new(handle: Arc<ProfilerHandle>, schedule: ScheduleTag, name: &str) -> Self {
+        Self {
+            handle,
+            schedule,
+            name: name.into(),
+        }
+    }
+}
+
+impl<S: System> Adapt<S> for SystemProfiler {
+    type In = S::In;
+    type Out = S::Out;
+
+    fn adapt(&mut self, input: Self::In, run_system: impl FnOnce(S::In) -> S::Out) -> Self::Out {
+        if !self.handle.enabled.load(Ordering::Relaxed) {
+            return run_system(input);
+        }
+
+        let start = Instant::now();
+        let output = run_system(input);
+        self.handle
+            .record(self.schedule, self.name.clone(), true, start);
+
+        output
+    }
+}
+
+/// Timing statistics for schedules, systems and frames. Inserted by the engine.
+///
+/// Enabled by default with the `editor_features` feature, and disabled otherwise. While disabled, nothing is timed.
+#[derive(Debug, Resource)]
+pub struct Profiler {
+    handle: Arc<ProfilerHandle>,
+    window: usize,
+    schedules: HashMap<ScheduleTag, TimingStats>,
+    systems: HashMap<(ScheduleTag, Arc<str>), TimingStats>,
+    frame_times: FrameHistogram,
+    trace: VecDeque<TraceEvent>,
+    trace_capacity: usize,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new(cfg!(feature = "editor_features"))
+    }
+}
+
+impl Profiler {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            handle: Arc::new(ProfilerHandle {
+                enabled: AtomicBool::new(enabled),
+                start: Instant::now(),
+                samples: Mutex::new(Vec::new()),
+            }),
+            window: DEFAULT_WINDOW,
+            schedules: HashMap::new(),
+            systems: HashMap::new(),
+            frame_times: FrameHistogram::default(),
+            trace: VecDeque::new(),
+            trace_capacity: DEFAULT_TRACE_CAPACITY,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.handle.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.handle.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// How many runs the rolling statistics cover.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// The handle that [`SystemProfiler`]s record into.
+    pub(crate) fn handle(&self) -> Arc<ProfilerHandle> {
+        self.handle.clone()
+    }
+
+    pub fn schedule_stats(&self, schedule: ScheduleTag) -> Option<&TimingStats> {
+        self.schedules.get(&schedule)
+    }
+
+    pub fn system_stats(&self, schedule: ScheduleTag, system: &str) -> Option<&TimingStats> {
+        self.systems.get(&(schedule, Arc::from(system)))
+    }
+
+    /// Iterates over every timed schedule.
+    pub fn iter_schedule_stats(&self) -> impl Iterator<Item = (&ScheduleTag, &TimingStats)> {
+        self.schedules.iter()
+    }
+
+    /// Iterates over every timed system, along with the schedule it's in.
+    pub fn iter_system_stats(&self) -> impl Iterator<Item = (ScheduleTag, &str, &TimingStats)> {
+        self.systems
+            .iter()
+            .map(|((schedule, name), stats)| (*schedule, name.as_ref(), stats))
+    }
+
+    pub fn frame_histogram(&self) -> &FrameHistogram {
+        &self.frame_times
+    }
+
+    /// Throws away every statistic and trace event recorded so far.
+    pub fn reset(&mut self) {
+        self.schedules.clear();
+        self.systems.clear();
+        self.frame_times = FrameHistogram::default();
+        self.trace.clear();
+        self.handle
+            .samples
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+
+    /// Records how long a schedule took, then collects the timings of the systems that ran during it.
+    pub(crate) fn record_schedule(&mut self, schedule: ScheduleTag, start: Instant) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        self.handle
+            .record(schedule, format!("{:?}", schedule).into(), false, start);
+
+        let samples = std::mem::take(
+            &mut *self
+                .handle
+                .samples
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+
+        for sample in samples {
+            let window = self.window;
+            let stats = if sample.is_system {
+                self.systems
+                    .entry((sample.schedule, sample.name.clone()))
+                    .or_insert_with(|| TimingStats::new(window))
+            } else {
+                self.schedules
+                    .entry(sample.schedule)
+                    .or_insert_with(|| TimingStats::new(window))
+            };
+            stats.record(sample.duration);
+
+            if self.trace.len() >= self.trace_capacity {
+                self.trace.pop_front();
+            }
+            self.trace.push_back(sample);
+        }
+    }
+
+    /// Records the real time between two updates.
+    pub(crate) fn record_frame_time(&mut self, frame_time: Duration) {
+        if self.is_enabled() {
+            self.frame_times.record(frame_time);
+        }
+    }
+
+    /// Returns every kept trace event in the Chrome trace-event JSON format.
+    pub fn chrome_trace(&self) -> serde_json::Value {
+        let events = self
+            .trace
+            .iter()
+            .map(|event| {
+                serde_json::json!({
+                    "name": event.name.as_ref(),
+                    "cat": if event.is_system { "system" } else { "schedule" },
+                    "ph": "X",
+                    "ts": event.start.as_secs_f64() * 1_000_000.0,
+                    "dur": event.duration.as_secs_f64() * 1_000_000.0,
+                    "pid": 1,
+                    "tid": event.thread,
+                    "args": { "schedule": format!("{:?}", event.schedule) },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        })
+    }
+
+    /// Writes [`Profiler::chrome_trace`] to a file.
+    pub fn export_chrome_trace(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        serde_json::to_writer(file, &self.chrome_trace()).map_err(std::io::Error::from)
+    }
+
+    /// A human readable table of every schedule and system, slowest average first, followed by the frame time histogram.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{:<40} {:>10} {:>10} {:>10}\n",
+            "name", "min (ms)", "avg (ms)", "max (ms)"
+        );
+
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+        let mut schedules = self.iter_schedule_stats().collect::<Vec<_>>();
+        schedules.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.avg()));
+
+        for (schedule, stats) in schedules {
+            report += &format!(
+                "{:<40} {:>10.3} {:>10.3} {:>10.3}\n",
+                format!("{:?}", schedule),
+                ms(stats.min()),
+                ms(stats.avg()),
+                ms(stats.max())
+            );
+
+            let mut systems = self
+                .iter_system_stats()
+                .filter(|(system_schedule, _, _)| system_schedule == schedule)
+                .collect::<Vec<_>>();
+            systems.sort_by_key(|(_, _, stats)| std::cmp::Reverse(stats.avg()));
+
+            for (_, name, stats) in systems {
+                report += &format!(
+                    "  {:<38} {:>10.3} {:>10.3} {:>10.3}\n",
+                    name,
+                    ms(stats.min()),
+                    ms(stats.avg()),
+                    ms(stats.max())
+                );
+            }
+        }
+
+        report += "\nframe time (ms): frames\n";
+        let last_bucket = FRAME_HISTOGRAM_BUCKETS - 1;
+        for (index, count) in self.frame_times.buckets().iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+
+            if index == last_bucket {
+                report += &format!("{:>3}+    : {}\n", last_bucket, count);
+            } else {
+                report += &format!("{:>3}-{:<3}: {}\n", index, index + 1, count);
+            }
+        }
+
+        report
+    }
+}
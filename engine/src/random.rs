@@ -0,0 +1,194 @@
+//! Contains the [`Random`] resource, the only source of randomness game logic should use, so that every random sequence can be reproduced.
+//!
+//! Randomness is split into named [`RngStream`]s, such as [`GAMEPLAY_STREAM`] and [`COSMETIC_STREAM`], so that purely visual randomness
+//! (like particles) can't change the outcome of gameplay randomness by pulling numbers out of the same sequence.
+//! Every stream is derived from one seed, and the state of every stream can be serialized.
+//!
+//! Scenes store the [`Random`] state in their save data. Loading a scene that has one replaces the resource with it,
+//! so saving and reloading a scene continues the exact same random sequences.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use bevy_ecs::system::Resource;
+use serde::{Deserialize, Serialize};
+
+/// The seed used by [`Random::default`], until a scene with its own seed is loaded.
+pub const DEFAULT_SEED: u64 = 0x6E69_6E6A_615F_6667;
+
+/// The stream for randomness that affects the outcome of the game, such as damage rolls or enemy decisions.
+pub static GAMEPLAY_STREAM: &str = "gameplay";
+/// The stream for randomness that is purely visual, such as particles or screen shake.
+pub static COSMETIC_STREAM: &str = "cosmetic";
+
+/// A single sequence of random numbers, using the PCG32 algorithm.
+///
+/// The same seed and stream always produce the same numbers, on every platform.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngStream {
+    state: u64,
+    /// Picks which of the generator's sequences is used, must be odd
+    increment: u64,
+}
+
+impl RngStream {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    /// Creates a stream from a seed. Different `stream` values give unrelated sequences for the same seed.
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rotation = (old >> 59) as u32;
+
+        xorshifted.rotate_right(rotation)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    /// Returns a number from `0.0` up to, but not including, `1.0`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Returns a number inside of `range`, without any bias towards smaller numbers. Returns `range.start` if the range is empty.
+    pub fn range_u32(&mut self, range: Range<u32>) -> u32 {
+        if range.is_empty() {
+            return range.start;
+        }
+
+        let bound = range.end - range.start;
+        // numbers below this would make some results more likely than others
+        let threshold = bound.wrapping_neg() % bound;
+
+        loop {
+            let value = self.next_u32();
+
+            if value >= threshold {
+                return range.start + value % bound;
+            }
+        }
+    }
+
+    /// Same as [`RngStream::range_u32`], for signed numbers.
+    pub fn range_i32(&mut self, range: Range<i32>) -> i32 {
+        if range.is_empty() {
+            return range.start;
+        }
+
+        let bound = range.end.abs_diff(range.start);
+
+        range.start.wrapping_add(self.range_u32(0..bound) as i32)
+    }
+
+    /// Returns a number from `range.start` up to, but not including, `range.end`.
+    pub fn range_f32(&mut self, range: Range<f32>) -> f32 {
+        range.start + self.next_f32() * (range.end - range.start)
+    }
+
+    /// Returns true with the given probability, from `0.0` (never) to `1.0` (always).
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Picks a random item out of the slice, or [`None`] if it's empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+
+        items.get(self.range_u32(0..items.len() as u32) as usize)
+    }
+
+    /// Shuffles the slice in place.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.range_u32(0..i as u32 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// Holds every named [`RngStream`], all derived from a single seed.
+///
+/// Streams are created the first time they're used, so adding a new stream never changes the numbers of existing ones.
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
+pub struct Random {
+    seed: u64,
+    #[serde(default)]
+    streams: BTreeMap<String, RngStream>,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: BTreeMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts every stream over using a new seed.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    /// Returns the stream with the given name, creating it if it hasn't been used yet.
+    pub fn stream(&mut self, name: &str) -> &mut RngStream {
+        let seed = self.seed;
+
+        self.streams
+            .entry(name.to_owned())
+            .or_insert_with(|| RngStream::new(seed, stream_id(name)))
+    }
+
+    /// Returns the [`GAMEPLAY_STREAM`].
+    pub fn gameplay(&mut self) -> &mut RngStream {
+        self.stream(GAMEPLAY_STREAM)
+    }
+
+    /// Returns the [`COSMETIC_STREAM`].
+    pub fn cosmetic(&mut self) -> &mut RngStream {
+        self.stream(COSMETIC_STREAM)
+    }
+
+    /// Returns the names of every stream that has been used so far.
+    pub fn stream_names(&self) -> impl Iterator<Item = &String> {
+        self.streams.keys()
+    }
+}
+
+/// Hashes a stream name with FNV-1a, which unlike the standard library's hasher is guaranteed to never change between versions.
+fn stream_id(name: &str) -> u64 {
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -4,18 +4,32 @@
 
 // Hi! If your reading this, welcome to my fun little project. Some shenanigans are afoot!
 
+use crate::crash;
+use crate::crash::{CrashRecoveryConfig, PanicHandler};
+use crate::events;
+use crate::events::{FocusChanged, WindowResized};
+use crate::freeze::FreezeType;
 use crate::input::KeycodeType;
 use crate::logging;
+use crate::profiler::Profiler;
+use crate::random::Random;
+use crate::scene;
 use crate::scene::SceneError;
 use crate::scene::SceneManager;
+use crate::schedule::ScheduleBuilder;
 use crate::schedule::ScheduleTag;
 use crate::space::Vector2;
+use crate::state;
+use crate::time::PlayTime;
 use crate::Camera;
 use crate::EngineConfig;
 use crate::EngineConfigError;
 use crate::GgezInterface;
 use crate::Input;
 use crate::SomeError;
+use crate::Time;
+use bevy_ecs::schedule::Schedules;
+use bevy_ecs::world::error::TryRunScheduleError;
 use bevy_ecs::world::*;
 use ggez::event::{self, EventHandler};
 use ggez::graphics::{self, Color};
@@ -23,8 +37,10 @@ use ggez::GameError;
 use ggez::{Context, GameResult};
 use log::debug;
 use log::*;
+use std::any::Any;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::Instant;
 
 /// A basic container-struct that handles [`ggez`]'s events and interfaces with [`bevy_ecs`]'s ECS to provide full engine functionality.
 /// Use the [`components::context::WorldInfo`] component in a query, then use `WorldInfo.game_info.` to access.
@@ -42,6 +58,8 @@ where
 {
     pub world: World,
     pub ticks_per_second: u32,
+    /// Kept outside of the world, since a panic can happen while [`GgezInterface`] is taken out of it.
+    crash_recovery: CrashRecoveryConfig,
 }
 
 impl GameRoot {
@@ -69,32 +87,81 @@ impl GameRoot {
 
     /// Loads and initialized essential data, and calls the [`ScheduleTag::Init`] systems
     ///
-    /// To pass in a `config`, create a static [`EngineConfig`] and pass in a reference
-    pub fn new(context: &mut Context, config: &'static EngineConfig) -> Result<Self, SomeError> {
+    /// To get a `config`, load one from a file using [`EngineConfig::load`], or create one in code.
+    pub fn new(context: &mut Context, config: EngineConfig) -> Result<Self, SomeError> {
+        let game_info = GgezInterface::new(context, config.clone());
+
+        let mut root = GameRoot::build(game_info, Input::load(), config)?;
+        GameRoot::update_context(&mut root, context);
+
+        Ok(root)
+    }
+
+    /// Creates a [`GameRoot`] without a [`ggez::Context`], for stepping the world in tests and tools.
+    ///
+    /// The world is built from `config` the same way as [`GameRoot::new`], including loading the configured scene and running [`ScheduleTag::Init`],
+    /// but nothing is ever rendered and time only advances when [`step_ticks`](GameRoot::step_ticks) or [`step_frame`](GameRoot::step_frame) is called.
+    ///
+    /// [`Input`] starts out without any actions, since there is no window to receive key events from and the key file isn't loaded.
+    /// Register every action your systems read with [`Input::new_action`] before stepping, as systems that unwrap
+    /// [`Input::get_action`] will panic on the first tick otherwise.
+    pub fn new_headless(config: EngineConfig) -> Result<Self, SomeError> {
+        let game_info = GgezInterface::new_headless(config.clone());
+
+        GameRoot::build(game_info, Input::headless(), config)
+    }
+
+    /// Shared setup between [`GameRoot::new`] and [`GameRoot::new_headless`]
+    fn build(
+        game_info: GgezInterface,
+        input: Input,
+        config: EngineConfig,
+    ) -> Result<Self, SomeError> {
+        config.validate().map_err(SomeError::EngineConfig)?;
+
         let mut world = World::new();
 
-        if let Err(err) = log::set_logger(&logging::LOGGER) {
-            eprintln!("Failed to create logger! [{}]", err.to_string())
-        }
+        // the logger can only be set once, but it can be reconfigured for every new root
+        let _ = log::set_logger(&logging::LOGGER);
 
-        log::set_max_level(LevelFilter::Trace);
+        let log_history = logging::LOGGER
+            .configure(&config.logger)
+            .map_err(SomeError::Misc)?;
 
-        info!("Begin log");
+        crash::install_panic_hook();
 
-        // crate::schedule::add_schedules(&mut world, (config.schedule_builder_functions)());
+        info!("Begin log");
 
-        let game_info = GgezInterface::new(context, config.clone());
         World::insert_resource(&mut world, game_info);
 
-        let input = Input::load();
         World::insert_resource(&mut world, input);
 
+        World::insert_resource(&mut world, log_history);
+
         let scene_manager = SceneManager::default();
         World::insert_resource(&mut world, scene_manager);
 
         let camera = Camera::default();
         World::insert_resource(&mut world, camera);
 
+        let time = Time::new(config.ticks_per_second);
+        World::insert_resource(&mut world, time);
+        World::insert_resource(&mut world, PlayTime::default());
+
+        // replaced by the random state saved in the scene, if it has one
+        World::insert_resource(&mut world, Random::default());
+
+        let profiler = Profiler::default();
+        let mut schedule_builder = ScheduleBuilder::default();
+        schedule_builder.set_profiler(profiler.handle());
+        World::insert_resource(&mut world, profiler);
+        World::insert_resource(&mut world, schedule_builder);
+
+        crate::events::register_engine_events(&mut world);
+
+        #[cfg(feature = "editor_features")]
+        World::insert_resource(&mut world, crate::frame_advance::FrameAdvance::default());
+
         trace!("Created main resources");
 
         crate::register_scene_types(&mut world);
@@ -102,10 +169,16 @@ impl GameRoot {
         let mut root = GameRoot {
             world,
             ticks_per_second: config.ticks_per_second,
+            crash_recovery: config.crash_recovery.clone(),
         };
-        GameRoot::update_context(&mut root, context);
 
-        (config.world_init)(&mut root.world);
+        crate::plugin::build_plugins(&config.plugins, &mut root.world);
+
+        crate::schedule::add_schedules(&mut root.world).map_err(SomeError::Schedule)?;
+
+        config
+            .validate_world(&root.world)
+            .map_err(SomeError::EngineConfig)?;
 
         trace!("Initialized world and created game root");
 
@@ -138,6 +211,11 @@ impl GameRoot {
                 Ok(())
             })?;
 
+        // tools and tests using a headless root shouldn't be offered someone else's crashed scenes
+        if config.crash_recovery.enabled && !root.engine().is_headless() {
+            crash::offer_restore(&mut root.world, &config.crash_recovery);
+        }
+
         if let Err(err) = root.world.try_run_schedule(ScheduleTag::Init) {
             log::info!("No Init schedule found.");
         } else {
@@ -155,38 +233,66 @@ impl GameRoot {
 
         Ok(root)
     }
-}
 
-impl EventHandler for GameRoot {
-    /// Passes guard clauses depending on the TPS, checks for debug logic, updates resources then runs [`ScheduleTag::Tick`]
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
-        #[cfg(feature = "editor_features")]
-        if self.engine().debug_mode {
-            self.world.run_schedule(ScheduleTag::DebugGUI)
+    /// Runs `count` ticks back to back, ignoring the tick rate.
+    ///
+    /// Meant for headless roots, see [`GameRoot::new_headless`]. Only the tick counter and game time in [`Time`] move forward, real time does not.
+    pub fn step_ticks(&mut self, count: u32) {
+        for _ in 0..count {
+            self.tick();
         }
+    }
 
-        // FPS limiter: read `check_update_time` docs for more details
-        if !ctx.time.check_update_time(self.ticks_per_second) {
-            return GameResult::Ok(());
+    /// Runs the [`ScheduleTag::Frame`] schedule once without a canvas, so nothing is drawn.
+    ///
+    /// Rendering systems should check for a canvas before drawing, since [`GgezInterface::get_canvas_mut`] will return [`None`] here.
+    pub fn step_frame(&mut self) {
+        if let Err(err) = self.try_run_schedule(ScheduleTag::Frame) {
+            trace!("No Frame schedule found.");
         }
-        let remaining_update_time = ctx.time.remaining_update_time();
-        if remaining_update_time > Duration::from_millis(80) {
-            debug!("Lag spike of 5+ frames [{:?}]", remaining_update_time);
-
-            if remaining_update_time > Duration::from_millis(10000) {
-                log::debug!(
-                    "Remaining update time is over 10,000 milliseconds! {}",
-                    remaining_update_time.as_millis()
-                );
-            }
-            while ctx.time.remaining_update_time() > Duration::from_millis(16) {
-                ctx.time.check_update_time(self.ticks_per_second);
+
+        #[cfg(feature = "editor_features")]
+        if self.engine().debug_mode {
+            if let Err(err) = self.try_run_schedule(ScheduleTag::DebugFrame) {
+                trace!("No DebugFrame schedule found.");
             }
         }
+    }
 
-        self.update_context(ctx);
+    /// Runs a schedule, timing it and its systems if the [`Profiler`] is enabled.
+    fn try_run_schedule(&mut self, tag: ScheduleTag) -> Result<(), TryRunScheduleError> {
+        let start = Instant::now();
+
+        self.world.try_run_schedule(tag)?;
+
+        self.world
+            .resource_mut::<Profiler>()
+            .record_schedule(tag, start);
+
+        Ok(())
+    }
+
+    /// Same as [`GameRoot::try_run_schedule`], but panics if the schedule doesn't exist.
+    fn run_schedule(&mut self, tag: ScheduleTag) {
+        if let Err(err) = self.try_run_schedule(tag) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Updates events, input and [`GameState`](crate::state::GameState)s and counts down [`FreezeType::IMPACT`],
+    /// then runs [`ScheduleTag::Tick`] (or [`ScheduleTag::FreezeTick`] while frozen, if it exists) once.
+    fn tick(&mut self) {
+        events::update_events(&mut self.world);
+        events::send_freeze_events(&mut self.world);
 
         self.world.resource_mut::<Input>().process_key_queue();
+        events::send_action_events(&mut self.world);
+
+        // a state that freezes the game should already be frozen for this tick's schedules
+        state::apply_state_transitions(&mut self.world);
+
+        // scenes finish loading before the schedules run, so the tick that lifts the loading freeze already sees the whole scene
+        scene::update_scene_loads(&mut self.world);
 
         #[cfg(feature = "editor_features")]
         if let Some(action) = self.world.resource::<Input>().get_action("enabledebugmode") {
@@ -197,22 +303,147 @@ impl EventHandler for GameRoot {
             }
         }
 
-        if self.engine().is_freeze_frame() {
-            self.world.run_schedule(ScheduleTag::FreezeTick)
+        // a stepped tick runs through the debug freeze without leaving it, so listeners don't see the game unpause and pause again
+        #[cfg(feature = "editor_features")]
+        {
+            self.engine_mut().debug_step =
+                crate::frame_advance::update_frame_advance(&mut self.world);
+        }
+
+        self.engine_mut().tick_freeze();
+
+        // time stands still while paused by frame-advance
+        if !matches!(self.engine().freeze_mode(), FreezeType::DEBUG(_)) {
+            self.world.resource_mut::<Time>().advance_tick();
+        }
+
+        // FreezeTick is optional, and replaces Tick while frozen only if it exists
+        let run_freeze_tick = self.engine().is_freeze_frame()
+            && self
+                .world
+                .resource::<Schedules>()
+                .contains(ScheduleTag::FreezeTick);
+
+        if run_freeze_tick {
+            self.run_schedule(ScheduleTag::FreezeTick)
         } else {
-            self.world.run_schedule(ScheduleTag::Tick)
+            self.run_schedule(ScheduleTag::Tick)
         }
 
+        #[cfg(feature = "editor_features")]
+        {
+            self.engine_mut().debug_step = false;
+        }
+
+        events::send_freeze_events(&mut self.world);
+
         #[cfg(feature = "editor_features")]
         if self.engine().debug_mode {
-            self.world.run_schedule(ScheduleTag::DebugTick)
+            self.run_schedule(ScheduleTag::DebugTick)
+        }
+    }
+
+    /// Sends [`FocusChanged`], then pushes [`FreezeType::UNFOCUSED`] when focus is lost if [`EngineConfig::freeze_on_unfocus`] is set,
+    /// and removes it once focus returns.
+    pub(crate) fn focus_changed(&mut self, gained: bool) {
+        events::send_event(&mut self.world, FocusChanged { focused: gained });
+
+        let mut engine = self.engine_mut();
+
+        if gained {
+            engine.remove_freeze(&FreezeType::UNFOCUSED);
+        } else if engine.get_engine_config().freeze_on_unfocus
+            && !engine.has_freeze(&FreezeType::UNFOCUSED)
+        {
+            engine.push_freeze(FreezeType::UNFOCUSED);
+        }
+    }
+
+    /// Sends [`WindowResized`]. A window resized to nothing has been minimized.
+    ///
+    /// Pushes [`FreezeType::MINIMIZED`] if [`EngineConfig::freeze_on_minimize`] is set. Otherwise, minimizing overrides
+    /// [`EngineConfig::freeze_on_unfocus`], so [`FreezeType::UNFOCUSED`] is removed.
+    pub(crate) fn window_resized(&mut self, width: f32, height: f32) {
+        events::send_event(&mut self.world, WindowResized { width, height });
+
+        let mut engine = self.engine_mut();
+
+        let minimized = width <= 0.0 || height <= 0.0;
+
+        if !minimized {
+            engine.remove_freeze(&FreezeType::MINIMIZED);
+        } else if engine.get_engine_config().freeze_on_minimize {
+            if !engine.has_freeze(&FreezeType::MINIMIZED) {
+                engine.push_freeze(FreezeType::MINIMIZED);
+            }
+        } else {
+            engine.remove_freeze(&FreezeType::UNFOCUSED);
+        }
+    }
+
+    /// Saves every loaded scene and a crash report, see [`crash::write_recovery`], then continues panicking.
+    ///
+    /// If a terminal is attached and a [`PanicHandler`] exists, it is run before the panic continues.
+    fn recover_from_panic(&mut self, payload: Box<dyn Any + Send>) -> ! {
+        let report = crash::take_panic_report(payload.as_ref());
+
+        if self.crash_recovery.enabled {
+            match crash::write_recovery(&mut self.world, &self.crash_recovery, &report) {
+                Ok(folder) => error!(
+                    "Saved loaded scenes and a crash report to {}",
+                    folder.display()
+                ),
+                Err(err) => error!("Could not write crash recovery [{}]", err),
+            }
+        }
+
+        if crash::is_interactive() {
+            if let Some(handler) = self.world.get_resource::<PanicHandler>().copied() {
+                (handler.0)(&mut self.world, &report);
+            }
+        }
+
+        log::logger().flush();
+
+        resume_unwind(payload)
+    }
+
+    /// The body of [`EventHandler::update`], run inside of [`catch_unwind`] so that a panic can be recovered from.
+    fn update_world(&mut self, ctx: &mut Context) -> GameResult {
+        #[cfg(feature = "editor_features")]
+        if self.engine().debug_mode {
+            self.run_schedule(ScheduleTag::DebugGUI)
+        }
+
+        self.world
+            .resource_mut::<Time>()
+            .advance_real(ctx.time.delta());
+
+        self.world.resource_mut::<PlayTime>().0 += ctx.time.delta();
+
+        self.world
+            .resource_mut::<Profiler>()
+            .record_frame_time(ctx.time.delta());
+
+        self.update_context(ctx);
+
+        let mut ticks_ran = 0;
+        while self.world.resource_mut::<Time>().consume_tick() {
+            if ticks_ran >= crate::time::MAX_TICKS_PER_UPDATE {
+                let dropped = self.world.resource_mut::<Time>().drop_backlog();
+                debug!("Lag spike of {}+ ticks [{:?} skipped]", ticks_ran, dropped);
+                break;
+            }
+
+            self.tick();
+            ticks_ran += 1;
         }
 
         Ok(())
     }
 
-    /// Creates a new [`Canvas`](graphics::Canvas) and calls the [`ScheduleTag::Frame`] schedule as often as possible
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+    /// The body of [`EventHandler::draw`], run inside of [`catch_unwind`] so that a panic can be recovered from.
+    fn draw_world(&mut self, ctx: &mut Context) -> GameResult {
         self.engine_mut().set_canvas(graphics::Canvas::from_frame(
             ctx,
             Color {
@@ -225,13 +456,13 @@ impl EventHandler for GameRoot {
 
         self.update_context(ctx);
 
-        self.world.run_schedule(ScheduleTag::Frame);
+        self.run_schedule(ScheduleTag::Frame);
 
         #[cfg(feature = "editor_features")]
         if self.engine().debug_mode {
             // self.world.run_schedule(ScheduleTag::Gizmos);
 
-            self.world.run_schedule(ScheduleTag::DebugFrame);
+            self.run_schedule(ScheduleTag::DebugFrame);
         }
 
         self.engine_mut()
@@ -239,6 +470,28 @@ impl EventHandler for GameRoot {
         .expect("game_info.current_canvas should never be moved during system running! If you took it, please undo that and make a clone or borrow instead of taking ownership over it.")
         .finish(&mut ctx.gfx)
     }
+}
+
+impl EventHandler for GameRoot {
+    /// Updates [`Time`], then runs as many ticks as the fixed timestep allows. See [`GameRoot::tick`] for what happens each tick.
+    ///
+    /// If anything panics, loaded scenes are saved before the panic continues. See [`crash`] for details.
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        match catch_unwind(AssertUnwindSafe(|| self.update_world(ctx))) {
+            Ok(result) => result,
+            Err(payload) => self.recover_from_panic(payload),
+        }
+    }
+
+    /// Creates a new [`Canvas`](graphics::Canvas) and calls the [`ScheduleTag::Frame`] schedule as often as possible
+    ///
+    /// Panics are handled the same way as in [`update`](EventHandler::update).
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        match catch_unwind(AssertUnwindSafe(|| self.draw_world(ctx))) {
+            Ok(result) => result,
+            Err(payload) => self.recover_from_panic(payload),
+        }
+    }
 
     fn mouse_button_down_event(
         &mut self,
@@ -364,7 +617,10 @@ impl EventHandler for GameRoot {
         Ok(())
     }
 
-    fn focus_event(&mut self, _ctx: &mut Context, _gained: bool) -> Result<(), ggez::GameError> {
+    /// See [`GameRoot::focus_changed`].
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) -> Result<(), ggez::GameError> {
+        self.focus_changed(gained);
+
         Ok(())
     }
 
@@ -376,12 +632,15 @@ impl EventHandler for GameRoot {
         Ok(false)
     }
 
+    /// See [`GameRoot::window_resized`].
     fn resize_event(
         &mut self,
         _ctx: &mut Context,
-        _width: f32,
-        _height: f32,
+        width: f32,
+        height: f32,
     ) -> Result<(), ggez::GameError> {
+        self.window_resized(width, height);
+
         Ok(())
     }
 
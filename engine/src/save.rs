@@ -0,0 +1,563 @@
+//! Save slots, which store the player's progress separately from the scene files that levels are authored in.
+//!
+//! Scene files describe how a level starts, while a save slot only stores what the player changed. Components opt in to being saved
+//! with `#[reflect(SaveGame)]` or [`register_saved_component`](crate::register_saved_component), and resources with
+//! [`register_saved_resource`](crate::register_saved_resource). Everything else is left out of the save.
+//!
+//! Loading a slot first reloads every saved scene from its authored file, then overlays the save on top of it. Saved entities are matched up
+//! with authored ones by their [`SceneEntityID`], or by name for authored entities whose ID wasn't stored in the scene file:
+//! * Saved components replace the authored ones, and saved component types the entity no longer had are removed.
+//! * Entities that were spawned during play are spawned again, and authored entities that were despawned during play are despawned again.
+//! * Saved resources replace the current ones, along with [`PlayTime`].
+//!
+//! Slots are stored as `<slot>.json` inside of [`EngineConfig::save_folder`](crate::EngineConfig), which defaults to a `saves` folder
+//! inside of [`ggez`]'s user data directory. Each one holds a [`SaveMetadata`] with the [`SAVE_FORMAT_VERSION`] it was written with.
+//! Saves are written to `<slot>.json.tmp` first and then moved over the slot, so a save that fails part way leaves the previous one intact.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy_ecs::entity::Entity;
+use bevy_ecs::reflect::{ReflectComponent, ReflectResource};
+use bevy_ecs::world::{EntityRef, World};
+use bevy_reflect::serde::Serializable;
+use bevy_reflect::{FromType, Reflect, ReflectDeserialize, ReflectSerialize, TypeRegistry};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::render::DowncastInsert;
+use crate::scene::{
+    add_entity_to_scene, Scene, SceneData, SceneEntityID, SceneError, SceneManager,
+};
+use crate::time::PlayTime;
+use crate::GgezInterface;
+
+/// The version written into every new save. Saves with a newer version than this are refused when loading.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+/// Marks a component or resource as part of the player's progress, so that it's stored in save slots.
+///
+/// Add it with `#[reflect(SaveGame)]`, or through [`register_saved_component`](crate::register_saved_component)
+/// and [`register_saved_resource`](crate::register_saved_resource).
+#[derive(Debug, Clone)]
+pub struct ReflectSaveGame;
+
+impl<T: Reflect> FromType<T> for ReflectSaveGame {
+    fn from_type() -> Self {
+        Self
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    /// The engine is running headless and no [`EngineConfig::save_folder`](crate::EngineConfig) was set.
+    NoSaveFolder,
+    /// Slot names can't be empty or contain path separators, since they're used as file names.
+    InvalidSlotName(String),
+    /// The slot has no save file.
+    MissingSlot(String),
+    IOError(std::io::Error),
+    /// The save file could not be parsed.
+    ParseFailure(String),
+    /// The save was written by a newer version of the game.
+    UnsupportedVersion {
+        found: u32,
+        supported: u32,
+    },
+    /// A saved scene has no authored file to reload it from.
+    MissingScenePath(String),
+    /// A saved component or resource has not been registered, [`String`] is its type path.
+    MissingTypeRegistry(String),
+    SerializeFailure(String),
+    Scene(SceneError),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::NoSaveFolder => write!(
+                f,
+                "No save folder was set, and there is no user data directory while headless"
+            ),
+            SaveError::InvalidSlotName(slot) => write!(f, "Invalid save slot name [{}]", slot),
+            SaveError::MissingSlot(slot) => write!(f, "No save found in slot [{}]", slot),
+            SaveError::IOError(err) => write!(f, "Save IO error [{}]", err),
+            SaveError::ParseFailure(err) => write!(f, "Could not parse save [{}]", err),
+            SaveError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Save was made with format version {}, but only versions up to {} are supported",
+                found, supported
+            ),
+            SaveError::MissingScenePath(scene) => {
+                write!(f, "Scene {} has no file to reload it from", scene)
+            }
+            SaveError::MissingTypeRegistry(path) => write!(f, "Missing type registry [{}]", path),
+            SaveError::SerializeFailure(err) => write!(f, "Save serialize failure [{}]", err),
+            SaveError::Scene(err) => write!(f, "Save scene error [{}]", err),
+        }
+    }
+}
+
+impl From<SceneError> for SaveError {
+    fn from(value: SceneError) -> Self {
+        SaveError::Scene(value)
+    }
+}
+
+/// Describes a save, and can be read without loading it through [`list_slots`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveMetadata {
+    /// The [`SAVE_FORMAT_VERSION`] the save was written with
+    pub version: u32,
+    pub slot: String,
+    /// When the save was written, in seconds since the unix epoch
+    pub saved_at: u64,
+    /// The [`PlayTime`] when the save was written
+    pub play_time: Duration,
+}
+
+/// The contents of a save slot's file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub metadata: SaveMetadata,
+    /// Keyed by scene name
+    #[serde(default)]
+    pub scenes: HashMap<String, SavedScene>,
+    /// Keyed by type path
+    #[serde(default)]
+    pub resources: HashMap<String, Value>,
+}
+
+/// The saved state of a single scene.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedScene {
+    /// The authored scene file that the save is applied on top of
+    pub path: Option<PathBuf>,
+    /// Every entity in the scene.
+    ///
+    /// Entities without any saved components are still listed, so that entities despawned during play stay despawned.
+    pub entities: Vec<SavedEntity>,
+}
+
+/// The saved state of a single entity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedEntity {
+    /// Matches the entity up with its authored version, even if either was renamed
+    pub id: SceneEntityID,
+    /// Used to match the entity up when no authored entity has its ID
+    pub name: String,
+    /// Keyed by type path
+    pub components: HashMap<String, Value>,
+}
+
+/// Returns the folder that save slots are stored in.
+pub fn save_folder(world: &World) -> Result<PathBuf, SaveError> {
+    let engine = world.resource::<GgezInterface>();
+
+    if let Some(folder) = &engine.get_engine_config().save_folder {
+        return Ok(PathBuf::from(folder));
+    }
+
+    engine
+        .try_get_context()
+        .map(|context| context.fs.user_data_dir().join("saves"))
+        .ok_or(SaveError::NoSaveFolder)
+}
+
+/// Returns the file a slot is stored in, whether or not it exists yet.
+pub fn slot_path(world: &World, slot: &str) -> Result<PathBuf, SaveError> {
+    if slot.is_empty() || slot.contains(['/', '\\', ':']) || slot.starts_with('.') {
+        return Err(SaveError::InvalidSlotName(slot.to_owned()));
+    }
+
+    Ok(save_folder(world)?.join(format!("{}.json", slot)))
+}
+
+/// Writes every loaded scene's saved components and every saved resource into the slot, replacing whatever it held before.
+pub fn save_slot(world: &mut World, slot: &str) -> Result<SaveMetadata, SaveError> {
+    let path = slot_path(world, slot)?;
+
+    let metadata = SaveMetadata {
+        version: SAVE_FORMAT_VERSION,
+        slot: slot.to_owned(),
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        play_time: world
+            .get_resource::<PlayTime>()
+            .map(|play_time| play_time.0)
+            .unwrap_or_default(),
+    };
+
+    let manager = world.resource::<SceneManager>();
+    let registry = &manager.type_registry;
+
+    let mut scenes = HashMap::new();
+
+    for (scene_name, scene_entity) in &manager.current_scenes {
+        let scene = world
+            .get::<Scene>(*scene_entity)
+            .ok_or(SceneError::NoSceneComponent)?;
+
+        let mut entities = Vec::new();
+
+        for entity in &scene.entities {
+            let Some(scene_data) = world.get::<SceneData>(*entity) else {
+                continue;
+            };
+
+            entities.push(SavedEntity {
+                id: scene_data.entity_id,
+                name: scene_data.entity_name.clone(),
+                components: save_components(registry, world.entity(*entity))?,
+            });
+        }
+
+        scenes.insert(
+            scene_name.clone(),
+            SavedScene {
+                path: scene.save_data_path().cloned(),
+                entities,
+            },
+        );
+    }
+
+    let mut resources = HashMap::new();
+
+    for registration in registry.iter() {
+        let (Some(_), Some(reflect_resource)) = (
+            registration.data::<ReflectSaveGame>(),
+            registration.data::<ReflectResource>(),
+        ) else {
+            continue;
+        };
+
+        let Some(resource) = reflect_resource.reflect(world) else {
+            continue;
+        };
+
+        resources.insert(
+            registration.type_info().type_path().to_owned(),
+            serialize_reflect(registry, resource)?,
+        );
+    }
+
+    let save = SaveFile {
+        metadata: metadata.clone(),
+        scenes,
+        resources,
+    };
+
+    std::fs::create_dir_all(path.parent().unwrap_or(Path::new("."))).map_err(SaveError::IOError)?;
+
+    let temp_path = path.with_extension("json.tmp");
+
+    let file = std::fs::File::create(&temp_path).map_err(SaveError::IOError)?;
+    serde_json::to_writer_pretty(file, &save)
+        .map_err(|err| SaveError::SerializeFailure(err.to_string()))?;
+    std::fs::rename(&temp_path, &path).map_err(SaveError::IOError)?;
+
+    trace!("Saved slot {} to {}", slot, path.display());
+
+    Ok(metadata)
+}
+
+/// Reloads every scene stored in the slot from its authored file, then applies the save on top of them.
+///
+/// Loaded scenes that aren't part of the save are left alone.
+pub fn load_slot(world: &mut World, slot: &str) -> Result<SaveMetadata, SaveError> {
+    let save = read_slot(world, slot)?;
+
+    if save.metadata.version > SAVE_FORMAT_VERSION {
+        return Err(SaveError::UnsupportedVersion {
+            found: save.metadata.version,
+            supported: SAVE_FORMAT_VERSION,
+        });
+    }
+
+    world.resource_scope::<SceneManager, Result<(), SaveError>>(|world, mut manager| {
+        for (scene_name, saved_scene) in &save.scenes {
+            let path = saved_scene
+                .path
+                .clone()
+                .ok_or_else(|| SaveError::MissingScenePath(scene_name.clone()))?;
+
+            if manager.current_scenes.contains_key(scene_name) {
+                manager.unload_scene_by_name(world, scene_name)?;
+            }
+
+            let scene_entity = manager.load_scene(world, path)?;
+
+            apply_saved_scene(world, &manager.type_registry, scene_entity, saved_scene)?;
+        }
+
+        for (type_path, value) in &save.resources {
+            let registration = manager
+                .type_registry
+                .get_with_type_path(type_path)
+                .ok_or_else(|| SaveError::MissingTypeRegistry(type_path.clone()))?;
+
+            let reflect_resource = registration
+                .data::<ReflectResource>()
+                .ok_or_else(|| SaveError::MissingTypeRegistry(type_path.clone()))?;
+
+            let resource = deserialize_reflect(&manager.type_registry, type_path, value)?;
+
+            reflect_resource.apply_or_insert(world, resource.as_ref());
+        }
+
+        Ok(())
+    })?;
+
+    world.insert_resource(PlayTime(save.metadata.play_time));
+
+    trace!("Loaded slot {}", slot);
+
+    Ok(save.metadata)
+}
+
+/// Reads the save file in a slot without applying it.
+pub fn read_slot(world: &World, slot: &str) -> Result<SaveFile, SaveError> {
+    let path = slot_path(world, slot)?;
+
+    if !path.exists() {
+        return Err(SaveError::MissingSlot(slot.to_owned()));
+    }
+
+    let file = std::fs::read_to_string(&path).map_err(SaveError::IOError)?;
+
+    serde_json::from_str(&file).map_err(|err| SaveError::ParseFailure(err.to_string()))
+}
+
+/// Returns the metadata of every save in the save folder, most recently saved first.
+///
+/// Files that can't be parsed as saves are skipped.
+pub fn list_slots(world: &World) -> Result<Vec<SaveMetadata>, SaveError> {
+    let folder = save_folder(world)?;
+
+    if !folder.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut slots = Vec::new();
+
+    for entry in std::fs::read_dir(&folder).map_err(SaveError::IOError)? {
+        let path = entry.map_err(SaveError::IOError)?.path();
+
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                serde_json::from_str::<SaveFile>(&file).map_err(|err| err.to_string())
+            });
+
+        match parsed {
+            Ok(save) => slots.push(save.metadata),
+            Err(err) => warn!("Skipped unreadable save {}: {}", path.display(), err),
+        }
+    }
+
+    slots.sort_by_key(|slot| Reverse(slot.saved_at));
+
+    Ok(slots)
+}
+
+/// Deletes the save file in a slot.
+pub fn delete_slot(world: &World, slot: &str) -> Result<(), SaveError> {
+    let path = slot_path(world, slot)?;
+
+    if !path.exists() {
+        return Err(SaveError::MissingSlot(slot.to_owned()));
+    }
+
+    std::fs::remove_file(path).map_err(SaveError::IOError)
+}
+
+/// Serializes every saved component on the entity, keyed by type path.
+fn save_components(
+    registry: &TypeRegistry,
+    entity: EntityRef,
+) -> Result<HashMap<String, Value>, SaveError> {
+    let mut components = HashMap::new();
+
+    for registration in registry.iter() {
+        let (Some(_), Some(reflect_component)) = (
+            registration.data::<ReflectSaveGame>(),
+            registration.data::<ReflectComponent>(),
+        ) else {
+            continue;
+        };
+
+        let Some(component) = reflect_component.reflect(entity) else {
+            continue;
+        };
+
+        components.insert(
+            registration.type_info().type_path().to_owned(),
+            serialize_reflect(registry, component)?,
+        );
+    }
+
+    Ok(components)
+}
+
+/// Overlays a saved scene onto the freshly loaded authored version of it.
+fn apply_saved_scene(
+    world: &mut World,
+    registry: &TypeRegistry,
+    scene_entity: Entity,
+    saved_scene: &SavedScene,
+) -> Result<(), SaveError> {
+    let authored_entities = world
+        .get::<Scene>(scene_entity)
+        .ok_or(SceneError::NoSceneComponent)?
+        .entities
+        .clone();
+
+    let mut entities_by_id = HashMap::new();
+    let mut entities_by_name = HashMap::new();
+
+    for entity in &authored_entities {
+        let Some(scene_data) = world.get::<SceneData>(*entity) else {
+            continue;
+        };
+
+        entities_by_id.insert(scene_data.entity_id, *entity);
+        entities_by_name.insert(scene_data.entity_name.clone(), *entity);
+    }
+
+    // IDs are matched first, so that a renamed entity can't be taken by another saved entity with its old name
+    let mut matched: Vec<Option<Entity>> = saved_scene
+        .entities
+        .iter()
+        .map(|saved_entity| entities_by_id.get(&saved_entity.id).copied())
+        .collect();
+    let mut kept: HashSet<Entity> = matched.iter().flatten().copied().collect();
+
+    for (saved_entity, entity) in saved_scene.entities.iter().zip(&mut matched) {
+        if entity.is_some() {
+            continue;
+        }
+
+        *entity = entities_by_name
+            .get(&saved_entity.name)
+            .copied()
+            .filter(|authored| kept.insert(*authored));
+    }
+
+    for entity in authored_entities {
+        if kept.contains(&entity) {
+            continue;
+        }
+
+        let Some(scene_data) = world.get::<SceneData>(entity) else {
+            continue;
+        };
+
+        trace!(
+            "Despawning {}, which was despawned in the save",
+            scene_data.entity_name
+        );
+
+        world
+            .get_mut::<Scene>(scene_entity)
+            .ok_or(SceneError::NoSceneComponent)?
+            .entities
+            .retain(|scene_entity| *scene_entity != entity);
+        world.despawn(entity);
+    }
+
+    let scene_id = world
+        .get::<Scene>(scene_entity)
+        .ok_or(SceneError::NoSceneComponent)?
+        .scene_id;
+
+    for (saved_entity, entity) in saved_scene.entities.iter().zip(matched) {
+        let entity = match entity {
+            Some(entity) => entity,
+            None => {
+                trace!(
+                    "Spawning {}, which was spawned in the save",
+                    saved_entity.name
+                );
+
+                // keeping the saved ID lets references to the entity find it again
+                let entity = world
+                    .spawn(SceneData {
+                        entity_name: saved_entity.name.clone(),
+                        scene_id: Some(scene_id),
+                        entity_id: saved_entity.id,
+                        hide_in_inspector: true,
+                    })
+                    .id();
+                add_entity_to_scene(world, scene_entity, entity, None)?;
+                entity
+            }
+        };
+
+        // saved components the entity lost during play shouldn't come back from the authored scene
+        for registration in registry.iter() {
+            let (Some(_), Some(reflect_component)) = (
+                registration.data::<ReflectSaveGame>(),
+                registration.data::<ReflectComponent>(),
+            ) else {
+                continue;
+            };
+
+            if !saved_entity
+                .components
+                .contains_key(registration.type_info().type_path())
+            {
+                reflect_component.remove(&mut world.entity_mut(entity));
+            }
+        }
+
+        for (type_path, value) in &saved_entity.components {
+            let component = deserialize_reflect(registry, type_path, value)?;
+
+            registry
+                .get_with_type_path(type_path)
+                .and_then(|registration| registration.data::<DowncastInsert>())
+                .ok_or_else(|| SaveError::MissingTypeRegistry(type_path.clone()))?
+                .downcast_insert(&mut world.entity_mut(entity), component);
+        }
+    }
+
+    Ok(())
+}
+
+fn serialize_reflect(registry: &TypeRegistry, value: &dyn Reflect) -> Result<Value, SaveError> {
+    let reflect_serialize = registry
+        .get(value.type_id())
+        .and_then(|registration| registration.data::<ReflectSerialize>())
+        .ok_or_else(|| SaveError::MissingTypeRegistry(value.reflect_type_path().to_owned()))?;
+
+    match reflect_serialize.get_serializable(value) {
+        Serializable::Owned(owned) => serde_json::to_value(owned),
+        Serializable::Borrowed(borrowed) => serde_json::to_value(borrowed),
+    }
+    .map_err(|err| SaveError::SerializeFailure(err.to_string()))
+}
+
+fn deserialize_reflect(
+    registry: &TypeRegistry,
+    type_path: &str,
+    value: &Value,
+) -> Result<Box<dyn Reflect>, SaveError> {
+    let reflect_deserialize = registry
+        .get_with_type_path(type_path)
+        .and_then(|registration| registration.data::<ReflectDeserialize>())
+        .ok_or_else(|| SaveError::MissingTypeRegistry(type_path.to_owned()))?;
+
+    reflect_deserialize
+        .deserialize(value)
+        .map_err(|err| SaveError::ParseFailure(format!("{}: {}", type_path, err)))
+}
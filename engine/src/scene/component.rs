@@ -7,14 +7,21 @@ use crate::assets::AssetStorage;
 use crate::assets::SceneAssetID;
 use crate::assets::SerializableAsset;
 use crate::assets::SerializedAsset;
+use crate::events;
+use crate::events::{SceneLoaded, SceneUnloaded};
+use crate::random::Random;
 use crate::scene::object_data::SceneData;
 use crate::scene::serialized_scene::ComponentData;
 use crate::scene::serialized_scene::SerializedSceneData;
+use crate::space::Parent;
 use crate::GgezInterface;
 
+use super::entity_ref;
 use super::error;
 use super::object_data;
 use super::object_id::ComponentInstanceID;
+use super::object_id::SceneEntityID;
+use super::prefab;
 use super::serialized_scene;
 use super::serialized_scene::DataHashmap;
 use super::serialized_scene::EntityHashmap;
@@ -22,7 +29,9 @@ use super::CounterType;
 use super::IDCounter;
 use super::ObjectID;
 use super::SceneError;
+use super::SceneFormat;
 use super::SceneManager;
+use super::SceneMigrations;
 use super::TestSuperTrait;
 
 use bevy_ecs::component::Component;
@@ -35,23 +44,27 @@ use bevy_reflect::serde::ReflectSerializer;
 use bevy_reflect::serde::Serializable;
 use bevy_reflect::GetTupleStructField;
 use bevy_reflect::Reflect;
+use bevy_reflect::ReflectDeserialize;
 use bevy_reflect::ReflectRef;
 use bevy_reflect::ReflectSerialize;
 use bevy_reflect::TypeRegistry;
 use inquire::Text;
 use log::error;
 use log::trace;
+use log::warn;
 use object_data::CustomSerialization;
 use serde::Serialize;
 use serde_json::Value;
 
 use std::any::Any;
 use std::collections::HashMap;
-use std::ffi::OsStr;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::io::Write;
+use std::ops::Deref;
+use std::path::Path;
 use std::path::PathBuf;
 
 /// Entity managment for loading and unloading in batches rather than having everything loaded at once.
@@ -86,6 +99,9 @@ pub struct Scene {
     pub scene_id: ObjectID,
 
     pub(crate) assets: HashMap<SceneAssetID, Asset<Box<dyn Reflect>>>,
+
+    /// The next [`SceneEntityID`] handed out by [`Scene::new_entity_id`]
+    pub(crate) next_entity_id: u64,
 }
 
 impl Clone for Scene {
@@ -108,6 +124,7 @@ impl Clone for Scene {
             save_data_path: self.save_data_path.clone(),
             scene_id: self.scene_id.clone(),
             assets,
+            next_entity_id: self.next_entity_id,
         }
     }
 }
@@ -121,6 +138,7 @@ impl Scene {
             save_data_path: None,
             scene_id: ObjectID::new(CounterType::Scenes),
             assets: HashMap::new(),
+            next_entity_id: 1,
         }
     }
 
@@ -172,6 +190,26 @@ impl Scene {
         None
     }
 
+    pub fn get_entity_by_id(&self, world: &World, id: SceneEntityID) -> Option<Entity> {
+        self.entities.iter().copied().find(|entity| {
+            world
+                .get::<SceneData>(*entity)
+                .is_some_and(|scene_data| scene_data.entity_id == id)
+        })
+    }
+
+    /// Hands out a [`SceneEntityID`] that no other entity in this scene has been given.
+    pub fn new_entity_id(&mut self) -> SceneEntityID {
+        let id = SceneEntityID(self.next_entity_id);
+        self.next_entity_id += 1;
+        id
+    }
+
+    /// Makes sure [`Scene::new_entity_id`] never hands out an ID that is already in use, such as one loaded from a file.
+    pub(crate) fn reserve_entity_id(&mut self, id: SceneEntityID) {
+        self.next_entity_id = self.next_entity_id.max(id.0 + 1);
+    }
+
     pub fn get_entities(&self) -> &Vec<Entity> {
         &self.entities
     }
@@ -210,10 +248,65 @@ impl Scene {
         Some(SerializableAsset::from_reflect_asset(asset, type_registry))
     }
 
-    pub(crate) fn initialize_asset(&mut self, asset_data: serde_json::Value) {
-        // let asset = Asset::new(asset_data);
-        todo!(); // TODO
-                 // self.assets.insert(asset.id, asset);
+    /// Rebuilds an asset written by [`SerializableAsset`] and stores it inside of the scene, returning its ID.
+    ///
+    /// The asset data is deserialized through the type registry using the stored `asset_data_type`,
+    /// so the asset's type must be registered with [`ReflectDeserialize`] type data.
+    pub(crate) fn initialize_asset(
+        &mut self,
+        asset_name: &str,
+        serialized_asset: serde_json::Value,
+        type_registry: &TypeRegistry,
+    ) -> Result<SceneAssetID, SceneError> {
+        let invalid = |reason: &str| SceneError::InvalidAsset {
+            asset_name: asset_name.to_owned(),
+            reason: reason.to_owned(),
+        };
+
+        let Value::Object(mut serialized_asset) = serialized_asset else {
+            return Err(invalid("expected the asset to be stored as a map"));
+        };
+
+        let asset_data_type = match serialized_asset.remove("asset_data_type") {
+            Some(Value::String(asset_data_type)) => asset_data_type,
+            Some(Value::Null) | None => {
+                return Err(invalid(
+                    "no asset_data_type was stored, so the asset's type is unknown",
+                ))
+            }
+            Some(_) => return Err(invalid("asset_data_type must be a type path")),
+        };
+
+        let asset_data = serialized_asset
+            .remove("asset_data")
+            .ok_or_else(|| invalid("no asset_data was stored"))?;
+
+        let registration = type_registry
+            .get_with_type_path(&asset_data_type)
+            .ok_or_else(|| {
+                SceneError::MissingTypeRegistry(format!(
+                    "{} (used by asset {})",
+                    asset_data_type, asset_name
+                ))
+            })?;
+
+        let reflect_deserialize = registration.data::<ReflectDeserialize>().ok_or_else(|| {
+            SceneError::NoSerializationImplementation(format!(
+                "{} (used by asset {})",
+                asset_data_type, asset_name
+            ))
+        })?;
+
+        let asset_data = reflect_deserialize.deserialize(asset_data).map_err(|err| {
+            invalid(&format!(
+                "could not deserialize {}: {}",
+                asset_data_type, err
+            ))
+        })?;
+
+        self.create_asset(asset_name.to_owned(), asset_data);
+
+        Ok(SceneAssetID::get(asset_name))
     }
 
     pub fn get_scene_id_from_name(&self, asset_name: &str) -> SceneAssetID {
@@ -247,16 +340,17 @@ pub fn save_scene(
     let scene_name = scene.name.clone();
 
     let f = || -> Result<PathBuf, SceneError> {
-        let value = world
-            .resource::<GgezInterface>()
-            .get_engine_config()
+        let config = world.resource::<GgezInterface>().get_engine_config();
+
+        let value = config
             .scenes_folder
+            .clone()
             .or_else(|| todo!())
             .ok_or(SceneError::NoEntitiesAvailable)?; // todo pick better error
 
         let mut from = PathBuf::from(value);
 
-        from.push(scene_name + ".json");
+        from.push(scene_name + "." + config.scene_format.extension());
 
         Ok(from)
     };
@@ -268,12 +362,7 @@ pub fn save_scene(
 
     trace!("Checking file extension");
 
-    if !(path.extension().unwrap_or(OsStr::new("none")) == "json") {
-        return Err(SceneError::SerializeFailure(format!(
-            "Invalid file type: Must be JSON. [{}]",
-            path.display()
-        )));
-    }
+    let format = SceneFormat::from_path(&path)?;
 
     trace!("Found path");
 
@@ -301,10 +390,14 @@ pub fn save_scene(
 
     trace!("Writing saved data to disk");
 
+    let value = serde_json::to_value(&value)
+        .map_err(|err| error::SceneError::SerializeFailure(err.to_string()))?;
+
     save_file.set_len(0);
 
-    serde_json::to_writer_pretty(save_file, &value)
-        .map_err(|err| error::SceneError::SerializeFailure(err.to_string()))?;
+    (&save_file)
+        .write_all(&format.write(&value)?)
+        .map_err(SceneError::IOError)?;
 
     world
         .get_mut::<Scene>(entity)
@@ -316,6 +409,29 @@ pub fn save_scene(
     Ok(())
 }
 
+/// Writes the scene to a file in the format picked by the path's extension, without changing where the scene is saved to.
+///
+/// Used to export scenes in [`SceneFormat::Binary`] for shipped games, while still saving them as text while editing.
+pub fn export_scene(
+    entity: Entity,
+    world: &mut World,
+    registry: &TypeRegistry,
+    path: &Path,
+) -> Result<(), error::SceneError> {
+    let format = SceneFormat::from_path(path)?;
+
+    let value = create_serializable_scene_data(world, registry, entity)?;
+
+    let value = serde_json::to_value(&value)
+        .map_err(|err| error::SceneError::SerializeFailure(err.to_string()))?;
+
+    std::fs::write(path, format.write(&value)?).map_err(SceneError::IOError)?;
+
+    trace!("Exported scene to {}", path.display());
+
+    Ok(())
+}
+
 /// Creates a new scene component and spawns an entity with it
 ///
 /// Does no validation to check if it shares a name, that's on the caller of the function
@@ -330,35 +446,73 @@ pub fn load_scene(
     world: &mut World,
     registry: &TypeRegistry,
 ) -> Result<Entity, error::SceneError> {
+    let deserialize = read_scene_file(&path, world.get_resource::<SceneMigrations>())?;
+
+    let scene_entity = deserialize.initialize(world, registry)?;
+
+    trace!(
+        "Loaded scene {} successfully",
+        path.to_str()
+            .expect("expected the path to be valid unicode")
+    );
+
+    finish_loading_scene(world, scene_entity, path);
+
+    Ok(scene_entity)
+}
+
+/// Reads, parses and migrates a scene file without touching the world, so that it can be done on another thread.
+pub(crate) fn read_scene_file(
+    path: &Path,
+    migrations: Option<&SceneMigrations>,
+) -> Result<SerializedSceneData, SceneError> {
     use std::io::prelude::*;
 
     trace!("Opening file");
 
-    let mut buf = String::new();
-    let _s = File::open(path.clone())
+    let mut buf = Vec::new();
+    let _s = File::open(path)
         .map_err(|err| -> error::SceneError { error::SceneError::IOError(err) })?
-        .read_to_string(&mut buf)
+        .read_to_end(&mut buf)
         .map_err(|err| SceneError::IOError(err))?;
 
     trace!("File found");
 
-    let deserialize = serde_json::from_str::<SerializedSceneData>(&buf).map_err(|err| {
+    let format = SceneFormat::detect(path, &buf)?;
+
+    let mut value = format.parse(&buf)?;
+
+    if let Some(migrations) = migrations {
+        let migrated = migrations.migrate(&mut value)?;
+
+        if migrated && migrations.rewrite_upgraded_files {
+            trace!("Rewriting migrated scene file");
+
+            std::fs::write(path, format.write(&value)?).map_err(SceneError::IOError)?;
+        }
+    }
+
+    serde_json::from_value::<SerializedSceneData>(value).map_err(|err| {
         error::SceneError::LoadFailure(
             "SerializedSceneData failed to deserialize: ".to_string() + err.to_string().as_str(),
         )
-    })?;
+    })
+}
 
-    let scene_entity = deserialize.initialize(world, registry)?;
+/// Remembers the file a newly initialized scene was loaded from, and sends [`SceneLoaded`] for it.
+pub(crate) fn finish_loading_scene(world: &mut World, scene_entity: Entity, path: PathBuf) {
+    let mut scene = world.get_mut::<Scene>(scene_entity).unwrap();
+    scene.save_data_path = Some(path.clone());
+    let name = scene.name.clone();
 
-    trace!(
-        "Loaded scene {} successfully",
-        path.to_str()
-            .expect("expected the path to be valid unicode")
+    events::send_event(
+        world,
+        SceneLoaded {
+            scene: scene_entity,
+            name,
+            path,
+        },
     );
-
-    world.get_mut::<Scene>(scene_entity).unwrap().save_data_path = Some(path);
-
-    Ok(scene_entity)
 }
 
 /// Instantly despawns every entity belonging to the scene before despawning the scene entity.
@@ -370,16 +524,24 @@ pub fn load_scene(
 /// Does not save before unloading! Make sure to call [`save_scene`] if anything in the scene must be serialized and stored.
 /// If you don't have any non-global game state contained inside though, you're free to ignore that and unload as you please.
 pub fn unload_scene(scene_entity: Entity, world: &mut World) -> Result<(), SceneError> {
-    for entity in world
+    let scene = world
         .get::<Scene>(scene_entity)
-        .ok_or(SceneError::NoSceneComponent)?
-        .entities
-        .clone()
-    {
+        .ok_or(SceneError::NoSceneComponent)?;
+    let name = scene.name.clone();
+
+    for entity in scene.entities.clone() {
         world.despawn(entity.to_owned());
     }
     world.despawn(scene_entity);
 
+    events::send_event(
+        world,
+        SceneUnloaded {
+            scene: scene_entity,
+            name,
+        },
+    );
+
     Ok(())
 }
 
@@ -396,6 +558,7 @@ pub fn add_entity_to_scene<'a>(
 ) -> Result<(), SceneError> {
     // Very specific way this following code is blocked, since we need a list of entity names that DOESN'T include the entity currently being added
     let mut entity_names: Vec<String> = Vec::new();
+    let mut entity_ids: Vec<SceneEntityID> = Vec::new();
     for (component, entity) in world.query::<(&SceneData, Entity)>().iter(world) {
         if !world
             .get::<Scene>(scene_entity)
@@ -406,6 +569,7 @@ pub fn add_entity_to_scene<'a>(
             continue;
         }
         entity_names.push(component.entity_name.to_owned());
+        entity_ids.push(component.entity_id);
     }
 
     if let None = world.get::<SceneData>(entity_to_add) {
@@ -446,6 +610,7 @@ pub fn add_entity_to_scene<'a>(
             .insert(object_data::SceneData {
                 entity_name: object_name,
                 scene_id: Some(scene_id),
+                entity_id: SceneEntityID::default(),
 
                 hide_in_inspector: true,
             });
@@ -459,6 +624,11 @@ pub fn add_entity_to_scene<'a>(
             .entity_name,
     );
 
+    let entity_id = world
+        .get::<SceneData>(entity_to_add)
+        .ok_or(SceneError::NoSceneDataComponent)?
+        .entity_id;
+
     let mut scene_entity = World::entity_mut(world, scene_entity);
     let mut scene = scene_entity
         .get_mut::<Scene>()
@@ -466,6 +636,14 @@ pub fn add_entity_to_scene<'a>(
 
     scene.entities.push(entity_to_add.clone());
 
+    // Entities that are new, or that were moved over from another scene, need an ID that isn't taken in this scene yet
+    if entity_id == SceneEntityID::default() || entity_ids.contains(&entity_id) {
+        let new_entity_id = scene.new_entity_id();
+        world.get_mut::<SceneData>(entity_to_add).unwrap().entity_id = new_entity_id;
+    } else {
+        scene.reserve_entity_id(entity_id);
+    }
+
     Ok(())
 }
 
@@ -521,6 +699,8 @@ pub fn create_serializable_scene_data<'a>(
 
     // scene reference is dropped here
 
+    entity_ref::store_entity_ref_ids(world, &scene_entity_list, type_registry);
+
     let mut entity_data: HashMap<String, HashMap<String, Value>> = HashMap::new();
 
     for (entity, serializable_components_data) in world
@@ -535,55 +715,15 @@ pub fn create_serializable_scene_data<'a>(
 
         trace!("  - Serializing {}'s components", entity_name);
 
-        let mut entity_hashmap: HashMap<String, Value> = HashMap::new();
-
-        for component in serializable_components_data.iter() {
-            let reflected_component = component.as_reflect();
-
-            let component_type_path = reflected_component.reflect_type_path();
-            trace!("    - Serializing {}", component_type_path);
-
-            let Some(registration) = type_registry.get(component.as_reflect().type_id()) else {
-                log::error!(
-                    "Could not find type registration for {}",
-                    component.as_reflect().reflect_type_path()
-                );
-                continue;
-            };
-
-            let Some(reflect_serialize) = registration.data::<ReflectSerialize>() else {
-                log::error!(
-                    "Could not find ReflectSerialize type data for {}",
-                    component.as_reflect().reflect_type_path()
-                );
-                continue;
-            };
-
-            let serializable_component: Serializable =
-                reflect_serialize.get_serializable(component.as_reflect());
-
-            let value = match serializable_component {
-                Serializable::Owned(owned) => serde_json::to_value(owned),
-                Serializable::Borrowed(borrowed) => serde_json::to_value(borrowed),
-            }
-            .unwrap();
-
-            trace!("      - Serialized");
-
-            entity_hashmap.insert(reflected_component.reflect_type_path().to_owned(), value);
-
-            trace!(
-                "   - Inserted serialized component data to {}'s serialize data",
-                entity_name
-            );
-        }
+        let entity_hashmap =
+            serialize_components(serializable_components_data.iter(), type_registry);
         let k = world.get::<SceneData>(entity).unwrap().entity_name.clone();
 
         entity_data.insert(k, entity_hashmap);
     }
 
     // Serialize empty entities aswell
-    for entity in scene_entity_list {
+    for &entity in &scene_entity_list {
         // Try/Catch to prevent failure from entities with missing scene components
         let result = || -> Result<(), SceneError> {
             let object_name = &world
@@ -603,6 +743,49 @@ pub fn create_serializable_scene_data<'a>(
         }
     }
 
+    let entity_ids = scene_entity_list
+        .iter()
+        .filter_map(|entity| world.get::<SceneData>(*entity))
+        .map(|scene_data| (scene_data.entity_name.clone(), scene_data.entity_id))
+        .collect::<HashMap<String, SceneEntityID>>();
+
+    trace!("Serializing hierarchy...");
+
+    let mut hierarchy = HashMap::new();
+
+    for entity in &scene_entity_list {
+        let Some(parent) = world.get::<Parent>(*entity) else {
+            continue;
+        };
+
+        // Parents outside of the scene can't be found again once the scene is loaded
+        if !scene_entity_list.contains(&parent.get()) {
+            warn!(
+                "Skipped serializing the parent of entity {}, as the parent is not in the scene",
+                entity.index()
+            );
+            continue;
+        }
+
+        let (Some(child), Some(parent)) = (
+            world.get::<SceneData>(*entity),
+            world.get::<SceneData>(parent.get()),
+        ) else {
+            continue;
+        };
+
+        hierarchy.insert(child.entity_name.clone(), parent.entity_name.clone());
+    }
+
+    trace!("Serializing prefab instances...");
+
+    let prefabs = prefab::extract_prefab_instances(
+        world,
+        &scene_entity_list,
+        &mut entity_data,
+        &mut hierarchy,
+    );
+
     trace!("Serialized entities successfully.");
 
     trace!("Serializing assets...");
@@ -628,11 +811,69 @@ pub fn create_serializable_scene_data<'a>(
 
     Ok(serialized_scene::SerializedSceneData {
         name: scene_name,
+        format_version: world
+            .get_resource::<SceneMigrations>()
+            .map_or(0, SceneMigrations::current_version),
         entity_data,
         asset_data,
+        entity_ids,
+        hierarchy,
+        prefabs,
+        rng: world.get_resource::<Random>().cloned(),
     })
 }
 
+/// Serializes every component that can be stored in a scene, keyed by their type paths.
+///
+/// Components that are missing from the type registry or don't have [`ReflectSerialize`] type data are skipped.
+pub(crate) fn serialize_components<C>(
+    components: impl IntoIterator<Item = C>,
+    type_registry: &TypeRegistry,
+) -> HashMap<String, Value>
+where
+    C: Deref<Target = dyn TestSuperTrait>,
+{
+    let mut entity_hashmap: HashMap<String, Value> = HashMap::new();
+
+    for component in components {
+        let reflected_component = component.as_reflect();
+
+        let component_type_path = reflected_component.reflect_type_path();
+        trace!("    - Serializing {}", component_type_path);
+
+        let Some(registration) = type_registry.get(component.as_reflect().type_id()) else {
+            log::error!(
+                "Could not find type registration for {}",
+                component.as_reflect().reflect_type_path()
+            );
+            continue;
+        };
+
+        let Some(reflect_serialize) = registration.data::<ReflectSerialize>() else {
+            log::error!(
+                "Could not find ReflectSerialize type data for {}",
+                component.as_reflect().reflect_type_path()
+            );
+            continue;
+        };
+
+        let serializable_component: Serializable =
+            reflect_serialize.get_serializable(component.as_reflect());
+
+        let value = match serializable_component {
+            Serializable::Owned(owned) => serde_json::to_value(owned),
+            Serializable::Borrowed(borrowed) => serde_json::to_value(borrowed),
+        }
+        .unwrap();
+
+        trace!("      - Serialized");
+
+        entity_hashmap.insert(reflected_component.reflect_type_path().to_owned(), value);
+    }
+
+    entity_hashmap
+}
+
 fn check_string(
     serialized_data_from_entity: Vec<u8>,
 ) -> Result<String, Result<SerializedSceneData, String>> {
@@ -0,0 +1,593 @@
+//! Structural diffs between two versions of a scene, stored as a [`ScenePatch`] that can be applied to other scene data or a live scene.
+//!
+//! A patch is a list of [`SceneChange`]s, each of which keeps the value it replaces as well as the new one.
+//! That makes every patch reversible through [`ScenePatch::invert`], which is what undo and redo are built on.
+//!
+//! Component fields are addressed by their path through the component's serialized JSON, so `["position", "x"]`
+//! points at `component["position"]["x"]`. Objects are compared field by field, while any other value is replaced whole.
+
+use std::collections::{BTreeSet, HashMap};
+
+use bevy_ecs::entity::Entity;
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_ecs::world::World;
+use bevy_reflect::serde::Serializable;
+use bevy_reflect::{ReflectDeserialize, ReflectSerialize, TypeRegistry};
+use log::{trace, warn};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use super::serialized_scene::{insert_components, SerializedSceneData};
+use super::{add_entity_to_scene, Scene, SceneData, SceneEntityID, SceneError};
+use crate::space::set_parent;
+
+/// A single change between two versions of a scene.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum SceneChange {
+    /// A new entity, along with every one of its components.
+    AddEntity {
+        entity: String,
+        components: HashMap<String, Value>,
+    },
+    /// An entity was despawned. Its components are kept so the change can be undone.
+    RemoveEntity {
+        entity: String,
+        components: HashMap<String, Value>,
+    },
+    AddComponent {
+        entity: String,
+        component: String,
+        value: Value,
+    },
+    RemoveComponent {
+        entity: String,
+        component: String,
+        value: Value,
+    },
+    /// A field of a component changed. [`None`] means the field doesn't exist on that side.
+    SetField {
+        entity: String,
+        component: String,
+        path: Vec<String>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "deserialize_field"
+        )]
+        old: Option<Value>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "deserialize_field"
+        )]
+        new: Option<Value>,
+    },
+}
+
+/// Keeps a field that is present but `null` as `Some(Value::Null)`, since only a missing field means the field doesn't exist.
+fn deserialize_field<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
+impl SceneChange {
+    /// The name of the entity the change applies to.
+    pub fn entity(&self) -> &str {
+        match self {
+            SceneChange::AddEntity { entity, .. }
+            | SceneChange::RemoveEntity { entity, .. }
+            | SceneChange::AddComponent { entity, .. }
+            | SceneChange::RemoveComponent { entity, .. }
+            | SceneChange::SetField { entity, .. } => entity,
+        }
+    }
+
+    /// Returns the change that undoes this one.
+    pub fn invert(&self) -> Self {
+        match self.clone() {
+            SceneChange::AddEntity { entity, components } => {
+                SceneChange::RemoveEntity { entity, components }
+            }
+            SceneChange::RemoveEntity { entity, components } => {
+                SceneChange::AddEntity { entity, components }
+            }
+            SceneChange::AddComponent {
+                entity,
+                component,
+                value,
+            } => SceneChange::RemoveComponent {
+                entity,
+                component,
+                value,
+            },
+            SceneChange::RemoveComponent {
+                entity,
+                component,
+                value,
+            } => SceneChange::AddComponent {
+                entity,
+                component,
+                value,
+            },
+            SceneChange::SetField {
+                entity,
+                component,
+                path,
+                old,
+                new,
+            } => SceneChange::SetField {
+                entity,
+                component,
+                path,
+                old: new,
+                new: old,
+            },
+        }
+    }
+}
+
+/// Every change needed to turn one version of a scene into another, in the order they should be applied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScenePatch {
+    pub changes: Vec<SceneChange>,
+}
+
+impl ScenePatch {
+    /// Compares the entities of two scenes, returning the changes that turn `old` into `new`.
+    ///
+    /// Entities are matched by name, and the changes are sorted by entity and component so that diffs of the same scenes are always the same.
+    /// Assets, the hierarchy and prefab instances are not compared.
+    pub fn diff(old: &SerializedSceneData, new: &SerializedSceneData) -> Self {
+        let mut changes = Vec::new();
+
+        let entity_names = old
+            .entity_data
+            .keys()
+            .chain(new.entity_data.keys())
+            .collect::<BTreeSet<&String>>();
+
+        for entity in entity_names {
+            let (old_components, new_components) =
+                match (old.entity_data.get(entity), new.entity_data.get(entity)) {
+                    (Some(old_components), Some(new_components)) => {
+                        (old_components, new_components)
+                    }
+                    (Some(components), None) => {
+                        changes.push(SceneChange::RemoveEntity {
+                            entity: entity.clone(),
+                            components: components.clone(),
+                        });
+                        continue;
+                    }
+                    (None, Some(components)) => {
+                        changes.push(SceneChange::AddEntity {
+                            entity: entity.clone(),
+                            components: components.clone(),
+                        });
+                        continue;
+                    }
+                    (None, None) => continue,
+                };
+
+            let component_paths = old_components
+                .keys()
+                .chain(new_components.keys())
+                .collect::<BTreeSet<&String>>();
+
+            for component in component_paths {
+                match (old_components.get(component), new_components.get(component)) {
+                    (Some(old_value), Some(new_value)) => diff_fields(
+                        entity,
+                        component,
+                        &mut Vec::new(),
+                        Some(old_value),
+                        Some(new_value),
+                        &mut changes,
+                    ),
+                    (Some(value), None) => changes.push(SceneChange::RemoveComponent {
+                        entity: entity.clone(),
+                        component: component.clone(),
+                        value: value.clone(),
+                    }),
+                    (None, Some(value)) => changes.push(SceneChange::AddComponent {
+                        entity: entity.clone(),
+                        component: component.clone(),
+                        value: value.clone(),
+                    }),
+                    (None, None) => (),
+                }
+            }
+        }
+
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the patch that undoes this one, so applying both leaves the scene as it was.
+    pub fn invert(&self) -> Self {
+        Self {
+            changes: self.changes.iter().rev().map(SceneChange::invert).collect(),
+        }
+    }
+
+    /// Applies every change to serialized scene data, such as a scene file that hasn't been loaded.
+    ///
+    /// Stops at the first change that doesn't fit the data, like removing an entity that doesn't exist,
+    /// or setting a field that no longer holds the value the patch replaces.
+    /// Changes before it stay applied.
+    pub fn apply_to_data(&self, scene_data: &mut SerializedSceneData) -> Result<(), SceneError> {
+        for change in &self.changes {
+            match change {
+                SceneChange::AddEntity { entity, components } => {
+                    if scene_data.entity_data.contains_key(entity) {
+                        return Err(already_exists(entity));
+                    }
+                    scene_data
+                        .entity_data
+                        .insert(entity.clone(), components.clone());
+                }
+                SceneChange::RemoveEntity { entity, .. } => {
+                    if scene_data.entity_data.remove(entity).is_none() {
+                        return Err(missing_entity(entity));
+                    }
+                    scene_data.entity_ids.remove(entity);
+                    scene_data.hierarchy.remove(entity);
+                }
+                SceneChange::AddComponent {
+                    entity,
+                    component,
+                    value,
+                } => {
+                    let components = scene_data
+                        .entity_data
+                        .get_mut(entity)
+                        .ok_or_else(|| missing_entity(entity))?;
+                    if components.contains_key(component) {
+                        return Err(SceneError::InvalidPatch(format!(
+                            "{} already has a {} component",
+                            entity, component
+                        )));
+                    }
+                    components.insert(component.clone(), value.clone());
+                }
+                SceneChange::RemoveComponent {
+                    entity, component, ..
+                } => {
+                    scene_data
+                        .entity_data
+                        .get_mut(entity)
+                        .ok_or_else(|| missing_entity(entity))?
+                        .remove(component)
+                        .ok_or_else(|| missing_component(entity, component))?;
+                }
+                SceneChange::SetField {
+                    entity,
+                    component,
+                    path,
+                    old,
+                    new,
+                } => {
+                    let value = scene_data
+                        .entity_data
+                        .get_mut(entity)
+                        .ok_or_else(|| missing_entity(entity))?
+                        .get_mut(component)
+                        .ok_or_else(|| missing_component(entity, component))?;
+                    set_field(value, path, old.as_ref(), new.clone())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies every change to a loaded scene, spawning, despawning and changing its entities through reflection.
+    ///
+    /// Like [`ScenePatch::apply_to_data`], stops at the first change that doesn't fit the scene.
+    pub fn apply(
+        &self,
+        world: &mut World,
+        scene_entity: Entity,
+        type_registry: &TypeRegistry,
+    ) -> Result<(), SceneError> {
+        for change in &self.changes {
+            trace!("Applying {:?}", change);
+
+            let scene = world
+                .get::<Scene>(scene_entity)
+                .ok_or(SceneError::NoSceneComponent)?;
+            let scene_id = scene.scene_id;
+            let target = scene.get_entity(world, change.entity().to_owned());
+
+            match change {
+                SceneChange::AddEntity { entity, components } => {
+                    if target.is_some() {
+                        return Err(already_exists(entity));
+                    }
+
+                    let mut new_entity = world.spawn(SceneData {
+                        entity_name: entity.clone(),
+                        scene_id: Some(scene_id),
+                        entity_id: SceneEntityID::default(),
+
+                        hide_in_inspector: true,
+                    });
+                    insert_components(&mut new_entity, components.clone(), type_registry)?;
+                    let new_entity = new_entity.id();
+
+                    add_entity_to_scene(world, scene_entity, new_entity, None)?;
+                }
+                SceneChange::RemoveEntity { entity, .. } => {
+                    let target = target.ok_or_else(|| missing_entity(entity))?;
+
+                    if let Err(err) = set_parent(world, target, None) {
+                        warn!("Could not detach {} before removing it: {}", entity, err);
+                    }
+
+                    world
+                        .get_mut::<Scene>(scene_entity)
+                        .ok_or(SceneError::NoSceneComponent)?
+                        .entities
+                        .retain(|scene_entity| *scene_entity != target);
+                    world.despawn(target);
+                }
+                SceneChange::AddComponent {
+                    entity,
+                    component,
+                    value,
+                } => {
+                    let target = target.ok_or_else(|| missing_entity(entity))?;
+
+                    let reflect_component = reflect_component(type_registry, component)?;
+                    if reflect_component.contains(world.entity(target)) {
+                        return Err(SceneError::InvalidPatch(format!(
+                            "{} already has a {} component",
+                            entity, component
+                        )));
+                    }
+
+                    insert_components(
+                        &mut world.entity_mut(target),
+                        HashMap::from([(component.clone(), value.clone())]),
+                        type_registry,
+                    )?;
+                }
+                SceneChange::RemoveComponent {
+                    entity, component, ..
+                } => {
+                    let target = target.ok_or_else(|| missing_entity(entity))?;
+
+                    let reflect_component = reflect_component(type_registry, component)?;
+                    if !reflect_component.contains(world.entity(target)) {
+                        return Err(missing_component(entity, component));
+                    }
+
+                    reflect_component.remove(&mut world.entity_mut(target));
+                }
+                SceneChange::SetField {
+                    entity,
+                    component,
+                    path,
+                    old,
+                    new,
+                } => {
+                    let target = target.ok_or_else(|| missing_entity(entity))?;
+
+                    set_component_field(
+                        world,
+                        target,
+                        entity,
+                        component,
+                        type_registry,
+                        |value| set_field(value, path, old.as_ref(), new.clone()),
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Pushes a [`SceneChange::SetField`] for every field that differs between the two values, recursing into objects.
+fn diff_fields(
+    entity: &str,
+    component: &str,
+    path: &mut Vec<String>,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<SceneChange>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old_fields)), Some(Value::Object(new_fields))) => {
+            let fields = old_fields
+                .keys()
+                .chain(new_fields.keys())
+                .collect::<BTreeSet<&String>>();
+
+            for field in fields {
+                path.push(field.clone());
+                diff_fields(
+                    entity,
+                    component,
+                    path,
+                    old_fields.get(field),
+                    new_fields.get(field),
+                    changes,
+                );
+                path.pop();
+            }
+        }
+        (old, new) if field_eq(old, new) => {}
+        (old, new) => changes.push(SceneChange::SetField {
+            entity: entity.to_owned(),
+            component: component.to_owned(),
+            path: path.clone(),
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+    }
+}
+
+/// Compares two field values, where [`None`] means the field doesn't exist.
+fn field_eq(a: Option<&Value>, b: Option<&Value>) -> bool {
+    match (a, b) {
+        (Some(Value::Object(a_fields)), Some(Value::Object(b_fields))) => {
+            a_fields.len() == b_fields.len()
+                && a_fields
+                    .iter()
+                    .all(|(field, a)| field_eq(Some(a), b_fields.get(field)))
+        }
+        // Floats can be written as `1` by hand, but are always serialized as `1.0`
+        (Some(Value::Number(a_number)), Some(Value::Number(b_number))) => {
+            a_number.as_f64() == b_number.as_f64()
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Replaces the field at `path` inside of `value`, or removes it if `new` is [`None`].
+///
+/// Fails if the field doesn't currently hold `old`, so that a patch never overwrites changes made after it.
+/// Arrays are indexed by the position written as a string, such as `"0"`.
+fn set_field(
+    value: &mut Value,
+    path: &[String],
+    old: Option<&Value>,
+    new: Option<Value>,
+) -> Result<(), SceneError> {
+    let Some((field, parent_path)) = path.split_last() else {
+        if !field_eq(Some(value), old) {
+            return Err(changed_field(path));
+        }
+        *value = new.unwrap_or(Value::Null);
+        return Ok(());
+    };
+
+    let mut parent = value;
+    for step in parent_path {
+        parent = match parent {
+            Value::Object(fields) => fields.get_mut(step),
+            Value::Array(items) => step
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get_mut(index)),
+            _ => None,
+        }
+        .ok_or_else(|| missing_field(path))?;
+    }
+
+    let current = match &*parent {
+        Value::Object(fields) => fields.get(field),
+        Value::Array(items) => field
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| items.get(index)),
+        _ => None,
+    };
+    if !field_eq(current, old) {
+        return Err(changed_field(path));
+    }
+
+    match (parent, new) {
+        (Value::Object(fields), Some(new)) => {
+            fields.insert(field.clone(), new);
+        }
+        (Value::Object(fields), None) => {
+            fields.remove(field);
+        }
+        (Value::Array(items), Some(new)) => {
+            let item = field
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get_mut(index))
+                .ok_or_else(|| missing_field(path))?;
+            *item = new;
+        }
+        _ => return Err(missing_field(path)),
+    }
+
+    Ok(())
+}
+
+/// Changes a live component by serializing it, patching the JSON with `set`, and applying the result back through reflection.
+fn set_component_field(
+    world: &mut World,
+    target: Entity,
+    entity: &str,
+    component: &str,
+    type_registry: &TypeRegistry,
+    set: impl FnOnce(&mut Value) -> Result<(), SceneError>,
+) -> Result<(), SceneError> {
+    let registration = type_registry
+        .get_with_type_path(component)
+        .ok_or(SceneError::MissingTypeRegistry(component.to_owned()))?;
+
+    let reflect_component = reflect_component(type_registry, component)?;
+    let reflect_serialize = registration.data::<ReflectSerialize>().ok_or(
+        SceneError::NoSerializationImplementation(component.to_owned()),
+    )?;
+    let reflect_deserialize = registration.data::<ReflectDeserialize>().ok_or(
+        SceneError::NoSerializationImplementation(component.to_owned()),
+    )?;
+
+    let current = reflect_component
+        .reflect(world.entity(target))
+        .ok_or_else(|| missing_component(entity, component))?;
+
+    let mut value = match reflect_serialize.get_serializable(current) {
+        Serializable::Owned(owned) => serde_json::to_value(owned),
+        Serializable::Borrowed(borrowed) => serde_json::to_value(borrowed),
+    }
+    .map_err(|err| SceneError::SerializeFailure(err.to_string()))?;
+
+    set(&mut value)?;
+
+    let patched = reflect_deserialize
+        .deserialize(value)
+        .map_err(|err| SceneError::InvalidPatch(format!("{} [{}]", component, err)))?;
+
+    reflect_component.apply(&mut world.entity_mut(target), &*patched);
+
+    Ok(())
+}
+
+fn reflect_component<'a>(
+    type_registry: &'a TypeRegistry,
+    component: &str,
+) -> Result<&'a ReflectComponent, SceneError> {
+    type_registry
+        .get_with_type_path(component)
+        .ok_or(SceneError::MissingTypeRegistry(component.to_owned()))?
+        .data::<ReflectComponent>()
+        .ok_or(SceneError::NoReflectData(component.to_owned()))
+}
+
+fn already_exists(entity: &str) -> SceneError {
+    SceneError::InvalidPatch(format!("Entity {} already exists", entity))
+}
+
+fn missing_entity(entity: &str) -> SceneError {
+    SceneError::InvalidPatch(format!("Entity {} does not exist", entity))
+}
+
+fn missing_component(entity: &str, component: &str) -> SceneError {
+    SceneError::InvalidPatch(format!("{} has no {} component", entity, component))
+}
+
+fn missing_field(path: &[String]) -> SceneError {
+    SceneError::InvalidPatch(format!("No field at {}", path.join(".")))
+}
+
+fn changed_field(path: &[String]) -> SceneError {
+    SceneError::InvalidPatch(format!(
+        "The field at {} was changed after the patch was made",
+        path.join(".")
+    ))
+}
@@ -0,0 +1,219 @@
+//! [`EntityRef`], a reference from a component to another entity that survives saving and loading its scene.
+//!
+//! An [`Entity`] is only valid for the session it was spawned in, so components can't store one directly.
+//! Instead they store an [`EntityRef`], which is saved as the target's [`SceneEntityID`] and turned back into the live [`Entity`]
+//! once every entity of the scene has been spawned.
+//!
+//! References are found through reflection, so any registered component can hold them, including inside of lists, maps and options.
+//! Only entities in the same scene can be referenced.
+
+use std::collections::HashMap;
+
+use bevy_ecs::entity::Entity;
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_ecs::world::World;
+use bevy_reflect::{Reflect, ReflectDeserialize, ReflectMut, ReflectSerialize, TypeRegistry};
+use log::warn;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::object_id::SceneEntityID;
+use super::SceneData;
+
+/// A reference to another entity in the same scene, which can be stored in serialized components.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Serialize, Deserialize)]
+pub struct EntityRef {
+    /// The target's ID, only kept up to date while saving and loading
+    id: Option<SceneEntityID>,
+    entity: Option<Entity>,
+}
+
+impl EntityRef {
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            id: None,
+            entity: Some(entity),
+        }
+    }
+
+    /// Returns the referenced entity, which is [`None`] if the reference is empty or its target couldn't be found when loading.
+    pub fn get(&self) -> Option<Entity> {
+        self.entity
+    }
+
+    pub fn set(&mut self, entity: Option<Entity>) {
+        self.id = None;
+        self.entity = entity;
+    }
+
+    /// The ID of the target as of the last time its scene was saved or loaded.
+    pub fn id(&self) -> Option<SceneEntityID> {
+        self.id
+    }
+}
+
+impl From<Entity> for EntityRef {
+    fn from(value: Entity) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Serialize for EntityRef {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.id.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EntityRef {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self {
+            id: Option::<SceneEntityID>::deserialize(deserializer)?,
+            entity: None,
+        })
+    }
+}
+
+/// Stores the [`SceneEntityID`] of every reference held by the given entities' components, so they can be serialized.
+///
+/// References to entities outside of `entities` are saved as empty, since they couldn't be found again when loading.
+pub(crate) fn store_entity_ref_ids(
+    world: &mut World,
+    entities: &[Entity],
+    type_registry: &TypeRegistry,
+) {
+    let ids = entities
+        .iter()
+        .filter_map(|entity| Some((*entity, world.get::<SceneData>(*entity)?.entity_id)))
+        .collect::<HashMap<Entity, SceneEntityID>>();
+
+    for_each_entity_ref(world, entities, type_registry, |entity_ref| {
+        entity_ref.id = entity_ref.entity.and_then(|entity| {
+            let id = ids.get(&entity).copied();
+            if id.is_none() {
+                warn!(
+                    "Saving a reference to entity {:?} as empty, as it's not in the same scene",
+                    entity
+                );
+            }
+            id
+        });
+    });
+}
+
+/// Points every reference held by the given entities' components at the live entity with the stored [`SceneEntityID`].
+pub(crate) fn resolve_entity_refs(
+    world: &mut World,
+    entities: &[Entity],
+    type_registry: &TypeRegistry,
+) {
+    let entities_by_id = entities
+        .iter()
+        .filter_map(|entity| Some((world.get::<SceneData>(*entity)?.entity_id, *entity)))
+        .collect::<HashMap<SceneEntityID, Entity>>();
+
+    for_each_entity_ref(world, entities, type_registry, |entity_ref| {
+        entity_ref.entity = entity_ref.id.and_then(|id| {
+            let entity = entities_by_id.get(&id).copied();
+            if entity.is_none() {
+                warn!("Could not find entity {} for a reference", id);
+            }
+            entity
+        });
+    });
+}
+
+/// Runs `f` on every [`EntityRef`] inside of the reflected components of the given entities.
+fn for_each_entity_ref(
+    world: &mut World,
+    entities: &[Entity],
+    type_registry: &TypeRegistry,
+    mut f: impl FnMut(&mut EntityRef),
+) {
+    for entity in entities {
+        let Some(world_entity) = world.get_entity(*entity) else {
+            continue;
+        };
+
+        let reflect_components = world_entity
+            .archetype()
+            .components()
+            .filter_map(|component_id| world.components().get_info(component_id)?.type_id())
+            .filter_map(|type_id| type_registry.get(type_id)?.data::<ReflectComponent>())
+            .cloned()
+            .collect::<Vec<ReflectComponent>>();
+
+        let mut entity_mut = world.entity_mut(*entity);
+
+        for reflect_component in reflect_components {
+            if let Some(mut component) = reflect_component.reflect_mut(&mut entity_mut) {
+                visit_entity_refs(component.as_reflect_mut(), &mut f);
+            }
+        }
+    }
+}
+
+fn visit_entity_refs(value: &mut dyn Reflect, f: &mut impl FnMut(&mut EntityRef)) {
+    if let Some(entity_ref) = value.downcast_mut::<EntityRef>() {
+        f(entity_ref);
+        return;
+    }
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(value) => {
+            for index in 0..value.field_len() {
+                if let Some(field) = value.field_at_mut(index) {
+                    visit_entity_refs(field, f);
+                }
+            }
+        }
+        ReflectMut::TupleStruct(value) => {
+            for index in 0..value.field_len() {
+                if let Some(field) = value.field_mut(index) {
+                    visit_entity_refs(field, f);
+                }
+            }
+        }
+        ReflectMut::Tuple(value) => {
+            for index in 0..value.field_len() {
+                if let Some(field) = value.field_mut(index) {
+                    visit_entity_refs(field, f);
+                }
+            }
+        }
+        ReflectMut::List(value) => {
+            for index in 0..value.len() {
+                if let Some(item) = value.get_mut(index) {
+                    visit_entity_refs(item, f);
+                }
+            }
+        }
+        ReflectMut::Array(value) => {
+            for index in 0..value.len() {
+                if let Some(item) = value.get_mut(index) {
+                    visit_entity_refs(item, f);
+                }
+            }
+        }
+        ReflectMut::Map(value) => {
+            for index in 0..value.len() {
+                if let Some((_, item)) = value.get_at_mut(index) {
+                    visit_entity_refs(item, f);
+                }
+            }
+        }
+        ReflectMut::Enum(value) => {
+            for index in 0..value.field_len() {
+                if let Some(field) = value.field_at_mut(index) {
+                    visit_entity_refs(field, f);
+                }
+            }
+        }
+        ReflectMut::Value(_) => (),
+    }
+}
@@ -34,6 +34,25 @@ pub enum SceneError {
     /// There were no entities available to operate upon.
     NoEntitiesAvailable,
     QueryFailure(String),
+    /// A stored asset could not be rebuilt, [`String`]s are the asset's name and what was wrong with it.
+    InvalidAsset {
+        asset_name: String,
+        reason: String,
+    },
+    /// A prefab file could not be turned into entities, [`String`] is what was wrong with it.
+    InvalidPrefab {
+        path: std::path::PathBuf,
+        reason: String,
+    },
+    /// A [`ScenePatch`](super::ScenePatch) did not fit the scene it was applied to, [`String`] is the change that failed.
+    InvalidPatch(String),
+    /// Scene files must be .json, .ron or .bin, [`String`] is the extension that was given.
+    UnsupportedFormat(String),
+    /// The scene file was saved with a newer format version than any registered [`Migration`](super::Migration), so it can't be read.
+    UnsupportedFormatVersion {
+        found: u32,
+        supported: u32,
+    },
 }
 
 impl Display for SceneError {
@@ -67,6 +86,25 @@ impl Display for SceneError {
             SceneError::QueryFailure(err) => {
                 write!(f, "Could not query for TestSuperTrait components {}", err)
             }
+            SceneError::InvalidAsset { asset_name, reason } => {
+                write!(f, "Invalid asset {} [{}]", asset_name, reason)
+            }
+            SceneError::InvalidPrefab { path, reason } => {
+                write!(f, "Invalid prefab {} [{}]", path.display(), reason)
+            }
+            SceneError::InvalidPatch(err) => write!(f, "Invalid scene patch [{}]", err),
+            SceneError::UnsupportedFormat(extension) => {
+                write!(
+                    f,
+                    "Scene files must be .json, .ron or .bin, got [{}]",
+                    extension
+                )
+            }
+            SceneError::UnsupportedFormatVersion { found, supported } => write!(
+                f,
+                "Scene has format version {}, but only versions up to {} are supported",
+                found, supported
+            ),
         }
     }
 }
@@ -0,0 +1,374 @@
+//! The file formats scenes can be stored in, picked using the scene file's extension.
+//!
+//! Every format goes through [`serde_json::Value`] on the way in and out, so [`SceneMigrations`](super::SceneMigrations) work the same no matter
+//! which format a scene is stored in, and converting a scene between formats with [`convert_scene_file`] never loses any data.
+//!
+//! [`SceneFormat::Binary`] is meant for shipped games, where scenes don't need to be readable and should load as fast as possible.
+//! Binary files start with [`BINARY_MAGIC`], so they're recognized no matter what their extension is.
+//!
+//! The binary format encodes that same [`Value`] rather than encoding components through [`ReflectSerialize`](bevy_reflect::ReflectSerialize).
+//! Encoding components directly would need every component registered just to read or convert a file, and migrations would have nothing to
+//! work on, since they edit a scene before any of its components are deserialized.
+//! Since binary files are trusted less than hand-written ones, reading them is capped at [`MAX_BINARY_DEPTH`] levels of nesting,
+//! and never allocates more than the file's own size.
+
+use std::fmt::Formatter;
+
+use std::path::Path;
+
+use bincode::Options;
+use serde::de::{
+    DeserializeSeed, EnumAccess, Error, SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Number, Value};
+
+use super::SceneError;
+
+/// The bytes every binary scene file starts with.
+pub const BINARY_MAGIC: &[u8; 8] = b"NFSCENE\0";
+
+/// The version of the binary encoding itself, written right after [`BINARY_MAGIC`].
+///
+/// Unrelated to a scene's `format_version`, which tracks changes to the components stored in it.
+pub const BINARY_VERSION: u32 = 1;
+
+/// How deeply arrays and objects can be nested in a binary scene, the same limit [`serde_json`] puts on JSON scenes.
+pub const MAX_BINARY_DEPTH: usize = 128;
+
+/// A file format that scenes can be loaded from and saved to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SceneFormat {
+    /// `.json` files
+    #[default]
+    Json,
+    /// `.ron` files
+    Ron,
+    /// `.bin` files, a compact encoding that can't be read or edited by hand
+    Binary,
+}
+
+impl SceneFormat {
+    /// Picks the format using the file's extension.
+    pub fn from_path(path: &Path) -> Result<Self, SceneError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+
+        match extension {
+            "json" => Ok(Self::Json),
+            "ron" => Ok(Self::Ron),
+            "bin" => Ok(Self::Binary),
+            other => Err(SceneError::UnsupportedFormat(other.to_owned())),
+        }
+    }
+
+    /// Picks the format of a file that has already been read, which is [`SceneFormat::Binary`] if it starts with [`BINARY_MAGIC`],
+    /// otherwise the format is picked using the file's extension.
+    pub fn detect(path: &Path, file: &[u8]) -> Result<Self, SceneError> {
+        if file.starts_with(BINARY_MAGIC) {
+            return Ok(Self::Binary);
+        }
+
+        Self::from_path(path)
+    }
+
+    /// The file extension used by the format, without the leading dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SceneFormat::Json => "json",
+            SceneFormat::Ron => "ron",
+            SceneFormat::Binary => "bin",
+        }
+    }
+
+    /// Returns true for formats that can be read and edited by hand.
+    pub fn is_text(&self) -> bool {
+        *self != SceneFormat::Binary
+    }
+
+    /// Parses the contents of a scene file.
+    pub fn parse(&self, file: &[u8]) -> Result<Value, SceneError> {
+        match self {
+            SceneFormat::Json => serde_json::from_slice(file).map_err(|err| err.to_string()),
+            SceneFormat::Ron => ron::de::from_bytes(file).map_err(|err| err.to_string()),
+            SceneFormat::Binary => parse_binary(file),
+        }
+        .map_err(|err| {
+            SceneError::LoadFailure(format!(
+                "Scene file is not valid {}: {}",
+                self.extension(),
+                err
+            ))
+        })
+    }
+
+    /// Writes a scene into the contents of a file.
+    ///
+    /// RON scenes are written as a map rather than a struct, since RON can only read structs back when it knows their type ahead of time.
+    pub fn write(&self, scene: &Value) -> Result<Vec<u8>, SceneError> {
+        match self {
+            SceneFormat::Json => serde_json::to_vec_pretty(scene).map_err(|err| err.to_string()),
+            SceneFormat::Ron => {
+                ron::ser::to_string_pretty(scene, ron::ser::PrettyConfig::default())
+                    .map(String::into_bytes)
+                    .map_err(|err| err.to_string())
+            }
+            SceneFormat::Binary => write_binary(scene),
+        }
+        .map_err(SceneError::SerializeFailure)
+    }
+}
+
+/// Reads a scene file and writes it back out in the format picked by `to`'s extension.
+///
+/// The scene is copied as is, without running any migrations or needing any of its components to be registered.
+pub fn convert_scene_file(from: &Path, to: &Path) -> Result<(), SceneError> {
+    let file = std::fs::read(from).map_err(SceneError::IOError)?;
+
+    let scene = SceneFormat::detect(from, &file)?.parse(&file)?;
+    let converted = SceneFormat::from_path(to)?.write(&scene)?;
+
+    std::fs::write(to, converted).map_err(SceneError::IOError)
+}
+
+/// Mirrors [`Value`] for the binary format, since bincode can't read data without knowing its type ahead of time.
+///
+/// Encoded with bincode's variable length integers, which keeps small numbers and short strings down to a byte or two of overhead.
+/// Read back through [`BinaryValueSeed`], straight into a [`Value`].
+#[derive(Debug, Serialize)]
+enum BinaryValue {
+    Null,
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<BinaryValue>),
+    Object(Vec<(String, BinaryValue)>),
+}
+
+/// The variants of [`BinaryValue`], in the order they're encoded in.
+const BINARY_VALUE_VARIANTS: &[&str] = &[
+    "Null", "Bool", "Unsigned", "Signed", "Float", "String", "Array", "Object",
+];
+
+impl From<&Value> for BinaryValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => BinaryValue::Null,
+            Value::Bool(bool) => BinaryValue::Bool(*bool),
+            Value::Number(number) => {
+                if let Some(unsigned) = number.as_u64() {
+                    BinaryValue::Unsigned(unsigned)
+                } else if let Some(signed) = number.as_i64() {
+                    BinaryValue::Signed(signed)
+                } else {
+                    BinaryValue::Float(number.as_f64().unwrap_or_default())
+                }
+            }
+            Value::String(string) => BinaryValue::String(string.clone()),
+            Value::Array(array) => BinaryValue::Array(array.iter().map(Self::from).collect()),
+            Value::Object(object) => BinaryValue::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Self::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Reads a [`BinaryValue`] into a [`Value`], failing once arrays and objects are nested deeper than [`MAX_BINARY_DEPTH`].
+///
+/// `depth` is how many arrays and objects the value is inside of.
+struct BinaryValueSeed {
+    depth: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for BinaryValueSeed {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        if self.depth > MAX_BINARY_DEPTH {
+            return Err(D::Error::custom(format!(
+                "values are nested more than {} levels deep",
+                MAX_BINARY_DEPTH
+            )));
+        }
+
+        deserializer.deserialize_enum("BinaryValue", BINARY_VALUE_VARIANTS, self)
+    }
+}
+
+impl<'de> Visitor<'de> for BinaryValueSeed {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a binary scene value")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+        let (variant, access) = data.variant::<u32>()?;
+
+        match variant {
+            0 => access.unit_variant().map(|_| Value::Null),
+            1 => access.newtype_variant().map(Value::Bool),
+            2 => access.newtype_variant::<u64>().map(Value::from),
+            3 => access.newtype_variant::<i64>().map(Value::from),
+            4 => {
+                let float = access.newtype_variant::<f64>()?;
+
+                // NaN and infinity can't be stored in a `Value`, the same way they can't be stored in JSON
+                Number::from_f64(float).map(Value::Number).ok_or_else(|| {
+                    A::Error::invalid_value(Unexpected::Float(float), &"a finite float")
+                })
+            }
+            5 => access.newtype_variant().map(Value::String),
+            6 => access.newtype_variant_seed(BinaryArraySeed {
+                depth: self.depth + 1,
+            }),
+            7 => access.newtype_variant_seed(BinaryObjectSeed {
+                depth: self.depth + 1,
+            }),
+            other => Err(A::Error::invalid_value(
+                Unexpected::Unsigned(other as u64),
+                &"a binary scene value variant",
+            )),
+        }
+    }
+}
+
+/// Reads the elements of a [`BinaryValue::Array`], see [`BinaryValueSeed`].
+struct BinaryArraySeed {
+    depth: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for BinaryArraySeed {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for BinaryArraySeed {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a binary scene array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut array = Vec::new();
+
+        while let Some(value) = seq.next_element_seed(BinaryValueSeed { depth: self.depth })? {
+            array.push(value);
+        }
+
+        Ok(Value::Array(array))
+    }
+}
+
+/// Reads the fields of a [`BinaryValue::Object`], which are stored as a list of key and value pairs, see [`BinaryValueSeed`].
+struct BinaryObjectSeed {
+    depth: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for BinaryObjectSeed {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for BinaryObjectSeed {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a binary scene object")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut object = Map::new();
+
+        while let Some((key, value)) =
+            seq.next_element_seed(BinaryFieldSeed { depth: self.depth })?
+        {
+            object.insert(key, value);
+        }
+
+        Ok(Value::Object(object))
+    }
+}
+
+/// Reads a single key and value pair of a [`BinaryValue::Object`].
+struct BinaryFieldSeed {
+    depth: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for BinaryFieldSeed {
+    type Value = (String, Value);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'de> Visitor<'de> for BinaryFieldSeed {
+    type Value = (String, Value);
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a binary scene object field")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let key = seq
+            .next_element::<String>()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let value = seq
+            .next_element_seed(BinaryValueSeed { depth: self.depth })?
+            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+
+        Ok((key, value))
+    }
+}
+
+fn parse_binary(file: &[u8]) -> Result<Value, String> {
+    let body = file
+        .strip_prefix(BINARY_MAGIC.as_slice())
+        .ok_or("missing binary scene header")?;
+
+    if body.len() < 4 {
+        return Err("missing binary version".to_owned());
+    }
+
+    let (version, body) = body.split_at(4);
+    let version = u32::from_le_bytes(version.try_into().unwrap());
+
+    if version != BINARY_VERSION {
+        return Err(format!(
+            "binary version {} is not supported, expected {}",
+            version, BINARY_VERSION
+        ));
+    }
+
+    // every byte read comes out of the file, so lengths claiming more than that are refused before anything is allocated for them
+    bincode::DefaultOptions::new()
+        .with_limit(body.len() as u64)
+        .deserialize_seed(BinaryValueSeed { depth: 0 }, body)
+        .map_err(|err| err.to_string())
+}
+
+fn write_binary(scene: &Value) -> Result<Vec<u8>, String> {
+    let mut file = BINARY_MAGIC.to_vec();
+    file.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+
+    bincode::DefaultOptions::new()
+        .serialize_into(&mut file, &BinaryValue::from(scene))
+        .map_err(|err| err.to_string())?;
+
+    Ok(file)
+}
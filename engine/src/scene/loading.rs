@@ -0,0 +1,225 @@
+//! Loading scenes in the background, so that large scenes don't stall the game loop.
+//!
+//! [`load_scene_async`] reads, parses and migrates the scene file on its own thread. Once the file is ready, its entities are spawned
+//! a few at a time each tick, [`SceneLoads::entities_per_tick`] at most, by [`update_scene_loads`] which [`GameRoot`](crate::GameRoot) runs before every tick.
+//!
+//! [`FreezeType::LOADING`] is in effect for as long as any scene is loading, so gameplay never sees a half spawned scene.
+//! Loading screens can show [`SceneLoads::progress`] in the meantime, and wait for [`SceneLoaded`](crate::events::SceneLoaded),
+//! which is sent once a scene has finished the same as with [`load_scene`](super::load_scene).
+//!
+//! Prefabs placed in the scene are still read on the main thread once the file is ready.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::{Mut, World};
+use log::{error, trace};
+
+use super::component::{finish_loading_scene, read_scene_file};
+use super::serialized_scene::{SceneSpawner, SerializedSceneData};
+use super::{SceneError, SceneManager, SceneMigrations};
+use crate::freeze::FreezeType;
+use crate::GgezInterface;
+
+/// Every scene that is currently being loaded by [`load_scene_async`].
+#[derive(Resource)]
+pub struct SceneLoads {
+    /// The most entities spawned for each loading scene every tick.
+    pub entities_per_tick: usize,
+    loads: Vec<PendingLoad>,
+    /// Whether [`FreezeType::LOADING`] was pushed by a load and still needs to be removed
+    freeze_pushed: bool,
+}
+
+impl Default for SceneLoads {
+    fn default() -> Self {
+        Self {
+            entities_per_tick: 64,
+            loads: Vec::new(),
+            freeze_pushed: false,
+        }
+    }
+}
+
+impl SceneLoads {
+    pub fn is_loading(&self) -> bool {
+        !self.loads.is_empty()
+    }
+
+    /// How far along every pending load is on average, from `0.0` to `1.0`. Scenes whose file is still being read count as `0.0`.
+    ///
+    /// Returns `1.0` when nothing is loading.
+    pub fn progress(&self) -> f32 {
+        if self.loads.is_empty() {
+            return 1.0;
+        }
+
+        self.loads.iter().map(PendingLoad::progress).sum::<f32>() / self.loads.len() as f32
+    }
+
+    /// The paths of every scene that is still loading.
+    pub fn pending(&self) -> impl Iterator<Item = &Path> {
+        self.loads.iter().map(|load| load.path.as_path())
+    }
+}
+
+struct PendingLoad {
+    path: PathBuf,
+    stage: LoadStage,
+}
+
+enum LoadStage {
+    /// The file is being read on the loader thread.
+    ///
+    /// The receiver is only in a [`Mutex`] so that [`SceneLoads`] can be a resource, it's never locked.
+    Reading(Mutex<Receiver<Result<SerializedSceneData, SceneError>>>),
+    Spawning(Box<SceneSpawner>),
+}
+
+impl PendingLoad {
+    fn progress(&self) -> f32 {
+        match &self.stage {
+            LoadStage::Reading(_) => 0.0,
+            LoadStage::Spawning(spawner) => spawner.progress(),
+        }
+    }
+
+    /// Moves the load along by one tick, returning it back if it hasn't finished yet.
+    fn update(
+        self,
+        world: &mut World,
+        scene_manager: &mut SceneManager,
+        entities_per_tick: usize,
+    ) -> Result<Option<Self>, SceneError> {
+        let PendingLoad { path, stage } = self;
+
+        match stage {
+            LoadStage::Reading(mut receiver) => match receiver
+                .get_mut()
+                .map_err(|_| SceneError::LoadFailure("The loader was poisoned".to_owned()))?
+                .try_recv()
+            {
+                Ok(serialized_scene) => {
+                    let spawner =
+                        serialized_scene?.into_spawner(world, &scene_manager.type_registry)?;
+
+                    trace!("Read scene file {}", path.display());
+
+                    Ok(Some(PendingLoad {
+                        path,
+                        stage: LoadStage::Spawning(Box::new(spawner)),
+                    }))
+                }
+                Err(TryRecvError::Empty) => Ok(Some(PendingLoad {
+                    path,
+                    stage: LoadStage::Reading(receiver),
+                })),
+                Err(TryRecvError::Disconnected) => Err(SceneError::LoadFailure(
+                    "The loader thread stopped without sending the scene".to_owned(),
+                )),
+            },
+            LoadStage::Spawning(mut spawner) => {
+                let type_registry = &scene_manager.type_registry;
+
+                if let Err(err) = spawner.spawn_entities(world, type_registry, entities_per_tick) {
+                    spawner.abort(world);
+                    return Err(err);
+                }
+
+                if !spawner.is_done() {
+                    return Ok(Some(PendingLoad {
+                        path,
+                        stage: LoadStage::Spawning(spawner),
+                    }));
+                }
+
+                let scene_entity = spawner.finish(world, type_registry)?;
+
+                trace!("Loaded scene {} successfully", path.display());
+
+                scene_manager.add_loaded_scene(world, scene_entity)?;
+                finish_loading_scene(world, scene_entity, path);
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Starts loading the scene at `path` in the background, freezing the game with [`FreezeType::LOADING`] until it's done.
+///
+/// The loaded scene becomes the target scene once it's finished. Failing to read or spawn the scene is only logged,
+/// since it happens after this returns.
+pub fn load_scene_async(world: &mut World, path: PathBuf) -> Result<(), SceneError> {
+    let migrations = world.get_resource::<SceneMigrations>().cloned();
+    let (sender, receiver) = mpsc::channel();
+
+    let thread_path = path.clone();
+    std::thread::Builder::new()
+        .name("scene loader".to_owned())
+        .spawn(move || {
+            // the receiver is gone if the load was dropped, in which case nobody needs the scene anymore
+            let _ = sender.send(read_scene_file(&thread_path, migrations.as_ref()));
+        })
+        .map_err(SceneError::IOError)?;
+
+    trace!("Started loading scene {}", path.display());
+
+    let mut loads = world.get_resource_or_insert_with(SceneLoads::default);
+    loads.loads.push(PendingLoad {
+        path,
+        stage: LoadStage::Reading(Mutex::new(receiver)),
+    });
+
+    let push_freeze = !loads.freeze_pushed;
+    loads.freeze_pushed = true;
+
+    if push_freeze {
+        if let Some(mut engine) = world.get_resource_mut::<GgezInterface>() {
+            engine.push_freeze(FreezeType::LOADING);
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves every scene started by [`load_scene_async`] along by one tick, removing [`FreezeType::LOADING`] once all of them are done.
+pub fn update_scene_loads(world: &mut World) {
+    if !world
+        .get_resource::<SceneLoads>()
+        .is_some_and(|loads| loads.is_loading() || loads.freeze_pushed)
+    {
+        return;
+    }
+
+    world.resource_scope(|world, mut loads: Mut<SceneLoads>| {
+        let entities_per_tick = loads.entities_per_tick.max(1);
+
+        // the scene manager may have been taken out of the world by a resource scope, loads wait for it to come back
+        if world.contains_resource::<SceneManager>() {
+            world.resource_scope(|world, mut scene_manager: Mut<SceneManager>| {
+                loads.loads = std::mem::take(&mut loads.loads)
+                    .into_iter()
+                    .filter_map(|load| {
+                        let path = load.path.clone();
+
+                        load.update(world, &mut scene_manager, entities_per_tick)
+                            .unwrap_or_else(|err| {
+                                error!("Could not load scene {}: {}", path.display(), err);
+                                None
+                            })
+                    })
+                    .collect();
+            });
+        }
+
+        if !loads.is_loading() && loads.freeze_pushed {
+            loads.freeze_pushed = false;
+            if let Some(mut engine) = world.get_resource_mut::<GgezInterface>() {
+                engine.remove_freeze(&FreezeType::LOADING);
+            }
+        }
+    });
+}
@@ -0,0 +1,355 @@
+//! Versioning for scene files, so that renaming or changing a component doesn't break scenes saved before the change.
+//!
+//! Every scene file stores the `format_version` it was saved with, and files from before versioning existed count as version `0`.
+//! Whenever a component is renamed, moved or changed, add a [`Migration`] to the [`SceneMigrations`] resource with the next version number,
+//! describing how to upgrade older files. Loading a scene runs every migration newer than the file's version on the raw JSON, in order,
+//! before any component is deserialized.
+
+use bevy_ecs::system::Resource;
+use log::{trace, warn};
+use serde_json::{Map, Value};
+
+use super::SceneError;
+
+/// A single change made to a scene file by a [`Migration`].
+#[derive(Debug, Clone)]
+pub enum MigrationStep {
+    /// Changes the type path that a component or scene asset is stored under, for when it's renamed or moved to another module.
+    RenameType { from: String, to: String },
+    /// Renames a field of every component of the given type.
+    RenameField {
+        type_path: String,
+        from: String,
+        to: String,
+    },
+    /// Adds a field with the given value to every component of the given type that doesn't have it yet.
+    ///
+    /// Components overridden by prefab instances are skipped, since they only store the fields the instance changed,
+    /// and adding the default there would replace the prefab's own value.
+    DefaultField {
+        type_path: String,
+        field: String,
+        value: Value,
+    },
+    /// Runs a function on the data of every component of the given type.
+    TransformComponent {
+        type_path: String,
+        transform: fn(&mut Value),
+    },
+    /// Runs a function on the whole scene file, for changes that aren't limited to a single component.
+    TransformScene(fn(&mut Value)),
+}
+
+/// Upgrades scene files to a new format version.
+///
+/// ```ignore
+/// Migration::new(1, "Moved TextRenderer into the render module")
+///     .rename_type("components::theo_matthew_game::TextRenderer", "components::render::TextRenderer")
+///     .default_field("components::render::TextRenderer", "font_size", serde_json::json!(16.0))
+/// ```
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// The version a file is at once this migration has been run on it
+    pub version: u32,
+    pub description: String,
+    pub steps: Vec<MigrationStep>,
+}
+
+impl Migration {
+    pub fn new(version: u32, description: impl Into<String>) -> Self {
+        Self {
+            version,
+            description: description.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn rename_type(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.steps.push(MigrationStep::RenameType {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    pub fn rename_field(
+        mut self,
+        type_path: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> Self {
+        self.steps.push(MigrationStep::RenameField {
+            type_path: type_path.into(),
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    pub fn default_field(
+        mut self,
+        type_path: impl Into<String>,
+        field: impl Into<String>,
+        value: Value,
+    ) -> Self {
+        self.steps.push(MigrationStep::DefaultField {
+            type_path: type_path.into(),
+            field: field.into(),
+            value,
+        });
+        self
+    }
+
+    pub fn transform_component(
+        mut self,
+        type_path: impl Into<String>,
+        transform: fn(&mut Value),
+    ) -> Self {
+        self.steps.push(MigrationStep::TransformComponent {
+            type_path: type_path.into(),
+            transform,
+        });
+        self
+    }
+
+    pub fn transform_scene(mut self, transform: fn(&mut Value)) -> Self {
+        self.steps.push(MigrationStep::TransformScene(transform));
+        self
+    }
+
+    /// Runs every step on a scene file, in the order they were added.
+    fn apply(&self, scene: &mut Value) {
+        for step in &self.steps {
+            match step {
+                MigrationStep::RenameType { from, to } => {
+                    for components in entity_components(scene, true) {
+                        if let Some(component) = components.remove(from) {
+                            components.insert(to.clone(), component);
+                        }
+                    }
+
+                    for asset_data_type in asset_data_types(scene) {
+                        if asset_data_type == from {
+                            *asset_data_type = to.clone();
+                        }
+                    }
+
+                    for removed_component in removed_prefab_components(scene) {
+                        if removed_component.as_str() == Some(from.as_str()) {
+                            *removed_component = Value::String(to.clone());
+                        }
+                    }
+                }
+                MigrationStep::RenameField {
+                    type_path,
+                    from,
+                    to,
+                } => {
+                    for component in components_of_type(scene, type_path, true) {
+                        let Some(fields) = component.as_object_mut() else {
+                            warn!(
+                                "Could not rename field {} of {}, as it has no named fields",
+                                from, type_path
+                            );
+                            continue;
+                        };
+
+                        if let Some(field) = fields.remove(from) {
+                            fields.insert(to.clone(), field);
+                        }
+                    }
+                }
+                MigrationStep::DefaultField {
+                    type_path,
+                    field,
+                    value,
+                } => {
+                    for component in components_of_type(scene, type_path, false) {
+                        let Some(fields) = component.as_object_mut() else {
+                            warn!(
+                                "Could not add field {} to {}, as it has no named fields",
+                                field, type_path
+                            );
+                            continue;
+                        };
+
+                        fields.entry(field.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+                MigrationStep::TransformComponent {
+                    type_path,
+                    transform,
+                } => {
+                    for component in components_of_type(scene, type_path, true) {
+                        transform(component);
+                    }
+                }
+                MigrationStep::TransformScene(transform) => transform(scene),
+            }
+        }
+    }
+}
+
+/// Holds every [`Migration`] for scene files, which are run by [`load_scene`](super::load_scene).
+///
+/// The highest migration version is the current format version, which is written into every scene that gets saved.
+#[derive(Debug, Default, Clone, Resource)]
+pub struct SceneMigrations {
+    /// Sorted by version
+    migrations: Vec<Migration>,
+    /// Whether scene files should be overwritten with their upgraded version after being migrated, so the migrations don't run again next time.
+    pub rewrite_upgraded_files: bool,
+}
+
+impl SceneMigrations {
+    /// Adds a migration, replacing any migration that already had the same version.
+    pub fn add(&mut self, migration: Migration) -> &mut Self {
+        match self
+            .migrations
+            .binary_search_by_key(&migration.version, |existing| existing.version)
+        {
+            Ok(index) => {
+                warn!(
+                    "Replaced scene migration {} ({}) with ({})",
+                    migration.version, self.migrations[index].description, migration.description
+                );
+                self.migrations[index] = migration;
+            }
+            Err(index) => self.migrations.insert(index, migration),
+        }
+        self
+    }
+
+    /// The format version that scenes are saved with.
+    pub fn current_version(&self) -> u32 {
+        self.migrations
+            .last()
+            .map_or(0, |migration| migration.version)
+    }
+
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Runs every migration newer than the scene file's `format_version` on it, then sets it to the current version.
+    ///
+    /// Returns true if any migration was run. Fails if the file is newer than the current version, since it can't be downgraded.
+    pub fn migrate(&self, scene: &mut Value) -> Result<bool, SceneError> {
+        let found = format_version(scene);
+        let current = self.current_version();
+
+        if found > current {
+            return Err(SceneError::UnsupportedFormatVersion {
+                found,
+                supported: current,
+            });
+        }
+
+        if found == current {
+            return Ok(false);
+        }
+
+        for migration in self
+            .migrations
+            .iter()
+            .filter(|migration| migration.version > found)
+        {
+            trace!(
+                "Migrating scene to version {} ({})",
+                migration.version,
+                migration.description
+            );
+            migration.apply(scene);
+        }
+
+        if let Some(scene) = scene.as_object_mut() {
+            scene.insert("format_version".to_owned(), Value::from(current));
+        }
+
+        Ok(true)
+    }
+}
+
+/// Reads the `format_version` of a scene file, which is `0` for files saved before versioning was added.
+pub fn format_version(scene: &Value) -> u32 {
+    scene
+        .get("format_version")
+        .and_then(Value::as_u64)
+        .map_or(0, |version| version as u32)
+}
+
+/// Returns the component map of every entity in a scene or prefab file.
+///
+/// If `include_overrides` is true, the components overridden by prefab instances are returned too, which only hold the fields the instance changed.
+fn entity_components(
+    scene: &mut Value,
+    include_overrides: bool,
+) -> impl Iterator<Item = &mut Map<String, Value>> {
+    let Some(scene) = scene.as_object_mut() else {
+        return Vec::new().into_iter();
+    };
+
+    let mut components = Vec::new();
+
+    for (key, value) in scene.iter_mut() {
+        match key.as_str() {
+            "entity_data" => components.extend(
+                value
+                    .as_object_mut()
+                    .into_iter()
+                    .flat_map(|entities| entities.values_mut())
+                    .filter_map(Value::as_object_mut),
+            ),
+            "prefabs" if include_overrides => components.extend(
+                value
+                    .as_object_mut()
+                    .into_iter()
+                    .flat_map(|instances| instances.values_mut())
+                    .filter_map(|instance| instance.get_mut("overrides"))
+                    .filter_map(Value::as_object_mut)
+                    .flat_map(|overrides| overrides.values_mut())
+                    .filter_map(Value::as_object_mut),
+            ),
+            _ => (),
+        }
+    }
+
+    components.into_iter()
+}
+
+/// Returns the type path of every component removed from a prefab instance in a scene file.
+fn removed_prefab_components(scene: &mut Value) -> impl Iterator<Item = &mut Value> {
+    scene
+        .get_mut("prefabs")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|instances| instances.values_mut())
+        .filter_map(|instance| instance.get_mut("removed_components"))
+        .filter_map(Value::as_object_mut)
+        .flat_map(|removed| removed.values_mut())
+        .filter_map(Value::as_array_mut)
+        .flatten()
+}
+
+/// Returns the type path stored with every asset in a scene file.
+fn asset_data_types(scene: &mut Value) -> impl Iterator<Item = &mut String> {
+    scene
+        .get_mut("asset_data")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|assets| assets.values_mut())
+        .filter_map(|asset| match asset.get_mut("asset_data_type") {
+            Some(Value::String(asset_data_type)) => Some(asset_data_type),
+            _ => None,
+        })
+}
+
+/// Returns the data of every component of the given type in a scene file, see [`entity_components`] for `include_overrides`.
+fn components_of_type<'a>(
+    scene: &'a mut Value,
+    type_path: &'a str,
+    include_overrides: bool,
+) -> impl Iterator<Item = &'a mut Value> {
+    entity_components(scene, include_overrides)
+        .filter_map(move |components| components.get_mut(type_path))
+}
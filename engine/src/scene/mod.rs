@@ -1,8 +1,14 @@
 mod component;
 mod converter;
+mod diff;
+mod entity_ref;
 mod error;
+mod format;
+mod loading;
+mod migration;
 mod object_data;
 mod object_id;
+mod prefab;
 mod scene_manager;
 mod serialized_scene;
 #[cfg(test)]
@@ -10,11 +16,18 @@ mod test;
 
 pub use component::Scene;
 pub use component::{
-    add_entity_to_scene, create_serializable_scene_data, load_scene, new_scene, save_scene,
-    unload_scene, validate_name,
+    add_entity_to_scene, create_serializable_scene_data, export_scene, load_scene, new_scene,
+    save_scene, unload_scene, validate_name,
 };
 pub use converter::*;
+pub use diff::{SceneChange, ScenePatch};
+pub use entity_ref::EntityRef;
 pub use error::SceneError;
+pub use format::{
+    convert_scene_file, SceneFormat, BINARY_MAGIC, BINARY_VERSION, MAX_BINARY_DEPTH,
+};
+pub use loading::{load_scene_async, update_scene_loads, SceneLoads};
+pub use migration::{format_version, Migration, MigrationStep, SceneMigrations};
 pub use object_data::CustomSerialization;
 pub use object_data::CustomSerializationData;
 pub use object_data::ReflectTestSuperTrait;
@@ -22,6 +35,7 @@ pub use object_data::SceneData;
 pub use object_data::TestSuperTrait;
 pub use object_id::CounterType;
 pub use object_id::ObjectID;
-pub use object_id::{ComponentInstanceID, Counter, IDCounter};
+pub use object_id::{ComponentInstanceID, Counter, IDCounter, SceneEntityID};
+pub use prefab::{spawn_prefab, Prefab, PrefabInstance, PrefabLink};
 pub use scene_manager::SceneManager;
 pub use serialized_scene::ToReflect;
@@ -9,7 +9,10 @@ use bevy_reflect::{reflect_trait, FromType, Reflect};
 use erased_serde::{Error, Serializer};
 use serde::Serialize;
 
-use super::{object_id::ComponentInstanceID, ObjectID, Scene, SceneManager};
+use super::{
+    object_id::{ComponentInstanceID, SceneEntityID},
+    ObjectID, Scene, SceneManager,
+};
 
 /// Holds data from the assigned [`Scene`] to operate upon.
 /// An entity cannot be serialized by the [`Scene`] if it does not have this component.
@@ -24,6 +27,8 @@ pub struct SceneData {
     pub entity_name: String,
     /// The ID of the current scene that the component holder belongs to.
     pub scene_id: Option<ObjectID>,
+    /// The ID that other entities in the scene use to refer to this entity, which is saved along with the scene.
+    pub entity_id: SceneEntityID,
     /// Contains the component path of every component that is reflectable.
     // pub component_paths: HashMap<ComponentInstanceID, String>,
     // pub component_ids: HashMap<String, ComponentInstanceID>,
@@ -1,4 +1,5 @@
 use bevy_reflect::Reflect;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -42,6 +43,32 @@ impl IDCounter for ComponentInstanceID {
     }
 }
 
+/// An identifier for an entity that stays the same every time its scene is saved and loaded, unlike [`Entity`](bevy_ecs::entity::Entity).
+///
+/// Handed out by [`Scene::new_entity_id`](super::Scene::new_entity_id), and only unique inside of the scene the entity belongs to.
+/// `0` is never handed out, and marks an entity that hasn't been given an ID yet.
+#[derive(
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
+pub struct SceneEntityID(pub(crate) u64);
+
+impl core::fmt::Display for SceneEntityID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug, Eq, Clone, Copy, PartialOrd, Reflect)]
 // #[reflect_value]
 pub struct ObjectID {
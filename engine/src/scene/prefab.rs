@@ -0,0 +1,471 @@
+//! Prefabs, reusable entity templates that are stored in their own files and can be placed into any number of scenes.
+//!
+//! A prefab file holds the same component data as a scene's `entity_data`, along with the `hierarchy` between its entities
+//! and the name of the `root` entity that the rest hang off of. Prefab files can be stored in any [`SceneFormat`].
+//!
+//! Scenes don't copy a prefab's components. Instead, each placed copy is stored as a [`PrefabInstance`], holding the prefab's path
+//! and only the component fields that the instance changed. Since every instance is rebuilt from the prefab file whenever its scene loads,
+//! editing a prefab updates every instance of it the next time they're loaded.
+//!
+//! The root entity of an instance is named after the instance, and every other entity is named `<instance>/<entity>`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::world::World;
+use bevy_reflect::TypeRegistry;
+use log::{error, trace, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::component::serialize_components;
+use super::serialized_scene::insert_components;
+use super::{
+    add_entity_to_scene, SceneData, SceneError, SceneFormat, SceneMigrations, TestSuperTrait,
+};
+use crate::space::{set_parent, Children};
+
+/// An entity, or a tree of entities, that can be placed into scenes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Prefab {
+    /// The [`SceneMigrations::current_version`] the prefab was saved with, the same as a scene's.
+    #[serde(default)]
+    pub format_version: u32,
+    /// The name of the entity that every other entity is attached to, directly or through other entities.
+    pub root: String,
+    /// Component data of every entity, keyed by the entity's name and then the component's type path.
+    pub entity_data: HashMap<String, HashMap<String, Value>>,
+    /// The name of each entity's parent, keyed by the entity's name. The root is left out.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub hierarchy: HashMap<String, String>,
+}
+
+/// A placed copy of a prefab, as it's stored in a scene file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrefabInstance {
+    pub prefab: PathBuf,
+    /// Component fields the instance changed, keyed by the name of the entity in the prefab and then the component's type path.
+    ///
+    /// Components that the prefab doesn't have are stored whole.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub overrides: HashMap<String, HashMap<String, Value>>,
+    /// Type paths of the components removed from the instance, keyed by the name of the entity in the prefab.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub removed_components: HashMap<String, Vec<String>>,
+    /// Entities of the prefab that were despawned from the instance.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_entities: Vec<String>,
+}
+
+/// Marks an entity as part of a prefab instance, so that saving its scene only stores what changed from the prefab.
+#[derive(Debug, Component, Clone, PartialEq, Eq)]
+pub struct PrefabLink {
+    pub prefab: PathBuf,
+    /// The name of the instance's root entity
+    pub instance: String,
+    /// The name of the entity inside of the prefab
+    pub entity: String,
+}
+
+/// A single entity of a prefab instance, with the instance's changes already applied.
+#[derive(Debug)]
+pub(crate) struct InstancedEntity {
+    /// The entity's name in the scene
+    pub name: String,
+    pub components: HashMap<String, Value>,
+    /// The scene name of the entity's parent
+    pub parent: Option<String>,
+    pub link: PrefabLink,
+}
+
+impl Prefab {
+    /// Reads a prefab file, running any [`SceneMigrations`] on it the same way as scene files.
+    ///
+    /// Like scene files, the file is overwritten with the migrated prefab if [`SceneMigrations::rewrite_upgraded_files`] is set.
+    pub fn load(path: &Path, world: &World) -> Result<Self, SceneError> {
+        Self::read_file(path, world, true)
+    }
+
+    /// Same as [`Prefab::load`], but never overwrites the file.
+    ///
+    /// Used while saving scenes, which shouldn't change any files besides the scene's own.
+    pub fn read(path: &Path, world: &World) -> Result<Self, SceneError> {
+        Self::read_file(path, world, false)
+    }
+
+    fn read_file(path: &Path, world: &World, rewrite: bool) -> Result<Self, SceneError> {
+        let file = std::fs::read(path).map_err(SceneError::IOError)?;
+
+        let format = SceneFormat::detect(path, &file)?;
+        let mut value = format.parse(&file)?;
+
+        if let Some(migrations) = world.get_resource::<SceneMigrations>() {
+            let migrated = migrations.migrate(&mut value)?;
+
+            if migrated && rewrite && migrations.rewrite_upgraded_files {
+                trace!("Rewriting migrated prefab file");
+
+                std::fs::write(path, format.write(&value)?).map_err(SceneError::IOError)?;
+            }
+        }
+
+        let prefab =
+            serde_json::from_value::<Prefab>(value).map_err(|err| SceneError::InvalidPrefab {
+                path: path.to_owned(),
+                reason: err.to_string(),
+            })?;
+
+        if !prefab.entity_data.contains_key(&prefab.root) {
+            return Err(SceneError::InvalidPrefab {
+                path: path.to_owned(),
+                reason: format!("the root entity {} has no entity data", prefab.root),
+            });
+        }
+
+        Ok(prefab)
+    }
+
+    /// Writes the prefab to a file, in the format picked by the file's extension.
+    pub fn save(&self, path: &Path) -> Result<(), SceneError> {
+        let value = serde_json::to_value(self)
+            .map_err(|err| SceneError::SerializeFailure(err.to_string()))?;
+
+        std::fs::write(path, SceneFormat::from_path(path)?.write(&value)?)
+            .map_err(SceneError::IOError)
+    }
+
+    /// Builds a prefab out of an entity in a scene and every entity attached to it.
+    ///
+    /// Entities are stored under their scene names, so every one of them needs a [`SceneData`] component.
+    pub fn from_entity(
+        world: &mut World,
+        root: Entity,
+        type_registry: &TypeRegistry,
+    ) -> Result<Self, SceneError> {
+        let mut prefab = Prefab {
+            format_version: world
+                .get_resource::<SceneMigrations>()
+                .map_or(0, SceneMigrations::current_version),
+            root: entity_name(world, root)?,
+            ..Default::default()
+        };
+
+        let mut query = world.query::<&dyn TestSuperTrait>();
+        let mut stack = vec![root];
+
+        while let Some(entity) = stack.pop() {
+            let name = entity_name(world, entity)?;
+
+            let components = match query.get(world, entity) {
+                Ok(components) => serialize_components(components.iter(), type_registry),
+                Err(_) => HashMap::new(),
+            };
+            prefab.entity_data.insert(name.clone(), components);
+
+            for child in world
+                .get::<Children>(entity)
+                .into_iter()
+                .flat_map(|c| c.iter())
+            {
+                prefab
+                    .hierarchy
+                    .insert(entity_name(world, *child)?, name.clone());
+                stack.push(*child);
+            }
+        }
+
+        Ok(prefab)
+    }
+
+    /// Builds the entities of an instance of this prefab, applying the instance's changes if there are any.
+    pub(crate) fn instantiate(
+        &self,
+        path: &Path,
+        instance_name: &str,
+        instance: Option<&PrefabInstance>,
+    ) -> Vec<InstancedEntity> {
+        let is_removed = |entity: &String| {
+            instance.is_some_and(|instance| instance.removed_entities.contains(entity))
+        };
+
+        let mut entities = Vec::new();
+
+        for (entity, components) in &self.entity_data {
+            if is_removed(entity) {
+                continue;
+            }
+
+            let mut components = components.clone();
+
+            if let Some(instance) = instance {
+                for removed in instance
+                    .removed_components
+                    .get(entity)
+                    .into_iter()
+                    .flatten()
+                {
+                    components.remove(removed);
+                }
+
+                for (component_path, patch) in instance.overrides.get(entity).into_iter().flatten()
+                {
+                    match components.get_mut(component_path) {
+                        Some(component) => apply_override(component, patch),
+                        None => {
+                            components.insert(component_path.clone(), patch.clone());
+                        }
+                    }
+                }
+            }
+
+            let parent = self
+                .hierarchy
+                .get(entity)
+                .filter(|parent| !is_removed(parent))
+                .map(|parent| self.instance_entity_name(instance_name, parent));
+
+            entities.push(InstancedEntity {
+                name: self.instance_entity_name(instance_name, entity),
+                components,
+                parent,
+                link: PrefabLink {
+                    prefab: path.to_owned(),
+                    instance: instance_name.to_owned(),
+                    entity: entity.clone(),
+                },
+            });
+        }
+
+        entities
+    }
+
+    /// The name that an entity of this prefab gets inside of a scene.
+    pub fn instance_entity_name(&self, instance_name: &str, entity: &str) -> String {
+        if entity == self.root {
+            instance_name.to_owned()
+        } else {
+            format!("{}/{}", instance_name, entity)
+        }
+    }
+}
+
+/// Spawns an instance of the prefab at `path` into a scene, returning the instance's root entity.
+///
+/// The root is named `instance_name`, with a number added to the end if the scene already has an entity with that name.
+pub fn spawn_prefab(
+    world: &mut World,
+    scene_entity: Entity,
+    path: &Path,
+    instance_name: &str,
+    type_registry: &TypeRegistry,
+) -> Result<Entity, SceneError> {
+    let prefab = Prefab::load(path, world)?;
+
+    // The root is added first, so that the other entities are named after the root's final name
+    let root = world.spawn_empty().id();
+    add_entity_to_scene(world, scene_entity, root, Some(instance_name.to_owned()))?;
+    let instance_name = entity_name(world, root)?;
+
+    let mut spawned = HashMap::new();
+    let mut parents = Vec::new();
+
+    for instanced in prefab.instantiate(path, &instance_name, None) {
+        let entity = if instanced.link.entity == prefab.root {
+            root
+        } else {
+            let entity = world.spawn_empty().id();
+            add_entity_to_scene(world, scene_entity, entity, Some(instanced.name.clone()))?;
+            entity
+        };
+
+        let mut entity_mut = world.entity_mut(entity);
+        insert_components(&mut entity_mut, instanced.components, type_registry)?;
+        entity_mut.insert(instanced.link);
+
+        spawned.insert(instanced.name, entity);
+        if let Some(parent) = instanced.parent {
+            parents.push((entity, parent));
+        }
+    }
+
+    for (entity, parent) in parents {
+        let Some(parent) = spawned.get(&parent) else {
+            continue;
+        };
+
+        if let Err(err) = set_parent(world, entity, Some(*parent)) {
+            warn!(
+                "Could not rebuild the hierarchy of {}: {}",
+                instance_name, err
+            );
+        }
+    }
+
+    trace!("Spawned prefab {} as {}", path.display(), instance_name);
+
+    Ok(root)
+}
+
+/// Pulls the entities of every prefab instance out of a scene's serialized data, replacing them with [`PrefabInstance`]s
+/// that only store what changed from the prefab.
+///
+/// Instances whose prefab can't be read anymore are left as regular entities, so nothing is lost.
+pub(crate) fn extract_prefab_instances(
+    world: &World,
+    scene_entities: &[Entity],
+    entity_data: &mut HashMap<String, HashMap<String, Value>>,
+    hierarchy: &mut HashMap<String, String>,
+) -> HashMap<String, PrefabInstance> {
+    let mut members: HashMap<&str, Vec<(&str, &PrefabLink)>> = HashMap::new();
+
+    for entity in scene_entities {
+        let (Some(link), Some(scene_data)) = (
+            world.get::<PrefabLink>(*entity),
+            world.get::<SceneData>(*entity),
+        ) else {
+            continue;
+        };
+
+        members
+            .entry(link.instance.as_str())
+            .or_default()
+            .push((scene_data.entity_name.as_str(), link));
+    }
+
+    let mut instances = HashMap::new();
+
+    for (instance_name, members) in members {
+        let path = &members[0].1.prefab;
+
+        let prefab = match Prefab::read(path, world) {
+            Ok(prefab) => prefab,
+            Err(err) => {
+                error!(
+                    "Could not read prefab {}, saving the entities of {} without it: {}",
+                    path.display(),
+                    instance_name,
+                    err
+                );
+                continue;
+            }
+        };
+
+        let mut instance = PrefabInstance {
+            prefab: path.clone(),
+            ..Default::default()
+        };
+
+        for (name, link) in &members {
+            // Entities taken out of the prefab since the scene was loaded are kept as regular entities
+            let Some(prefab_components) = prefab.entity_data.get(&link.entity) else {
+                continue;
+            };
+
+            let components = entity_data.remove(*name).unwrap_or_default();
+
+            let removed = prefab_components
+                .keys()
+                .filter(|component_path| !components.contains_key(*component_path))
+                .cloned()
+                .collect::<Vec<String>>();
+            if !removed.is_empty() {
+                instance
+                    .removed_components
+                    .insert(link.entity.clone(), removed);
+            }
+
+            let mut overrides = HashMap::new();
+            for (component_path, component) in components {
+                let changed = match prefab_components.get(&component_path) {
+                    Some(prefab_component) => find_override(prefab_component, &component),
+                    None => Some(component),
+                };
+
+                if let Some(changed) = changed {
+                    overrides.insert(component_path, changed);
+                }
+            }
+            if !overrides.is_empty() {
+                instance.overrides.insert(link.entity.clone(), overrides);
+            }
+
+            // The prefab rebuilds its own hierarchy, so only attachments made in the scene are kept
+            let prefab_parent = prefab
+                .hierarchy
+                .get(&link.entity)
+                .map(|parent| prefab.instance_entity_name(instance_name, parent));
+            if prefab_parent.is_some() && hierarchy.get(*name) == prefab_parent.as_ref() {
+                hierarchy.remove(*name);
+            }
+        }
+
+        instance.removed_entities = prefab
+            .entity_data
+            .keys()
+            .filter(|entity| !members.iter().any(|(_, link)| link.entity == **entity))
+            .cloned()
+            .collect();
+
+        instances.insert(instance_name.to_owned(), instance);
+    }
+
+    instances
+}
+
+/// Applies the fields of an override on top of a prefab's component data.
+///
+/// Objects are merged field by field, while any other value replaces the prefab's value outright.
+fn apply_override(component: &mut Value, patch: &Value) {
+    match (component, patch) {
+        (Value::Object(fields), Value::Object(patch_fields)) => {
+            for (field, patch) in patch_fields {
+                match fields.get_mut(field) {
+                    Some(value) => apply_override(value, patch),
+                    None => {
+                        fields.insert(field.clone(), patch.clone());
+                    }
+                }
+            }
+        }
+        (component, patch) => *component = patch.clone(),
+    }
+}
+
+/// Returns the fields of `component` that differ from the prefab's data, or [`None`] if nothing changed.
+fn find_override(prefab_component: &Value, component: &Value) -> Option<Value> {
+    match (prefab_component, component) {
+        (Value::Object(prefab_fields), Value::Object(fields)) => {
+            let mut changed = Map::new();
+
+            for (field, value) in fields {
+                let field_override = match prefab_fields.get(field) {
+                    Some(prefab_value) => find_override(prefab_value, value),
+                    None => Some(value.clone()),
+                };
+
+                if let Some(field_override) = field_override {
+                    changed.insert(field.clone(), field_override);
+                }
+            }
+
+            (!changed.is_empty()).then_some(Value::Object(changed))
+        }
+        // Hand written prefabs may store `1` where the component is serialized as `1.0`
+        (Value::Number(prefab_number), Value::Number(number))
+            if prefab_number.as_f64() == number.as_f64() =>
+        {
+            None
+        }
+        (prefab_value, value) if prefab_value == value => None,
+        (_, value) => Some(value.clone()),
+    }
+}
+
+fn entity_name(world: &World, entity: Entity) -> Result<String, SceneError> {
+    world
+        .get::<SceneData>(entity)
+        .map(|scene_data| scene_data.entity_name.clone())
+        .ok_or(SceneError::NoSceneDataComponent)
+}
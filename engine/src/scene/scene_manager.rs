@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use bevy_ecs::entity::Entity;
 use bevy_ecs::system::Resource;
@@ -78,6 +78,32 @@ impl SceneManager {
         )
     }
 
+    /// Exports the target scene to a file in the format picked by the path's extension, see [`export_scene`](super::export_scene).
+    pub fn export_scene(&self, world: &mut World, path: &Path) -> Result<(), error::SceneError> {
+        super::export_scene(
+            self.target_scene.ok_or(error::SceneError::NoTargetScene)?,
+            world,
+            &self.type_registry,
+            path,
+        )
+    }
+
+    /// Spawns an instance of the prefab at `path` into the target scene, returning the instance's root entity.
+    pub fn spawn_prefab(
+        &self,
+        world: &mut World,
+        path: &Path,
+        instance_name: &str,
+    ) -> Result<Entity, error::SceneError> {
+        super::spawn_prefab(
+            world,
+            self.target_scene.ok_or(error::SceneError::NoTargetScene)?,
+            path,
+            instance_name,
+            &self.type_registry,
+        )
+    }
+
     pub fn load_scene(
         &mut self,
         world: &mut World,
@@ -86,30 +112,77 @@ impl SceneManager {
         let result = load_scene(path, world, &self.type_registry);
 
         if let Ok(entity) = result {
-            let scene_name = world
-                .get::<Scene>(entity)
-                .ok_or(error::SceneError::LoadFailure(
-                    "Failed to find the scene component on the newly instantiated scene".to_owned(),
-                ))?
-                .name
-                .to_owned();
-            self.current_scenes.insert(scene_name, entity);
-            self.scenes_by_ids
-                .insert(world.get::<Scene>(entity).unwrap().scene_id, entity);
-
-            self.target_scene = Some(entity);
+            self.add_loaded_scene(world, entity)?;
         }
         result
     }
 
+    /// Starts loading a scene in the background, see [`load_scene_async`](super::load_scene_async).
+    ///
+    /// Unlike [`SceneManager::load_scene`], the scene only becomes the target scene once it has finished loading.
+    pub fn load_scene_async(
+        &self,
+        world: &mut World,
+        path: PathBuf,
+    ) -> Result<(), error::SceneError> {
+        super::load_scene_async(world, path)
+    }
+
+    /// Keeps track of a scene that just finished loading, and makes it the target scene.
+    pub(crate) fn add_loaded_scene(
+        &mut self,
+        world: &World,
+        entity: Entity,
+    ) -> Result<(), error::SceneError> {
+        let scene = world
+            .get::<Scene>(entity)
+            .ok_or(error::SceneError::LoadFailure(
+                "Failed to find the scene component on the newly instantiated scene".to_owned(),
+            ))?;
+
+        self.current_scenes.insert(scene.name.to_owned(), entity);
+        self.scenes_by_ids.insert(scene.scene_id, entity);
+
+        self.target_scene = Some(entity);
+        Ok(())
+    }
+
     pub fn unload_scene(&mut self, world: &mut World) -> Result<(), error::SceneError> {
         if let Some(target_scene) = self.target_scene.take() {
-            Ok(unload_scene(target_scene, world)?)
+            unload_scene(target_scene, world)?;
+
+            self.current_scenes
+                .retain(|_, scene_entity| *scene_entity != target_scene);
+            self.scenes_by_ids
+                .retain(|_, scene_entity| *scene_entity != target_scene);
+
+            Ok(())
         } else {
             Err(error::SceneError::NoTargetScene)
         }
     }
 
+    /// Unloads the scene with the given name, whether or not it's the target scene. Does not save.
+    pub fn unload_scene_by_name(
+        &mut self,
+        world: &mut World,
+        name: &str,
+    ) -> Result<(), error::SceneError> {
+        let scene_entity = self
+            .current_scenes
+            .remove(name)
+            .ok_or(error::SceneError::NoTargetScene)?;
+
+        self.scenes_by_ids
+            .retain(|_, entity| *entity != scene_entity);
+
+        if self.target_scene == Some(scene_entity) {
+            self.target_scene = None;
+        }
+
+        unload_scene(scene_entity, world)
+    }
+
     pub fn get_scene_by_id(&self, id: ObjectID) -> Option<Entity> {
         self.scenes_by_ids.get(&id).copied()
     }
@@ -3,13 +3,18 @@ use crate::assets::SerializedAsset;
 use crate::render::DowncastInsert;
 use crate::scene::object_id::ComponentInstanceID;
 use crate::scene::IDCounter;
+use crate::space::set_parent;
 
 use super::add_entity_to_scene;
 use super::component;
+use super::entity_ref::resolve_entity_refs;
 use super::object_data::SceneData;
+use super::object_id::SceneEntityID;
+use super::prefab::{Prefab, PrefabInstance, PrefabLink};
 use super::SceneError;
 use bevy_ecs::entity::Entity;
 use bevy_ecs::reflect::ReflectComponent;
+use bevy_ecs::world::EntityWorldMut;
 use bevy_ecs::world::World;
 use bevy_reflect::DynamicList;
 use bevy_reflect::DynamicMap;
@@ -59,8 +64,18 @@ pub trait ToReflect {
 #[derive(Debug)]
 pub struct SerializedSceneData {
     pub name: String,
+    /// The [`SceneMigrations::current_version`](super::SceneMigrations::current_version) the scene was saved with, `0` if it was saved before versioning existed.
+    pub format_version: u32,
     pub entity_data: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// The [`SceneEntityID`] of each entity, keyed by the entity's name. Entities missing from it are given a new ID when loaded.
+    pub entity_ids: HashMap<String, SceneEntityID>,
     pub asset_data: HashMap<String, serde_json::Value>,
+    /// The name of each entity's [`Parent`](crate::space::Parent), keyed by the entity's name. Entities without a parent are left out.
+    pub hierarchy: HashMap<String, String>,
+    /// Every placed [`Prefab`](super::Prefab), keyed by the name of the instance's root entity.
+    pub prefabs: HashMap<String, PrefabInstance>,
+    /// The state of the [`Random`](crate::random::Random) resource when the scene was saved, which replaces the resource when the scene is loaded.
+    pub rng: Option<crate::random::Random>,
 }
 
 impl SerializedSceneData {
@@ -74,13 +89,121 @@ impl SerializedSceneData {
         world: &mut World,
         type_registry: &TypeRegistry,
     ) -> Result<Entity, SceneError> {
+        let mut spawner = self.into_spawner(world, type_registry)?;
+        spawner.spawn_entities(world, type_registry, usize::MAX)?;
+        spawner.finish(world, type_registry)
+    }
+
+    /// Initializes the scene's assets and expands its prefab instances, without spawning any entities yet.
+    ///
+    /// Used to spread the spawning of large scenes over several ticks, see [`SceneSpawner`].
+    pub(crate) fn into_spawner(
+        self,
+        world: &mut World,
+        type_registry: &TypeRegistry,
+    ) -> Result<SceneSpawner, SceneError> {
         trace!("Initializing new scene ({})...", self.name);
 
-        let scene = component::Scene::new(self.name.to_owned());
+        let mut scene = component::Scene::new(self.name.to_owned());
+
+        for entity_id in self.entity_ids.values() {
+            scene.reserve_entity_id(*entity_id);
+        }
+
+        // Assets come first, so that a broken asset doesn't leave any entities behind
+        for (asset_name, serialized_asset_data) in self.asset_data {
+            trace!("Initializing asset {}", asset_name);
+            scene.initialize_asset(&asset_name, serialized_asset_data, type_registry)?;
+        }
+
+        let mut entity_data = self.entity_data;
+        let mut hierarchy = self.hierarchy;
+        let mut prefab_links = HashMap::new();
+
+        for (instance_name, instance) in self.prefabs {
+            trace!("Initializing prefab instance {}", instance_name);
+
+            let prefab = Prefab::load(&instance.prefab, world)?;
+
+            for instanced in prefab.instantiate(&instance.prefab, &instance_name, Some(&instance)) {
+                if entity_data.contains_key(&instanced.name) {
+                    warn!(
+                        "Skipped {} from prefab instance {}, as the scene already has an entity with that name",
+                        instanced.name, instance_name
+                    );
+                    continue;
+                }
+
+                if let Some(parent) = instanced.parent {
+                    hierarchy.entry(instanced.name.clone()).or_insert(parent);
+                }
+                prefab_links.insert(instanced.name.clone(), instanced.link);
+                entity_data.insert(instanced.name, instanced.components);
+            }
+        }
+
+        let entity_data = entity_data.into_iter().collect::<Vec<_>>();
+
+        Ok(SceneSpawner {
+            total_entities: entity_data.len(),
+            scene,
+            entity_data,
+            entity_ids: self.entity_ids,
+            hierarchy,
+            prefab_links,
+            rng: self.rng,
+            entities: Vec::new(),
+            entities_by_name: HashMap::new(),
+        })
+    }
+}
+
+/// A scene part way through being initialized, created by [`SerializedSceneData::into_spawner`].
+///
+/// Entities are spawned in batches through [`SceneSpawner::spawn_entities`], but are not added to a [`Scene`] until [`SceneSpawner::finish`] is called.
+pub(crate) struct SceneSpawner {
+    scene: component::Scene,
+    /// Entities that have yet to be spawned
+    entity_data: Vec<(String, HashMap<String, Value>)>,
+    entity_ids: HashMap<String, SceneEntityID>,
+    hierarchy: HashMap<String, String>,
+    prefab_links: HashMap<String, PrefabLink>,
+    rng: Option<crate::random::Random>,
+    total_entities: usize,
+    entities: Vec<Entity>,
+    entities_by_name: HashMap<String, Entity>,
+}
+
+impl SceneSpawner {
+    pub fn name(&self) -> &str {
+        &self.scene.name
+    }
+
+    /// How much of the scene has been spawned, from `0.0` to `1.0`.
+    pub fn progress(&self) -> f32 {
+        if self.total_entities == 0 {
+            return 1.0;
+        }
+
+        self.entities.len() as f32 / self.total_entities as f32
+    }
 
-        let mut entities: Vec<Entity> = Vec::new();
+    pub fn is_done(&self) -> bool {
+        self.entity_data.is_empty()
+    }
+
+    /// Spawns up to `count` of the scene's remaining entities.
+    pub fn spawn_entities(
+        &mut self,
+        world: &mut World,
+        type_registry: &TypeRegistry,
+        count: usize,
+    ) -> Result<(), SceneError> {
+        for _ in 0..count {
+            let Some((entity_name, component_data_hashmap)) = self.entity_data.pop() else {
+                break;
+            };
 
-        for (entity_name, component_data_hashmap) in self.entity_data {
             trace!("Initializing new entity ({})", &entity_name);
             trace!("Component list: {:?}", component_data_hashmap);
 
@@ -97,89 +220,163 @@ impl SerializedSceneData {
                 .last();
 
             let bundle = SceneData {
+                entity_id: self
+                    .entity_ids
+                    .get(&entity_name)
+                    .copied()
+                    .unwrap_or_default(),
                 entity_name,
-                scene_id: Some(scene.scene_id),
+                scene_id: Some(self.scene.scene_id),
 
                 hide_in_inspector: true,
             };
 
             let entity_name_debug = bundle.entity_name.clone();
+            let entity_name = bundle.entity_name.clone();
 
             let mut entity = world.spawn(bundle);
 
             trace!("Spawned {} with SceneData component", entity_name_debug);
 
-            for (component_path, component_data) in component_data_hashmap {
-                trace!("Initializing component {}", component_path);
-
-                let component_registration: &bevy_reflect::TypeRegistration = type_registry
-                    .get_with_type_path(&component_path)
-                    .ok_or(SceneError::MissingTypeRegistry(component_path.to_owned()))?;
-
-                let reflect_component = component_registration.data::<ReflectComponent>().ok_or(
-                    SceneError::MissingTypeRegistry(format!(
-                        "The {} component is missing a #[reflect(Component)] helper",
-                        component_path
-                    )),
-                )?;
-
-                let reflect_deserialize = component_registration
-                    .data::<ReflectDeserialize>()
-                    .ok_or(SceneError::NoSerializationImplementation(format!(
-                    "The {} component is missing deserialization type data in the type registry",
-                    component_path
-                )))?;
-
-                let type_info = component_registration.type_info();
-
-                let value = component_data.to_string();
-
-                let mut json = serde_json::Deserializer::from_str(value.as_str());
-
-                let value = match reflect_deserialize.deserialize(&mut json) {
-                    Ok(ok) => ok,
-                    Err(err) => {
-                        log::error!(
-                            // your deserialization implementation returned an error, so it was omitted.
-                            "Could not deserialize {} [{}] \nvalue: {}",
-                            component_path,
-                            err.to_string(),
-                            value
-                        );
-                        continue;
-                    }
-                };
+            // Keep track of the entity before its components are inserted, so that it's cleaned up by `abort` if they fail
+            self.entities.push(entity.id());
+            self.entities_by_name
+                .insert(entity_name.clone(), entity.id());
 
-                json.end().unwrap();
-
-                type_registry
-                    .get_with_type_path((*value).reflect_type_path())
-                    .unwrap()
-                    .data::<DowncastInsert>()
-                    .unwrap()
-                    .downcast_insert(&mut entity, value);
+            insert_components(&mut entity, component_data_hashmap, type_registry)?;
 
-                trace!(
-                    "Inserted {} into {} successfully.",
-                    component_path,
-                    entity.get::<SceneData>().unwrap().entity_name
-                )
+            if let Some(link) = self.prefab_links.remove(&entity_name) {
+                entity.insert(link);
             }
-            entities.push(entity.id());
         }
 
-        for (asset_name, serialized_asset_data) in self.asset_data {
-            trace!("Skipped {}. {:?}", asset_name, serialized_asset_data);
+        Ok(())
+    }
+
+    /// Spawns the scene entity, adds every spawned entity to it, then restores the references and hierarchy between them.
+    ///
+    /// Any entities that have yet to be spawned are spawned first.
+    pub fn finish(
+        mut self,
+        world: &mut World,
+        type_registry: &TypeRegistry,
+    ) -> Result<Entity, SceneError> {
+        self.spawn_entities(world, type_registry, usize::MAX)?;
+
+        if let Some(rng) = self.rng {
+            trace!("Restored random state with seed {}", rng.seed());
+            world.insert_resource(rng);
         }
 
-        let scene_entity = world.spawn(scene).id();
+        let entities = self.entities;
+        let entities_by_name = self.entities_by_name;
+
+        let scene_entity = world.spawn(self.scene).id();
 
         // We have to wait until after the scene entity is spawned before we can start adding entities to the scene component
-        for entity in entities {
-            let _ = add_entity_to_scene(world, scene_entity, entity, None);
+        for entity in &entities {
+            let _ = add_entity_to_scene(world, scene_entity, *entity, None);
+        }
+
+        // Every entity has its ID now, so references between them can be pointed at the live entities
+        resolve_entity_refs(world, &entities, type_registry);
+
+        for (child_name, parent_name) in self.hierarchy {
+            let (Some(child), Some(parent)) = (
+                entities_by_name.get(&child_name),
+                entities_by_name.get(&parent_name),
+            ) else {
+                warn!(
+                    "Could not attach {} to {}, as one of them is not in the scene",
+                    child_name, parent_name
+                );
+                continue;
+            };
+
+            if let Err(err) = set_parent(world, *child, Some(*parent)) {
+                warn!(
+                    "Could not attach {} to {}: {}",
+                    child_name, parent_name, err
+                );
+            }
         }
         Ok(scene_entity)
     }
+
+    /// Despawns every entity spawned so far, for when loading the scene is cancelled or fails part way through.
+    pub fn abort(self, world: &mut World) {
+        for entity in self.entities {
+            world.despawn(entity);
+        }
+    }
+}
+
+/// Deserializes every component of an entity from its scene data and inserts them into the entity.
+///
+/// Components that fail to deserialize are skipped, but components that aren't registered properly fail the whole entity.
+pub(crate) fn insert_components(
+    entity: &mut EntityWorldMut,
+    component_data_hashmap: HashMap<String, Value>,
+    type_registry: &TypeRegistry,
+) -> Result<(), SceneError> {
+    for (component_path, component_data) in component_data_hashmap {
+        trace!("Initializing component {}", component_path);
+
+        let component_registration: &bevy_reflect::TypeRegistration = type_registry
+            .get_with_type_path(&component_path)
+            .ok_or(SceneError::MissingTypeRegistry(component_path.to_owned()))?;
+
+        let reflect_component = component_registration.data::<ReflectComponent>().ok_or(
+            SceneError::MissingTypeRegistry(format!(
+                "The {} component is missing a #[reflect(Component)] helper",
+                component_path
+            )),
+        )?;
+
+        let reflect_deserialize = component_registration.data::<ReflectDeserialize>().ok_or(
+            SceneError::NoSerializationImplementation(format!(
+                "The {} component is missing deserialization type data in the type registry",
+                component_path
+            )),
+        )?;
+
+        let type_info = component_registration.type_info();
+
+        let value = component_data.to_string();
+
+        let mut json = serde_json::Deserializer::from_str(value.as_str());
+
+        let value = match reflect_deserialize.deserialize(&mut json) {
+            Ok(ok) => ok,
+            Err(err) => {
+                log::error!(
+                    // your deserialization implementation returned an error, so it was omitted.
+                    "Could not deserialize {} [{}] \nvalue: {}",
+                    component_path,
+                    err.to_string(),
+                    value
+                );
+                continue;
+            }
+        };
+
+        json.end().unwrap();
+
+        type_registry
+            .get_with_type_path((*value).reflect_type_path())
+            .unwrap()
+            .data::<DowncastInsert>()
+            .unwrap()
+            .downcast_insert(entity, value);
+
+        trace!(
+            "Inserted {} into {} successfully.",
+            component_path,
+            entity.get::<SceneData>().unwrap().entity_name
+        )
+    }
+
+    Ok(())
 }
 
 impl Serialize for SerializedSceneData {
@@ -190,8 +387,19 @@ impl Serialize for SerializedSceneData {
         let mut serialize_struct = serializer.serialize_struct("SerializedScene", 2)?;
 
         let _ = serialize_struct.serialize_field("name", &self.name);
+        let _ = serialize_struct.serialize_field("format_version", &self.format_version);
         let _ = serialize_struct.serialize_field("entity_data", &self.entity_data);
+        let _ = serialize_struct.serialize_field("entity_ids", &self.entity_ids);
         let _ = serialize_struct.serialize_field("asset_data", &self.asset_data);
+        if !self.hierarchy.is_empty() {
+            let _ = serialize_struct.serialize_field("hierarchy", &self.hierarchy);
+        }
+        if !self.prefabs.is_empty() {
+            let _ = serialize_struct.serialize_field("prefabs", &self.prefabs);
+        }
+        if let Some(rng) = &self.rng {
+            let _ = serialize_struct.serialize_field("rng", rng);
+        }
         serialize_struct.end()
     }
 }
@@ -206,7 +414,16 @@ impl<'de> Deserialize<'de> for SerializedSceneData {
     {
         deserializer.deserialize_struct(
             "SerializedScene",
-            &["name", "entity_data", "asset_data"],
+            &[
+                "name",
+                "format_version",
+                "entity_data",
+                "entity_ids",
+                "asset_data",
+                "hierarchy",
+                "prefabs",
+                "rng",
+            ],
             SceneVisitor,
         )
     }
@@ -228,16 +445,26 @@ impl<'de> Visitor<'de> for SceneVisitor {
     {
         let mut serialized_scene = SerializedSceneData {
             name: String::new(),
+            format_version: 0,
             entity_data: HashMap::new(),
+            entity_ids: HashMap::new(),
             asset_data: HashMap::new(),
+            hierarchy: HashMap::new(),
+            prefabs: HashMap::new(),
+            rng: None,
         };
 
         // This at one point was like 20 lines long
         while let Some(key) = map.next_key::<String>()? {
             match key.as_str() {
                 "name" => serialized_scene.name = map.next_value()?,
+                "format_version" => serialized_scene.format_version = map.next_value()?,
                 "entity_data" => serialized_scene.entity_data = map.next_value()?,
+                "entity_ids" => serialized_scene.entity_ids = map.next_value()?,
                 "asset_data" => serialized_scene.asset_data = map.next_value()?,
+                "hierarchy" => serialized_scene.hierarchy = map.next_value()?,
+                "prefabs" => serialized_scene.prefabs = map.next_value()?,
+                "rng" => serialized_scene.rng = map.next_value()?,
                 _ => (),
             };
         }
@@ -1,51 +1,567 @@
-use std::any::Any;
+//! Contains the [`ScheduleBuilder`], which builds the engine's schedules out of systems registered by name.
+//!
+//! Instead of every game crate building its own copy of each schedule, systems are registered once with [`ScheduleBuilder::register_system`],
+//! then placed into a schedule and one of the engine defined [`EngineSet`]s through a [`ScheduleDefinition`].
+//! Definitions are plain data, so they can be written by hand, loaded from a file with [`ScheduleBuilder::load_definitions`],
+//! or extended by other crates (like the editor) without needing to rebuild the whole schedule.
+//!
+//! [`add_schedules`] is called by [`GameRoot`](crate::GameRoot) after every [`EnginePlugin`](crate::EnginePlugin) has been built,
+//! which turns every definition into a [`Schedule`] and adds it to the world.
 
+use crate::freeze::{FreezeBehaviour, FreezeType};
+use crate::profiler::{ProfilerHandle, SystemProfiler};
+use crate::GgezInterface;
 use bevy_ecs::prelude::*;
-use bevy_ecs::schedule::{Schedule, ScheduleBuildSettings, ScheduleLabel, SystemConfigs};
-use egui::ahash::HashMap;
+use bevy_ecs::schedule::{
+    Condition, ExecutorKind, LogLevel, Schedule, ScheduleBuildSettings, ScheduleLabel, Schedules,
+    SystemConfigs,
+};
+use bevy_ecs::system::AdapterSystem;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 
-use crate::space::Position;
+/// Builds every [`ScheduleDefinition`] in the world's [`ScheduleBuilder`] and adds them to the world.
+///
+/// If a schedule with the same [`ScheduleTag`] was already added by hand, the defined systems are added on top of it instead of replacing it.
+pub fn add_schedules(world: &mut World) -> Result<(), ScheduleError> {
+    // only exists once a schedule has been added, which plugins don't have to do by hand
+    world.init_resource::<Schedules>();
 
-pub fn add_schedules(world: &mut World, schedule_builders: Vec<fn() -> Schedule>) {
-    for builder in schedule_builders {
-        world.add_schedule(builder())
+    world.resource_scope(|world, builder: Mut<ScheduleBuilder>| {
+        for tag in builder.definitions.keys() {
+            let mut schedules = world.resource_mut::<Schedules>();
+
+            match schedules.get_mut(*tag) {
+                Some(schedule) => builder.add_to_schedule(*tag, schedule)?,
+                None => {
+                    schedules.insert(builder.build(*tag)?);
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// The build settings used by schedules created through a [`ScheduleBuilder`].
+pub static DEFAULT_BUILD_SETTINGS: ScheduleBuildSettings = ScheduleBuildSettings {
+    ambiguity_detection: LogLevel::Warn,
+    hierarchy_detection: LogLevel::Warn,
+    use_shortnames: false,
+    report_sets: true,
+    auto_insert_apply_deferred: true,
+};
+
+/// Labelled groups of systems that the engine defines inside its schedules.
+///
+/// Sets run in the order given by [`ScheduleDefinition::set_order`], which defaults to [`EngineSet::DEFAULT_ORDER`].
+#[derive(Debug, SystemSet, Hash, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum EngineSet {
+    /// Reads player input and turns it into intent, such as which direction a character should move.
+    Input,
+    /// Gameplay rules that react to input and change state, such as setting velocities.
+    GameLogic,
+    /// Moves objects using their velocities and resolves collisions.
+    Physics,
+    /// Draws the current state to the screen.
+    Render,
+}
+
+impl EngineSet {
+    /// Input is read first, game logic acts on it, physics applies the results, and rendering draws them.
+    pub const DEFAULT_ORDER: [EngineSet; 4] = [
+        EngineSet::Input,
+        EngineSet::GameLogic,
+        EngineSet::Physics,
+        EngineSet::Render,
+    ];
+
+    fn default_order() -> Vec<EngineSet> {
+        Self::DEFAULT_ORDER.to_vec()
+    }
+}
+
+/// A set containing a single system, named after the name it was registered with.
+///
+/// Used to order systems against each other by name, which is how systems in the same [`EngineSet`] keep the order they were listed in.
+#[derive(Debug, SystemSet, Hash, Eq, PartialEq, Clone)]
+pub struct NamedSystem(pub String);
+
+/// Which executor a built schedule should use. Mirrors [`ExecutorKind`], which can't be serialized.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleExecutor {
+    Simple,
+    SingleThreaded,
+    #[default]
+    MultiThreaded,
+}
+
+impl From<ScheduleExecutor> for ExecutorKind {
+    fn from(value: ScheduleExecutor) -> Self {
+        match value {
+            ScheduleExecutor::Simple => ExecutorKind::Simple,
+            ScheduleExecutor::SingleThreaded => ExecutorKind::SingleThreaded,
+            ScheduleExecutor::MultiThreaded => ExecutorKind::MultiThreaded,
+        }
+    }
+}
+
+/// A single system inside of a [`ScheduleDefinition`], referred to by the name it was registered with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemEntry {
+    pub name: String,
+    /// The set this system belongs to. Systems without a set aren't ordered against anything.
+    #[serde(default)]
+    pub set: Option<EngineSet>,
+    /// How this system behaves while the game is frozen. Only used in [`ScheduleTag::Tick`].
+    #[serde(default)]
+    pub freeze: FreezeBehaviour,
+}
+
+impl SystemEntry {
+    /// Keeps this system running while the game is frozen by `freeze`'s variant. Can be called more than once to add more variants.
+    pub fn runs_during(&mut self, freeze: FreezeType) -> &mut Self {
+        match &mut self.freeze {
+            FreezeBehaviour::RunsDuring(allowed) => allowed.push(freeze),
+            behaviour => *behaviour = FreezeBehaviour::RunsDuring(vec![freeze]),
+        }
+        self
+    }
+
+    /// Only pauses this system while the game is frozen by `freeze`'s variant. Can be called more than once to add more variants.
+    pub fn paused_by(&mut self, freeze: FreezeType) -> &mut Self {
+        match &mut self.freeze {
+            FreezeBehaviour::PausedBy(paused) => paused.push(freeze),
+            behaviour => *behaviour = FreezeBehaviour::PausedBy(vec![freeze]),
+        }
+        self
+    }
+
+    /// Keeps this system running no matter the freeze state, except while paused for debugging by [`FreezeType::DEBUG`].
+    pub fn always_runs(&mut self) -> &mut Self {
+        self.freeze = FreezeBehaviour::Always;
+        self
     }
 }
 
-#[derive(Debug, Resource)]
+/// The list of systems that make up a schedule, which can be written in code or loaded from data.
+///
+/// Systems in the same [`EngineSet`] run in the order they are listed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleDefinition {
+    pub tag: ScheduleTag,
+    #[serde(default)]
+    pub executor: ScheduleExecutor,
+    /// The order that sets are run in. Sets left out of this list aren't ordered against the others.
+    #[serde(default = "EngineSet::default_order")]
+    pub set_order: Vec<EngineSet>,
+    #[serde(default)]
+    pub systems: Vec<SystemEntry>,
+}
+
+impl ScheduleDefinition {
+    pub fn new(tag: ScheduleTag, executor: ScheduleExecutor) -> Self {
+        Self {
+            tag,
+            executor,
+            set_order: EngineSet::default_order(),
+            systems: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScheduleError {
+    /// A definition referred to a system name that was never registered.
+    MissingSystem {
+        schedule: ScheduleTag,
+        system: String,
+    },
+    /// The same system name was listed twice in one schedule.
+    DuplicateSystem {
+        schedule: ScheduleTag,
+        system: String,
+    },
+    /// Definitions could not be read from a file.
+    IOError(std::io::Error),
+    /// Definitions could not be parsed.
+    InvalidDefinition(String),
+}
+
+impl std::fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::MissingSystem { schedule, system } => write!(
+                f,
+                "Schedule {:?} uses system \"{}\", which has not been registered",
+                schedule, system
+            ),
+            ScheduleError::DuplicateSystem { schedule, system } => write!(
+                f,
+                "Schedule {:?} lists system \"{}\" more than once",
+                schedule, system
+            ),
+            ScheduleError::IOError(err) => write!(f, "{}", err),
+            ScheduleError::InvalidDefinition(err) => {
+                write!(f, "Invalid schedule definition: {}", err)
+            }
+        }
+    }
+}
+
+/// Adds a registered system to a schedule, placing it in the given sets, pausing it according to the freeze behaviour if given,
+/// and timing it with the profiler if given.
+type AddSystemFn = Box<
+    dyn Fn(
+            &mut Schedule,
+            NamedSystem,
+            Option<EngineSet>,
+            Option<FreezeBehaviour>,
+            Option<SystemProfiler>,
+        ) + Send
+        + Sync,
+>;
+
+/// Holds every system registered by name, and the [`ScheduleDefinition`]s that use them.
+///
+/// The engine inserts this before building any [`EnginePlugin`](crate::EnginePlugin)s, with definitions for
+/// [`ScheduleTag::Tick`], [`ScheduleTag::Frame`] and [`ScheduleTag::Init`] and its own systems already registered.
+/// The Tick definition starts out counting down the components in [`crate::timer`], then moving objects and propagating their [`GlobalTransform`](crate::space::GlobalTransform)s
+/// at the start of [`EngineSet::Physics`]. Every other definition starts out empty.
+#[derive(Resource)]
 pub struct ScheduleBuilder {
-    schedule_settings: ScheduleBuildSettings,
-    pub systems: Vec<fn()>,
-    tag: ScheduleTag,
+    registry: HashMap<String, AddSystemFn>,
+    definitions: HashMap<ScheduleTag, ScheduleDefinition>,
+    profiler: Option<Arc<ProfilerHandle>>,
+}
+
+impl Default for ScheduleBuilder {
+    fn default() -> Self {
+        let mut builder = Self {
+            registry: HashMap::new(),
+            definitions: HashMap::new(),
+            profiler: None,
+        };
+
+        builder.add_definition(ScheduleDefinition::new(
+            ScheduleTag::Tick,
+            ScheduleExecutor::MultiThreaded,
+        ));
+        builder.add_definition(ScheduleDefinition::new(
+            ScheduleTag::Frame,
+            ScheduleExecutor::SingleThreaded,
+        ));
+        builder.add_definition(ScheduleDefinition::new(
+            ScheduleTag::Init,
+            ScheduleExecutor::MultiThreaded,
+        ));
+
+        builder.register_system("space_update", crate::space::update);
+        builder.register_system("transform_propagate", crate::space::propagate_transforms);
+        builder.register_system("timer_update", crate::timer::timer_update);
+        builder.register_system("cooldown_update", crate::timer::cooldown_update);
+        builder.register_system("despawn_after_update", crate::timer::despawn_after_update);
+
+        // objects move and then update their global transforms before any physics or rendering systems a game adds,
+        // which all read GlobalTransform instead of the local transform components
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::Physics, "space_update");
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::Physics, "transform_propagate");
+
+        // timers count before any gameplay logic, so that gameplay sees timers that finish this tick
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::GameLogic, "timer_update");
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::GameLogic, "cooldown_update");
+        builder.add_to_set(
+            ScheduleTag::Tick,
+            EngineSet::GameLogic,
+            "despawn_after_update",
+        );
+
+        builder
+    }
+}
+
+impl std::fmt::Debug for ScheduleBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScheduleBuilder")
+            .field("registry", &self.registry.keys().collect::<Vec<_>>())
+            .field("definitions", &self.definitions)
+            .finish()
+    }
 }
 
 impl ScheduleBuilder {
-    pub fn build(&self) -> Schedule {
-        let mut schedule = Schedule::new(self.tag.clone());
+    /// Registers a system under the given name, so that definitions can refer to it.
+    ///
+    /// Registering a different system under a name that's already taken replaces the old one.
+    pub fn register_system<M>(
+        &mut self,
+        name: impl Into<String>,
+        system: impl IntoSystem<(), (), M> + Clone + Send + Sync + 'static,
+    ) {
+        self.register_configured(name.into(), system, |configs| configs);
+    }
 
-        for system in &self.systems {
-            schedule.add_systems(system.clone());
+    /// Same as [`ScheduleBuilder::register_system`], but the system only runs while `condition` is true.
+    ///
+    /// The condition is added on top of the system's freeze behaviour, so both have to allow it to run.
+    /// Use [`in_state`](crate::state::in_state) to only run a system in certain [`GameState`](crate::state::GameState)s.
+    pub fn register_system_if<M, C>(
+        &mut self,
+        name: impl Into<String>,
+        system: impl IntoSystem<(), (), M> + Clone + Send + Sync + 'static,
+        condition: impl Condition<C> + Clone + Send + Sync + 'static,
+    ) {
+        self.register_configured(name.into(), system, move |configs| {
+            configs.run_if(condition.clone())
+        });
+    }
+
+    fn register_configured<M>(
+        &mut self,
+        name: String,
+        system: impl IntoSystem<(), (), M> + Clone + Send + Sync + 'static,
+        configure: impl Fn(SystemConfigs) -> SystemConfigs + Send + Sync + 'static,
+    ) {
+        self.registry.insert(
+            name,
+            Box::new(
+                move |schedule: &mut Schedule,
+                      named: NamedSystem,
+                      set: Option<EngineSet>,
+                      freeze: Option<FreezeBehaviour>,
+                      profiler: Option<SystemProfiler>| {
+                    let mut configs = configure(match profiler {
+                        Some(profiler) => {
+                            let system = IntoSystem::into_system(system.clone());
+                            let name = system.name();
+                            AdapterSystem::new(profiler, system, name).into_configs()
+                        }
+                        None => system.clone().into_configs(),
+                    })
+                    .in_set(named);
+
+                    if let Some(set) = set {
+                        configs = configs.in_set(set);
+                    }
+
+                    if let Some(freeze) = freeze {
+                        configs = configs
+                            .run_if(move |engine: Res<GgezInterface>| freeze.should_run(&engine));
+                    }
+
+                    schedule.add_systems(configs);
+                },
+            ),
+        );
+    }
+
+    /// Times every system built from now on with the given profiler.
+    pub(crate) fn set_profiler(&mut self, profiler: Arc<ProfilerHandle>) {
+        self.profiler = Some(profiler);
+    }
+
+    /// Returns true if a system has been registered under the given name.
+    pub fn has_system(&self, name: &str) -> bool {
+        self.registry.contains_key(name)
+    }
+
+    /// Adds a definition, replacing any existing definition for the same [`ScheduleTag`].
+    pub fn add_definition(&mut self, definition: ScheduleDefinition) {
+        self.definitions.insert(definition.tag, definition);
+    }
+
+    /// Merges a definition into the existing definition for the same [`ScheduleTag`], or adds it if there isn't one.
+    ///
+    /// The executor and set order are replaced, while systems are matched by name:
+    /// a system that is already listed has its entry replaced in place, and any other system is appended.
+    /// Systems only listed in the existing definition, like the engine's own, are kept.
+    pub fn merge_definition(&mut self, definition: ScheduleDefinition) {
+        let Some(existing) = self.definitions.get_mut(&definition.tag) else {
+            self.add_definition(definition);
+            return;
+        };
+
+        existing.executor = definition.executor;
+        existing.set_order = definition.set_order;
+
+        for entry in definition.systems {
+            match existing
+                .systems
+                .iter_mut()
+                .find(|existing_entry| existing_entry.name == entry.name)
+            {
+                Some(existing_entry) => *existing_entry = entry,
+                None => existing.systems.push(entry),
+            }
+        }
+    }
+
+    pub fn get_definition(&self, tag: ScheduleTag) -> Option<&ScheduleDefinition> {
+        self.definitions.get(&tag)
+    }
+
+    /// Returns the definition for the given tag, creating an empty multithreaded one if it doesn't exist yet.
+    pub fn definition_mut(&mut self, tag: ScheduleTag) -> &mut ScheduleDefinition {
+        self.definitions
+            .entry(tag)
+            .or_insert_with(|| ScheduleDefinition::new(tag, ScheduleExecutor::MultiThreaded))
+    }
+
+    /// Appends a registered system to the end of the given set in the given schedule.
+    ///
+    /// The system doesn't need to be registered yet, but it must be by the time the schedule is built.
+    ///
+    /// Returns the new entry, so that its freeze behaviour can be changed with [`SystemEntry::runs_during`] and the like.
+    pub fn add_to_set(
+        &mut self,
+        tag: ScheduleTag,
+        set: EngineSet,
+        name: impl Into<String>,
+    ) -> &mut SystemEntry {
+        self.push_entry(tag, name.into(), Some(set))
+    }
+
+    /// Appends a registered system to the given schedule, without placing it in a set.
+    pub fn add_to_schedule_unsorted(
+        &mut self,
+        tag: ScheduleTag,
+        name: impl Into<String>,
+    ) -> &mut SystemEntry {
+        self.push_entry(tag, name.into(), None)
+    }
+
+    fn push_entry(
+        &mut self,
+        tag: ScheduleTag,
+        name: String,
+        set: Option<EngineSet>,
+    ) -> &mut SystemEntry {
+        let systems = &mut self.definition_mut(tag).systems;
+
+        systems.push(SystemEntry {
+            name,
+            set,
+            freeze: FreezeBehaviour::default(),
+        });
+
+        systems.last_mut().unwrap()
+    }
+
+    /// Loads a JSON list of [`ScheduleDefinition`]s from a file, merging each into the existing definition with the same tag.
+    ///
+    /// See [`ScheduleBuilder::merge_definition`], which keeps systems that the file doesn't list.
+    pub fn load_definitions(&mut self, path: impl AsRef<Path>) -> Result<(), ScheduleError> {
+        let file = std::fs::read_to_string(path).map_err(ScheduleError::IOError)?;
+
+        let definitions = serde_json::from_str::<Vec<ScheduleDefinition>>(&file)
+            .map_err(|err| ScheduleError::InvalidDefinition(err.to_string()))?;
+
+        for definition in definitions {
+            self.merge_definition(definition);
         }
 
-        schedule.set_build_settings(self.schedule_settings.clone());
+        Ok(())
+    }
+
+    /// Builds a new [`Schedule`] from the definition with the given tag.
+    ///
+    /// If there is no definition for the tag, an empty schedule is returned.
+    pub fn build(&self, tag: ScheduleTag) -> Result<Schedule, ScheduleError> {
+        let mut schedule = Schedule::new(tag);
+
+        schedule.set_build_settings(DEFAULT_BUILD_SETTINGS.clone());
+
+        if let Some(definition) = self.definitions.get(&tag) {
+            schedule.set_executor_kind(definition.executor.into());
+        }
 
-        schedule
+        self.add_to_schedule(tag, &mut schedule)?;
+
+        Ok(schedule)
     }
 
-    pub fn add_schedule<M>(&mut self, system: fn()) {
-        self.systems.push(system);
+    /// Adds every system in the definition with the given tag to an existing schedule, along with the set ordering.
+    pub fn add_to_schedule(
+        &self,
+        tag: ScheduleTag,
+        schedule: &mut Schedule,
+    ) -> Result<(), ScheduleError> {
+        let Some(definition) = self.definitions.get(&tag) else {
+            return Ok(());
+        };
+
+        if definition.set_order.len() > 1 {
+            for pair in definition.set_order.windows(2) {
+                schedule.configure_sets(pair[0].before(pair[1]));
+            }
+        }
+
+        let mut last_in_set: HashMap<EngineSet, &str> = HashMap::new();
+        let mut added: Vec<&str> = Vec::new();
+
+        for entry in &definition.systems {
+            let add_system =
+                self.registry
+                    .get(&entry.name)
+                    .ok_or_else(|| ScheduleError::MissingSystem {
+                        schedule: tag,
+                        system: entry.name.clone(),
+                    })?;
+
+            if added.contains(&entry.name.as_str()) {
+                return Err(ScheduleError::DuplicateSystem {
+                    schedule: tag,
+                    system: entry.name.clone(),
+                });
+            }
+            added.push(&entry.name);
+
+            // freezing only ever pauses game logic, so other schedules ignore freeze behaviour
+            let freeze = match tag {
+                ScheduleTag::Tick => Some(entry.freeze.clone()),
+                _ => None,
+            };
+
+            let profiler = self
+                .profiler
+                .as_ref()
+                .map(|handle| SystemProfiler::new(handle.clone(), tag, &entry.name));
+
+            add_system(
+                schedule,
+                NamedSystem(entry.name.clone()),
+                entry.set,
+                freeze,
+                profiler,
+            );
+
+            if let Some(set) = entry.set {
+                if let Some(previous) = last_in_set.insert(set, &entry.name) {
+                    schedule.configure_sets(
+                        NamedSystem(entry.name.clone()).after(NamedSystem(previous.to_owned())),
+                    );
+                }
+            }
+        }
+
+        Ok(())
     }
 }
 
 /// A value representing this schedule's behaviour, for when it should be run
-#[derive(Debug, ScheduleLabel, Hash, Eq, PartialEq, Clone, Copy)]
+#[derive(Debug, ScheduleLabel, Hash, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum ScheduleTag {
-    /// Runs game logic as normal.
+    /// Runs game logic, every tick, even while frozen.
     ///
-    /// See also [`ScheduleTag::FreezeTick`] and [`crate::freeze::FreezeType`], or [`ScheduleTag::Frame`] for rendering
+    /// Each system decides whether it pauses while frozen through run conditions, see [`crate::freeze`] and [`crate::freeze::FreezeBehaviour`].
+    /// See [`ScheduleTag::Frame`] for rendering.
     Tick,
 
-    /// Most game logic is paused at the moment, but some functionality can still run to maintain presentation.
+    /// Optional. If added, this is run instead of [`ScheduleTag::Tick`] while frozen.
+    ///
+    /// Prefer giving systems in the Tick schedule a freeze behaviour instead, which avoids adding the same system to both schedules.
     FreezeTick,
 
     /// The game is using the current stored state and rendering to the screen.
@@ -0,0 +1,265 @@
+//! Parent/child relationships between entities, and the [`GlobalTransform`] that is computed from them.
+//!
+//! An entity's [`Position`], [`Rotation`] and [`Scale`] are local to its [`Parent`], if it has one.
+//! [`propagate_transforms`] walks down from the entities without a parent every tick and stores where each entity actually ends up in its [`GlobalTransform`],
+//! which is what renderers and colliders should read.
+//!
+//! Always change the hierarchy through [`set_parent`] or the [`SetParent`] command, so that [`Parent`] and [`Children`] stay in sync.
+
+use std::fmt::Display;
+use std::ops::Deref;
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::Command;
+use log::{error, warn};
+
+use super::{Position, Rotation, Scale, Vector2};
+
+/// The entity this entity is attached to. Its transform components are relative to the parent's [`GlobalTransform`].
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub(crate) Entity);
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// Every entity attached to this one, in the order they were attached.
+#[derive(Debug, Component, Clone, Default)]
+pub struct Children(pub(crate) Vec<Entity>);
+
+impl Deref for Children {
+    type Target = [Entity];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Where an entity ends up in the world after applying the transforms of all of its parents.
+///
+/// Computed by [`propagate_transforms`], so changing it directly does nothing. Change the [`Position`], [`Rotation`] and [`Scale`] instead.
+#[derive(Debug, Component, Clone, Copy, PartialEq)]
+pub struct GlobalTransform {
+    pub position: Vector2,
+    /// In radians, the same as [`Rotation`]
+    pub rotation: f32,
+    pub scale: Vector2,
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl GlobalTransform {
+    pub const IDENTITY: Self = Self {
+        position: Vector2::new(0.0, 0.0),
+        rotation: 0.0,
+        scale: Vector2::new(1.0, 1.0),
+    };
+
+    /// Builds a transform out of an entity's local transform components, using the identity for any that are missing.
+    pub fn from_local(
+        position: Option<&Position>,
+        rotation: Option<&Rotation>,
+        scale: Option<&Scale>,
+    ) -> Self {
+        Self {
+            position: position.map_or(Self::IDENTITY.position, |position| **position),
+            rotation: rotation.map_or(Self::IDENTITY.rotation, |rotation| **rotation),
+            scale: scale.map_or(Self::IDENTITY.scale, |scale| **scale),
+        }
+    }
+
+    /// Moves a point from this transform's local space into world space.
+    pub fn transform_point(&self, point: Vector2) -> Vector2 {
+        let scaled = point.scaled(&self.scale);
+        let (sin, cos) = self.rotation.sin_cos();
+
+        Vector2::new(
+            self.position.x + scaled.x * cos - scaled.y * sin,
+            self.position.y + scaled.x * sin + scaled.y * cos,
+        )
+    }
+
+    /// Returns the global transform of a child with the given local transform, when this is its parent's global transform.
+    pub fn mul_transform(&self, local: &GlobalTransform) -> Self {
+        Self {
+            position: self.transform_point(local.position),
+            rotation: self.rotation + local.rotation,
+            scale: self.scale.scaled(&local.scale),
+        }
+    }
+}
+
+impl From<GlobalTransform> for ggez::graphics::Transform {
+    fn from(value: GlobalTransform) -> Self {
+        ggez::graphics::Transform::Values {
+            dest: mint::Point2::<f32> {
+                x: value.position.x,
+                y: value.position.y,
+            },
+            rotation: value.rotation,
+            scale: value.scale.into(),
+            offset: mint::Point2 { x: 0.0, y: 0.0 },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyError {
+    /// The entity does not exist, or has been despawned.
+    MissingEntity(Entity),
+    /// The parent is the child itself, or one of the child's descendants.
+    Cycle { child: Entity, parent: Entity },
+}
+
+impl Display for HierarchyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HierarchyError::MissingEntity(entity) => {
+                write!(f, "Entity {:?} does not exist", entity)
+            }
+            HierarchyError::Cycle { child, parent } => write!(
+                f,
+                "Cannot attach {:?} to {:?}, as it would become its own ancestor",
+                child, parent
+            ),
+        }
+    }
+}
+
+/// Attaches `child` to `parent`, detaching it from its old parent first. Passing [`None`] as the parent only detaches it.
+///
+/// Fails without changing anything if either entity is missing, or if the parent is the child or one of its descendants.
+pub fn set_parent(
+    world: &mut World,
+    child: Entity,
+    parent: Option<Entity>,
+) -> Result<(), HierarchyError> {
+    if world.get_entity(child).is_none() {
+        return Err(HierarchyError::MissingEntity(child));
+    }
+
+    if let Some(parent) = parent {
+        if world.get_entity(parent).is_none() {
+            return Err(HierarchyError::MissingEntity(parent));
+        }
+
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            if entity == child {
+                return Err(HierarchyError::Cycle { child, parent });
+            }
+            ancestor = world.get::<Parent>(entity).map(Parent::get);
+        }
+    }
+
+    if let Some(old_parent) = world.get::<Parent>(child).map(Parent::get) {
+        if let Some(mut children) = world.get_mut::<Children>(old_parent) {
+            children.0.retain(|entity| *entity != child);
+        }
+    }
+
+    let Some(parent) = parent else {
+        world.entity_mut(child).remove::<Parent>();
+        return Ok(());
+    };
+
+    world.entity_mut(child).insert(Parent(parent));
+
+    let mut parent = world.entity_mut(parent);
+    match parent.get_mut::<Children>() {
+        Some(mut children) => children.0.push(child),
+        None => {
+            parent.insert(Children(vec![child]));
+        }
+    }
+
+    Ok(())
+}
+
+/// [`Command`] version of [`set_parent`], for changing the hierarchy from inside of systems.
+#[derive(Debug, Clone, Copy)]
+pub struct SetParent {
+    pub child: Entity,
+    pub parent: Option<Entity>,
+}
+
+impl Command for SetParent {
+    fn apply(self, world: &mut World) {
+        if let Err(err) = set_parent(world, self.child, self.parent) {
+            error!("Could not set parent: {}", err);
+        }
+    }
+}
+
+/// Computes the [`GlobalTransform`] of every entity in the hierarchy, walking down the [`Children`] of entities without a parent.
+///
+/// Entities that have transform components or are part of a hierarchy are given a [`GlobalTransform`] if they don't have one yet,
+/// which shows up once this system's commands are applied.
+/// Entities whose parent was despawned are treated as having no parent.
+#[allow(clippy::type_complexity)]
+pub fn propagate_transforms(
+    mut commands: Commands,
+    roots: Query<
+        Entity,
+        (
+            Without<Parent>,
+            Or<(With<Position>, With<Children>, With<GlobalTransform>)>,
+        ),
+    >,
+    orphans: Query<(Entity, &Parent)>,
+    mut transforms: Query<(
+        Option<&Position>,
+        Option<&Rotation>,
+        Option<&Scale>,
+        Option<&mut GlobalTransform>,
+    )>,
+    children: Query<&Children>,
+    parents: Query<&Parent>,
+    mut stack: Local<Vec<(Entity, GlobalTransform)>>,
+) {
+    stack.extend(roots.iter().map(|root| (root, GlobalTransform::IDENTITY)));
+    stack.extend(
+        orphans
+            .iter()
+            // every entity matches the transforms query, so this only fails for despawned parents
+            .filter(|(_, parent)| !transforms.contains(parent.get()))
+            .map(|(orphan, _)| (orphan, GlobalTransform::IDENTITY)),
+    );
+
+    while let Some((entity, parent_global)) = stack.pop() {
+        let Ok((position, rotation, scale, global_transform)) = transforms.get_mut(entity) else {
+            continue;
+        };
+
+        let global =
+            parent_global.mul_transform(&GlobalTransform::from_local(position, rotation, scale));
+        match global_transform {
+            Some(mut global_transform) => *global_transform = global,
+            None => {
+                commands.entity(entity).insert(global);
+            }
+        }
+
+        let Ok(entity_children) = children.get(entity) else {
+            continue;
+        };
+        for &child in entity_children.iter() {
+            // a child that doesn't point back at this entity was attached without set_parent,
+            // and following it could visit entities more than once or never stop
+            if parents.get(child).map(Parent::get) != Ok(entity) {
+                warn!(
+                    "Entity {:?} is a child of {:?}, but its parent is not set to match",
+                    child, entity
+                );
+                continue;
+            }
+            stack.push((child, global));
+        }
+    }
+}
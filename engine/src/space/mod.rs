@@ -8,6 +8,7 @@
 // don't you love it when you have 17 impl blocks in one file yay fun
 // anyways i split them up into several blocks so as to make organization a little bit easier yw
 
+pub(crate) mod hierarchy;
 pub(crate) mod pos;
 pub(crate) mod rtt;
 pub(crate) mod scl;
@@ -15,9 +16,15 @@ pub(crate) mod transform;
 pub(crate) mod vel;
 pub(crate) mod vtx;
 
+#[cfg(test)]
+mod test;
+
 use bevy_ecs::prelude::*;
 use log::error;
 use mint::Point2;
+pub use hierarchy::{
+    propagate_transforms, set_parent, Children, GlobalTransform, HierarchyError, Parent, SetParent,
+};
 pub use pos::Position;
 pub use rtt::Rotation;
 pub use scl::Scale;
@@ -0,0 +1,95 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy_ecs::system::RunSystemOnce;
+use bevy_ecs::world::World;
+
+use super::{
+    propagate_transforms, set_parent, Children, GlobalTransform, HierarchyError, Parent, Position,
+    Rotation, Scale, Vector2,
+};
+
+fn assert_near(actual: Vector2, expected: Vector2) {
+    assert!(
+        (actual.x - expected.x).abs() < 1e-4 && (actual.y - expected.y).abs() < 1e-4,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn set_parent_rejects_cycles() {
+    let mut world = World::new();
+    let grandparent = world.spawn_empty().id();
+    let parent = world.spawn_empty().id();
+    let child = world.spawn_empty().id();
+
+    set_parent(&mut world, parent, Some(grandparent)).unwrap();
+    set_parent(&mut world, child, Some(parent)).unwrap();
+
+    assert_eq!(
+        set_parent(&mut world, grandparent, Some(child)),
+        Err(HierarchyError::Cycle {
+            child: grandparent,
+            parent: child
+        })
+    );
+    assert_eq!(
+        set_parent(&mut world, child, Some(child)),
+        Err(HierarchyError::Cycle {
+            child,
+            parent: child
+        })
+    );
+
+    // a rejected parent leaves the hierarchy untouched
+    assert!(world.get::<Parent>(grandparent).is_none());
+    assert_eq!(world.get::<Parent>(child).map(Parent::get), Some(parent));
+    assert_eq!(&world.get::<Children>(parent).unwrap()[..], &[child]);
+    assert!(world.get::<Children>(child).is_none());
+}
+
+#[test]
+fn propagate_transforms_through_nested_children() {
+    let mut world = World::new();
+    let parent = world
+        .spawn((
+            Position::new(10.0, 0.0),
+            Rotation::new(FRAC_PI_2),
+            Scale::new(2.0, 2.0),
+        ))
+        .id();
+    let child = world.spawn(Position::new(1.0, 0.0)).id();
+    let grandchild = world.spawn(Position::new(1.0, 0.0)).id();
+
+    set_parent(&mut world, child, Some(parent)).unwrap();
+    set_parent(&mut world, grandchild, Some(child)).unwrap();
+
+    world.run_system_once(propagate_transforms);
+
+    let parent_global = *world.get::<GlobalTransform>(parent).unwrap();
+    assert_near(parent_global.position, Vector2::new(10.0, 0.0));
+
+    // the parent's rotation turns the child's offset downwards, and its scale doubles it
+    let child_global = *world.get::<GlobalTransform>(child).unwrap();
+    assert_near(child_global.position, Vector2::new(10.0, 2.0));
+    assert!((child_global.rotation - FRAC_PI_2).abs() < 1e-4);
+    assert_near(child_global.scale, Vector2::new(2.0, 2.0));
+
+    let grandchild_global = *world.get::<GlobalTransform>(grandchild).unwrap();
+    assert_near(grandchild_global.position, Vector2::new(10.0, 4.0));
+
+    // moving the parent moves the whole hierarchy on the next propagation
+    *world.get_mut::<Position>(parent).unwrap() = Position::new(0.0, 0.0);
+    world.run_system_once(propagate_transforms);
+
+    let grandchild_global = *world.get::<GlobalTransform>(grandchild).unwrap();
+    assert_near(grandchild_global.position, Vector2::new(0.0, 4.0));
+
+    // a child whose parent was despawned propagates as if it had no parent
+    world.despawn(parent);
+    world.run_system_once(propagate_transforms);
+
+    let grandchild_global = *world.get::<GlobalTransform>(grandchild).unwrap();
+    assert_near(grandchild_global.position, Vector2::new(2.0, 0.0));
+}
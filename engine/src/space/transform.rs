@@ -4,6 +4,7 @@ use log::trace;
 use serde::{Deserialize, Serialize};
 
 use super::{Position, Rotation, Scale, Vector2, Velocity};
+use crate::Time;
 
 #[derive(Debug, Component, Default, Clone, Copy, Reflect, Serialize, Deserialize)]
 #[reflect(Component)]
@@ -40,14 +41,20 @@ impl Transform {
     }
 }
 
-pub fn update(mut query: Query<(&mut Position, &Velocity, Option<&TransformSettings>)>) {
+/// Moves every [`Position`] by its [`Velocity`], scaled by [`Time::time_scale`] so that movement follows the time scale.
+pub fn update(
+    mut query: Query<(&mut Position, &Velocity, Option<&TransformSettings>)>,
+    time: Res<Time>,
+) {
+    let time_scale = time.time_scale();
+
     for (mut position, velocity, transform_settings) in query.iter_mut() {
         if let Some(transform_settings) = transform_settings {
             if transform_settings.auto_update {
-                position.translate(velocity);
+                position.translate(&(**velocity * time_scale));
             }
         } else {
-            position.translate(velocity);
+            position.translate(&(**velocity * time_scale));
         }
     }
 }
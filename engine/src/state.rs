@@ -0,0 +1,210 @@
+//! Contains [`GameState`], a resource for tracking which state the game is in, such as a menu, gameplay or a pause screen.
+//!
+//! Add a state type with [`register_state`], usually from an [`EnginePlugin`](crate::EnginePlugin), then queue transitions with [`GameState::set`].
+//! Queued transitions are applied by [`GameRoot`](crate::GameRoot) at the start of each tick, after input has been processed.
+//! Each transition runs the [`OnExit`] schedule of the state being left, then the [`OnEnter`] schedule of the state being entered,
+//! and sends a [`StateChanged`] event. Neither schedule has to exist.
+//!
+//! Use [`in_state`] as a run condition to only run a system in certain states,
+//! see [`ScheduleBuilder::register_system_if`](crate::schedule::ScheduleBuilder::register_system_if).
+//!
+//! A state can freeze the game while it's active through [`StateType::freeze`], which is how a pause screen stops gameplay in [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick).
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use bevy_ecs::event::Event;
+use bevy_ecs::schedule::ScheduleLabel;
+use bevy_ecs::system::{Res, Resource};
+use bevy_ecs::world::World;
+use log::{trace, warn};
+
+use crate::events;
+use crate::freeze::FreezeType;
+use crate::GgezInterface;
+
+/// How many transitions can be applied in a single tick before the rest are left for the next one.
+///
+/// Stops two [`OnEnter`] schedules that queue each other's state from locking up the game.
+pub const MAX_TRANSITIONS_PER_TICK: usize = 16;
+
+/// A type that can be used as a [`GameState`], usually a fieldless enum.
+pub trait StateType: Debug + Clone + PartialEq + Eq + Hash + Send + Sync + 'static {
+    /// The freeze state pushed while this state is active, which is removed again when the state is left.
+    ///
+    /// Return [`FreezeType::PAUSED`] for pause screens and [`FreezeType::LOADING`] for loading screens to stop gameplay systems while in them.
+    fn freeze(&self) -> Option<FreezeType> {
+        None
+    }
+}
+
+/// The schedule run when a [`GameState`] enters the given state.
+#[derive(Debug, ScheduleLabel, Hash, Eq, PartialEq, Clone)]
+pub struct OnEnter<S: StateType>(pub S);
+
+/// The schedule run when a [`GameState`] leaves the given state.
+#[derive(Debug, ScheduleLabel, Hash, Eq, PartialEq, Clone)]
+pub struct OnExit<S: StateType>(pub S);
+
+/// Sent after a [`GameState`] has finished transitioning, once both [`OnExit`] and [`OnEnter`] have been run.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub struct StateChanged<S: StateType> {
+    pub previous: S,
+    pub current: S,
+}
+
+/// The current state of type `S`, along with any transitions waiting to be applied.
+///
+/// Inserted by [`register_state`].
+#[derive(Debug, Resource)]
+pub struct GameState<S: StateType> {
+    current: S,
+    queue: VecDeque<S>,
+    /// False until the initial state's [`OnEnter`] has been run
+    entered: bool,
+}
+
+impl<S: StateType> GameState<S> {
+    /// Returns the state that is currently active.
+    pub fn get(&self) -> &S {
+        &self.current
+    }
+
+    /// Returns true if `state` is currently active.
+    pub fn is(&self, state: &S) -> bool {
+        self.current == *state
+    }
+
+    /// Queues a transition into `state`, which is applied at the start of the next tick.
+    ///
+    /// Transitions are applied in the order they were queued. Queueing the state that will already be active at that point does nothing.
+    pub fn set(&mut self, state: S) {
+        self.queue.push_back(state);
+    }
+
+    /// Returns the transitions that haven't been applied yet, oldest first.
+    pub fn queued(&self) -> impl Iterator<Item = &S> {
+        self.queue.iter()
+    }
+
+    /// Drops every queued transition.
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+    }
+}
+
+/// Keeps track of every registered state type, so that their transitions can all be applied together.
+#[derive(Resource, Default)]
+pub struct StateRegistry {
+    appliers: Vec<fn(&mut World)>,
+}
+
+/// Adds a [`GameState<S>`] resource starting in `initial`, whose transitions are applied by the engine at the start of every tick.
+///
+/// The [`OnEnter`] schedule of `initial` is run on the first tick. Registering the same state type twice only changes the initial state.
+pub fn register_state<S: StateType>(world: &mut World, initial: S) {
+    let state = GameState {
+        current: initial,
+        queue: VecDeque::new(),
+        entered: false,
+    };
+
+    if world.contains_resource::<GameState<S>>() {
+        world.insert_resource(state);
+        return;
+    }
+
+    world.insert_resource(state);
+    events::register_event::<StateChanged<S>>(world);
+
+    world
+        .get_resource_or_insert_with(StateRegistry::default)
+        .appliers
+        .push(apply_transitions::<S>);
+}
+
+/// Run condition that is true while the [`GameState`] of `state`'s type is in `state`.
+///
+/// Always false if that state type was never registered.
+pub fn in_state<S: StateType>(state: S) -> impl FnMut(Option<Res<GameState<S>>>) -> bool + Clone {
+    move |current: Option<Res<GameState<S>>>| current.is_some_and(|current| current.is(&state))
+}
+
+/// Applies the queued transitions of every registered state type.
+pub(crate) fn apply_state_transitions(world: &mut World) {
+    let Some(registry) = world.get_resource::<StateRegistry>() else {
+        return;
+    };
+
+    for apply in registry.appliers.clone() {
+        apply(world);
+    }
+}
+
+fn apply_transitions<S: StateType>(world: &mut World) {
+    if !world.resource::<GameState<S>>().entered {
+        let mut state = world.resource_mut::<GameState<S>>();
+        state.entered = true;
+        let initial = state.current.clone();
+
+        update_freeze(world, None, &initial);
+        let _ = world.try_run_schedule(OnEnter(initial));
+    }
+
+    for _ in 0..MAX_TRANSITIONS_PER_TICK {
+        let mut state = world.resource_mut::<GameState<S>>();
+
+        let Some(next) = state.queue.pop_front() else {
+            return;
+        };
+
+        if state.current == next {
+            continue;
+        }
+
+        let previous = std::mem::replace(&mut state.current, next.clone());
+
+        trace!("State transition {:?} -> {:?}", previous, next);
+
+        let _ = world.try_run_schedule(OnExit(previous.clone()));
+        update_freeze(world, Some(&previous), &next);
+        let _ = world.try_run_schedule(OnEnter(next.clone()));
+
+        events::send_event(
+            world,
+            StateChanged {
+                previous,
+                current: next,
+            },
+        );
+    }
+
+    let remaining = world.resource::<GameState<S>>().queue.len();
+    if remaining > 0 {
+        warn!(
+            "Applied {} state transitions this tick, leaving {} queued until next tick",
+            MAX_TRANSITIONS_PER_TICK, remaining
+        );
+    }
+}
+
+/// Removes the freeze state of the state being left, and pushes the freeze state of the one being entered.
+fn update_freeze<S: StateType>(world: &mut World, previous: Option<&S>, next: &S) {
+    let previous_freeze = previous.and_then(StateType::freeze);
+    let next_freeze = next.freeze();
+
+    if previous_freeze == next_freeze {
+        return;
+    }
+
+    let mut engine = world.resource_mut::<GgezInterface>();
+
+    if let Some(freeze) = previous_freeze {
+        engine.remove_freeze(&freeze);
+    }
+
+    if let Some(freeze) = next_freeze {
+        engine.push_freeze(freeze);
+    }
+}
@@ -0,0 +1,1295 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::event::{Event, Events};
+use bevy_ecs::reflect::{ReflectComponent, ReflectResource};
+use bevy_ecs::schedule::{Schedule, Schedules};
+use bevy_ecs::system::{ResMut, Resource};
+use bevy_ecs::world::{Mut, World};
+use bevy_reflect::{Reflect, TypePath};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::crash::{self, CrashRecoveryConfig, PanicReport};
+use crate::events::{ActionPressed, FreezeChanged, SceneLoaded};
+use crate::freeze::{FreezeBehaviour, FreezeType};
+use crate::input::KeycodeType;
+use crate::logging::LogFilter;
+use crate::plugin::{plugin_name, validate_plugins};
+use crate::save::{self, ReflectSaveGame, SaveError, SAVE_FORMAT_VERSION};
+use crate::scene::{Scene, SceneData, SceneManager};
+use crate::schedule::{EngineSet, ScheduleBuilder, ScheduleTag};
+use crate::space::{GlobalTransform, Position, Transform, TransformSettings, Vector2, Velocity};
+use crate::state::{
+    in_state, register_state, GameState, OnEnter, OnExit, StateChanged, StateType,
+    MAX_TRANSITIONS_PER_TICK,
+};
+use crate::time::PlayTime;
+use crate::timer::Timer;
+use crate::{
+    ActionData, EngineConfig, EngineConfigError, EnginePlugin, GameRoot, GgezInterface, Input, Time,
+};
+
+/// Creates an empty folder for a test to write its files into, replacing one left over from an earlier run.
+pub(crate) fn test_folder(name: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("engine_test_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&folder);
+    std::fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+/// Writes a scene without any entities, for roots that need a scene to start with.
+pub(crate) fn write_empty_scene(folder: &Path, name: &str) -> PathBuf {
+    let path = folder.join(format!("{}.json", name));
+    std::fs::write(&path, format!("{{\"name\": \"{}\"}}", name)).unwrap();
+    path
+}
+
+/// A headless root that starts in an empty scene, with the given plugins built.
+///
+/// Its [`Input`](crate::Input) has no actions, so tests have to register any that their systems read, see [`GameRoot::new_headless`].
+pub(crate) fn headless_root(name: &str, plugins: Vec<Arc<dyn EnginePlugin>>) -> GameRoot {
+    GameRoot::new_headless(headless_config(name, plugins)).unwrap()
+}
+
+/// The config [`headless_root`] builds its root from, for tests that need to change it first.
+pub(crate) fn headless_config(name: &str, plugins: Vec<Arc<dyn EnginePlugin>>) -> EngineConfig {
+    let folder = test_folder(name);
+    let scene_path = write_empty_scene(&folder, name);
+
+    EngineConfig {
+        external_scene_paths: vec![scene_path.to_string_lossy().into_owned()],
+        scenes_folder: Some(folder.to_string_lossy().into_owned()),
+        scene_format: Default::default(),
+        save_folder: Some(folder.join("saves").to_string_lossy().into_owned()),
+        plugins,
+        ticks_per_second: 50,
+        freeze_on_unfocus: false,
+        freeze_on_minimize: false,
+        run_debug_schedules: false,
+        logger: Default::default(),
+        crash_recovery: CrashRecoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+    }
+}
+
+#[derive(Resource, Default)]
+struct TickCount(u32);
+
+fn count_ticks(mut count: ResMut<TickCount>) {
+    count.0 += 1;
+}
+
+struct TickCountPlugin;
+
+impl EnginePlugin for TickCountPlugin {
+    fn build(&self, world: &mut World) {
+        world.init_resource::<TickCount>();
+
+        let mut builder = world.resource_mut::<ScheduleBuilder>();
+        builder.register_system("count_ticks", count_ticks);
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::GameLogic, "count_ticks");
+    }
+}
+
+/// Ticks of hitstop for [`trigger_hitstop`] to push on the next tick it runs.
+#[derive(Resource, Default)]
+struct PendingHitstop(Option<u32>);
+
+fn trigger_hitstop(mut pending: ResMut<PendingHitstop>, mut engine: ResMut<GgezInterface>) {
+    if let Some(ticks) = pending.0.take() {
+        engine.push_freeze(FreezeType::IMPACT(ticks));
+    }
+}
+
+struct HitstopPlugin;
+
+impl EnginePlugin for HitstopPlugin {
+    fn build(&self, world: &mut World) {
+        world.init_resource::<PendingHitstop>();
+
+        let mut builder = world.resource_mut::<ScheduleBuilder>();
+        builder.register_system("trigger_hitstop", trigger_hitstop);
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::GameLogic, "trigger_hitstop");
+    }
+}
+
+#[test]
+fn headless_root_steps_ticks() {
+    let mut root = headless_root("step_ticks", vec![Arc::new(TickCountPlugin)]);
+
+    assert_eq!(root.world.resource::<TickCount>().0, 0);
+    assert_eq!(root.world.resource::<Time>().ticks(), 0);
+
+    root.step_ticks(10);
+
+    assert_eq!(root.world.resource::<TickCount>().0, 10);
+
+    let time = root.world.resource::<Time>();
+    assert_eq!(time.ticks(), 10);
+    assert_eq!(time.game_elapsed(), time.fixed_delta() * 10);
+    // stepping doesn't wait for real time to pass
+    assert_eq!(time.elapsed(), Duration::ZERO);
+
+    // slowing time down only shrinks how much game time each tick is worth
+    root.world.resource_mut::<Time>().set_time_scale(0.5);
+    root.step_ticks(4);
+
+    assert_eq!(root.world.resource::<TickCount>().0, 14);
+
+    let time = root.world.resource::<Time>();
+    assert_eq!(time.ticks(), 14);
+    assert_eq!(time.game_elapsed(), time.fixed_delta() * 12);
+}
+
+#[test]
+fn default_schedules_propagate_global_transforms() {
+    let mut root = headless_root("propagate_defaults", Vec::new());
+
+    // a renderer only needs a plain Transform, which it draws through the GlobalTransform the engine gives it
+    let entity = root
+        .world
+        .spawn(Transform {
+            position: Position::new(4.0, 2.0),
+            velocity: Velocity::new(1.0, 0.0),
+            settings: TransformSettings { auto_update: true },
+            ..Transform::new()
+        })
+        .id();
+
+    root.step_ticks(1);
+
+    let global = *root.world.get::<GlobalTransform>(entity).unwrap();
+    let position = **root.world.get::<Position>(entity).unwrap();
+
+    // objects move before their global transform is computed, so both agree on the same tick
+    assert_ne!(position, Vector2::new(4.0, 2.0));
+    assert_eq!(global.position, position);
+}
+
+/// Places `count_ticks` into the Tick schedule through a definition loaded from a file.
+struct LoadedTickPlugin(PathBuf);
+
+impl EnginePlugin for LoadedTickPlugin {
+    fn build(&self, world: &mut World) {
+        world.init_resource::<TickCount>();
+
+        let mut builder = world.resource_mut::<ScheduleBuilder>();
+        builder.register_system("count_ticks", count_ticks);
+        builder.load_definitions(&self.0).unwrap();
+    }
+}
+
+#[test]
+fn loaded_tick_definitions_keep_engine_systems() {
+    let path = test_folder("loaded_tick_files").join("schedules.json");
+    std::fs::write(
+        &path,
+        r#"[{ "tag": "Tick", "systems": [{ "name": "count_ticks", "set": "GameLogic" }] }]"#,
+    )
+    .unwrap();
+
+    let mut root = headless_root("loaded_tick", vec![Arc::new(LoadedTickPlugin(path))]);
+
+    let entity = root
+        .world
+        .spawn(Transform {
+            position: Position::new(4.0, 2.0),
+            ..Transform::new()
+        })
+        .id();
+
+    root.step_ticks(1);
+
+    // the loaded system runs alongside the engine's, which still propagate transforms
+    assert_eq!(root.world.resource::<TickCount>().0, 1);
+    assert_eq!(
+        root.world.get::<GlobalTransform>(entity).unwrap().position,
+        Vector2::new(4.0, 2.0)
+    );
+
+    let builder = root.world.resource::<ScheduleBuilder>();
+    let systems = &builder.get_definition(ScheduleTag::Tick).unwrap().systems;
+    assert!(systems
+        .iter()
+        .any(|entry| entry.name == "transform_propagate"));
+    assert!(systems.iter().any(|entry| entry.name == "count_ticks"));
+}
+
+#[test]
+fn zero_length_timers_finish_on_their_first_tick() {
+    let mut timer = Timer::new(0);
+    assert!(timer.tick());
+    assert!(timer.just_finished());
+    assert!(!timer.tick());
+    assert!(timer.is_finished());
+
+    let mut repeating = Timer::repeating(0);
+    assert!(repeating.tick());
+    assert!(repeating.tick());
+}
+
+#[test]
+fn debug_freeze_halts_systems_that_always_run() {
+    let mut root = headless_root("debug_freeze_behaviours", Vec::new());
+    let mut engine = root.world.resource_mut::<GgezInterface>();
+
+    let always = FreezeBehaviour::Always;
+    let paused_by_impact = FreezeBehaviour::PausedBy(vec![FreezeType::IMPACT(0)]);
+    let runs_during_debug = FreezeBehaviour::RunsDuring(vec![FreezeType::DEBUG(Box::default())]);
+
+    engine.push_freeze(FreezeType::PAUSED);
+    assert!(always.should_run(&engine));
+    assert!(paused_by_impact.should_run(&engine));
+    assert!(!runs_during_debug.should_run(&engine));
+
+    // pausing for debugging stops everything that doesn't ask to keep running during it by name
+    engine.enter_debug_freeze();
+    assert!(!always.should_run(&engine));
+    assert!(!paused_by_impact.should_run(&engine));
+    assert!(!FreezeBehaviour::PausedByAny.should_run(&engine));
+    assert!(runs_during_debug.should_run(&engine));
+
+    engine.exit_debug_freeze();
+    assert!(always.should_run(&engine));
+}
+
+#[test]
+fn freezes_pushed_during_a_debug_freeze_go_underneath_it() {
+    let mut config = headless_config("debug_freeze_underneath", Vec::new());
+    config.freeze_on_unfocus = true;
+    let mut root = GameRoot::new_headless(config).unwrap();
+
+    root.world
+        .resource_mut::<GgezInterface>()
+        .enter_debug_freeze();
+    root.world
+        .resource_mut::<GgezInterface>()
+        .drain_freeze_changes();
+
+    // losing focus, entering a paused state and loading a scene all push freezes of their own
+    root.focus_changed(false);
+    root.world
+        .resource_mut::<GgezInterface>()
+        .push_freeze(FreezeType::PAUSED);
+    root.world
+        .resource_mut::<GgezInterface>()
+        .push_freeze(FreezeType::LOADING);
+
+    let mut engine = root.world.resource_mut::<GgezInterface>();
+    assert!(matches!(engine.freeze_mode(), FreezeType::DEBUG(_)));
+    assert!(!FreezeBehaviour::Always.should_run(&engine));
+    assert!(engine.has_freeze(&FreezeType::UNFOCUSED));
+
+    // each change is recorded as if the debug freeze weren't in the way
+    let changes = engine.drain_freeze_changes();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0].previous, FreezeType::NONE);
+    assert_eq!(changes[0].current, FreezeType::UNFOCUSED);
+    assert_eq!(changes[2].previous, FreezeType::PAUSED);
+    assert_eq!(changes[2].current, FreezeType::LOADING);
+
+    // removing them again doesn't disturb the debug freeze either
+    engine.remove_freeze(&FreezeType::LOADING);
+    assert!(matches!(engine.freeze_mode(), FreezeType::DEBUG(_)));
+
+    assert!(engine.exit_debug_freeze());
+    assert_eq!(engine.freeze_mode(), &FreezeType::PAUSED);
+}
+
+#[test]
+fn freeze_stack_returns_to_earlier_states() {
+    let mut root = headless_root("freeze_stack", Vec::new());
+    let mut engine = root.world.resource_mut::<GgezInterface>();
+
+    assert_eq!(engine.pop_freeze(), FreezeType::NONE);
+    assert_eq!(engine.freeze_mode(), &FreezeType::NONE);
+
+    engine.push_freeze(FreezeType::PAUSED);
+    engine.push_freeze(FreezeType::LOADING);
+    engine.push_freeze(FreezeType::UNFOCUSED);
+    assert_eq!(engine.freeze_mode(), &FreezeType::UNFOCUSED);
+    assert!(engine.has_freeze(&FreezeType::PAUSED));
+
+    // removing a state further down leaves the one in effect alone
+    assert_eq!(
+        engine.remove_freeze(&FreezeType::LOADING),
+        Some(FreezeType::LOADING)
+    );
+    assert_eq!(engine.remove_freeze(&FreezeType::LOADING), None);
+    assert_eq!(engine.freeze_mode(), &FreezeType::UNFOCUSED);
+
+    assert_eq!(
+        engine.remove_freeze(&FreezeType::UNFOCUSED),
+        Some(FreezeType::UNFOCUSED)
+    );
+    assert_eq!(engine.freeze_mode(), &FreezeType::PAUSED);
+
+    assert_eq!(engine.pop_freeze(), FreezeType::PAUSED);
+    assert_eq!(engine.freeze_mode(), &FreezeType::NONE);
+    assert!(!engine.has_freeze(&FreezeType::PAUSED));
+
+    // every change in effect was recorded, but not removals from further down
+    let changes = engine.drain_freeze_changes();
+    assert_eq!(changes.len(), 6);
+    assert_eq!(changes[0].previous, FreezeType::NONE);
+    assert_eq!(changes[0].current, FreezeType::NONE);
+    assert_eq!(changes[5].previous, FreezeType::PAUSED);
+    assert_eq!(changes[5].current, FreezeType::NONE);
+}
+
+#[test]
+fn impact_freezes_exactly_as_many_ticks_as_pushed() {
+    let mut root = headless_root(
+        "impact_countdown",
+        vec![Arc::new(TickCountPlugin), Arc::new(HitstopPlugin)],
+    );
+
+    root.world.resource_mut::<PendingHitstop>().0 = Some(3);
+    root.step_ticks(1);
+    assert_eq!(root.world.resource::<TickCount>().0, 1);
+    assert_eq!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        &FreezeType::IMPACT(3)
+    );
+
+    root.step_ticks(3);
+    assert_eq!(root.world.resource::<TickCount>().0, 1);
+    assert_eq!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        &FreezeType::IMPACT(0)
+    );
+
+    root.step_ticks(1);
+    assert_eq!(root.world.resource::<TickCount>().0, 2);
+    assert_eq!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        &FreezeType::NONE
+    );
+
+    // a single tick of hitstop still freezes a tick
+    root.world.resource_mut::<PendingHitstop>().0 = Some(1);
+    root.step_ticks(3);
+    assert_eq!(root.world.resource::<TickCount>().0, 4);
+}
+
+#[test]
+fn window_events_freeze_when_configured() {
+    let mut root = headless_root("window_freezes", Vec::new());
+
+    // the test config doesn't freeze on either
+    root.focus_changed(false);
+    root.window_resized(0.0, 0.0);
+    assert_eq!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        &FreezeType::NONE
+    );
+
+    let mut config = headless_config("window_freezes_configured", Vec::new());
+    config.freeze_on_unfocus = true;
+    config.freeze_on_minimize = true;
+    let mut root = GameRoot::new_headless(config).unwrap();
+
+    root.focus_changed(false);
+    root.focus_changed(false);
+    root.window_resized(0.0, 0.0);
+    root.window_resized(0.0, 0.0);
+
+    let engine = root.world.resource::<GgezInterface>();
+    assert_eq!(engine.freeze_mode(), &FreezeType::MINIMIZED);
+    assert!(engine.has_freeze(&FreezeType::UNFOCUSED));
+    assert!(engine.is_freeze_frame());
+
+    // restoring the window lifts the minimized freeze, while focus only returns with focus
+    root.window_resized(800.0, 600.0);
+    assert_eq!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        &FreezeType::UNFOCUSED
+    );
+
+    root.focus_changed(true);
+    assert_eq!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        &FreezeType::NONE
+    );
+
+    // minimizing overrides freezing on unfocus when minimizing doesn't freeze
+    let mut config = headless_config("window_freezes_unfocused", Vec::new());
+    config.freeze_on_unfocus = true;
+    let mut root = GameRoot::new_headless(config).unwrap();
+
+    root.focus_changed(false);
+    assert_eq!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        &FreezeType::UNFOCUSED
+    );
+    root.window_resized(0.0, 0.0);
+    assert_eq!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        &FreezeType::NONE
+    );
+}
+
+#[cfg(feature = "editor_features")]
+#[test]
+fn frame_advance_steps_without_leaving_the_debug_freeze() {
+    use crate::events::FreezeChanged;
+    use crate::frame_advance::FrameAdvance;
+
+    let mut root = headless_root(
+        "frame_advance",
+        vec![Arc::new(TickCountPlugin), Arc::new(HitstopPlugin)],
+    );
+    let mut freeze_reader = root.world.resource::<Events<FreezeChanged>>().get_reader();
+
+    // asking to pause twice before the next tick still pauses
+    let mut frame_advance = root.world.resource_mut::<FrameAdvance>();
+    frame_advance.request_toggle_pause();
+    frame_advance.request_toggle_pause();
+    root.step_ticks(1);
+
+    assert!(FrameAdvance::is_paused(
+        root.world.resource::<GgezInterface>()
+    ));
+    assert_eq!(root.world.resource::<TickCount>().0, 0);
+    assert_eq!(
+        freeze_reader
+            .read(root.world.resource::<Events<FreezeChanged>>())
+            .count(),
+        1
+    );
+
+    root.world.resource_mut::<FrameAdvance>().request_steps(3);
+    for _ in 0..4 {
+        root.step_ticks(1);
+        assert!(FrameAdvance::is_paused(
+            root.world.resource::<GgezInterface>()
+        ));
+        assert_eq!(
+            freeze_reader
+                .read(root.world.resource::<Events<FreezeChanged>>())
+                .count(),
+            0
+        );
+    }
+
+    // only the requested steps run, and time only passes for them
+    assert_eq!(root.world.resource::<TickCount>().0, 3);
+    assert_eq!(root.world.resource::<Time>().ticks(), 3);
+    assert!(matches!(
+        root.world.resource::<GgezInterface>().freeze_mode(),
+        FreezeType::DEBUG(_)
+    ));
+
+    // hitstop started by a stepped tick waits underneath the debug pause instead of unpausing the game
+    root.world.resource_mut::<PendingHitstop>().0 = Some(2);
+    root.world.resource_mut::<FrameAdvance>().request_steps(1);
+    root.step_ticks(1);
+
+    let engine = root.world.resource::<GgezInterface>();
+    assert!(FrameAdvance::is_paused(engine));
+    assert!(engine.has_freeze(&FreezeType::IMPACT(0)));
+    assert_eq!(root.world.resource::<Time>().ticks(), 4);
+    assert_eq!(
+        freeze_reader
+            .read(root.world.resource::<Events<FreezeChanged>>())
+            .count(),
+        1
+    );
+
+    root.step_ticks(1);
+    assert_eq!(root.world.resource::<Time>().ticks(), 4);
+
+    // the hitstop only counts down through steps, and freezes exactly as many of them as it was pushed with
+    let counted = root.world.resource::<TickCount>().0;
+    for ticks in 5..7 {
+        root.world.resource_mut::<FrameAdvance>().request_steps(1);
+        root.step_ticks(1);
+
+        let engine = root.world.resource::<GgezInterface>();
+        assert!(FrameAdvance::is_paused(engine));
+        assert!(engine.has_freeze(&FreezeType::IMPACT(0)));
+        assert_eq!(root.world.resource::<Time>().ticks(), ticks);
+        assert_eq!(root.world.resource::<TickCount>().0, counted);
+    }
+
+    // running out sends the same event it would unpaused, and the step runs like any other
+    root.world.resource_mut::<FrameAdvance>().request_steps(1);
+    root.step_ticks(1);
+
+    let engine = root.world.resource::<GgezInterface>();
+    assert!(FrameAdvance::is_paused(engine));
+    assert!(!engine.has_freeze(&FreezeType::IMPACT(0)));
+    assert_eq!(root.world.resource::<Time>().ticks(), 7);
+    assert_eq!(root.world.resource::<TickCount>().0, counted + 1);
+    let changes: Vec<_> = freeze_reader
+        .read(root.world.resource::<Events<FreezeChanged>>())
+        .cloned()
+        .collect();
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(changes[0].previous, FreezeType::IMPACT(_)));
+    assert_eq!(changes[0].current, FreezeType::NONE);
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Default, Serialize, Deserialize, Reflect)]
+#[reflect(Component, SaveGame)]
+struct Coins(u32);
+
+#[derive(Debug, Clone, PartialEq, Resource, Default, Serialize, Deserialize, Reflect)]
+#[reflect(Resource)]
+struct Checkpoint(String);
+
+struct SavePlugin;
+
+impl EnginePlugin for SavePlugin {
+    fn build(&self, world: &mut World) {
+        world.resource_scope(|world, mut manager: Mut<SceneManager>| {
+            crate::register_component::<Coins>(world, &mut manager.type_registry);
+            crate::register_saved_resource::<Checkpoint>(&mut manager.type_registry);
+        });
+        world.insert_resource(Checkpoint("start".to_owned()));
+    }
+}
+
+/// Returns every entity in the scene with the given name, along with their [`Coins`] if they have any.
+fn coins_named(root: &GameRoot, scene: &str, name: &str) -> Vec<Option<Coins>> {
+    let manager = root.world.resource::<SceneManager>();
+    let scene_entity = manager.current_scenes[scene];
+
+    root.world
+        .get::<Scene>(scene_entity)
+        .unwrap()
+        .entities
+        .iter()
+        .filter(|entity| {
+            root.world
+                .get::<SceneData>(**entity)
+                .is_some_and(|scene_data| scene_data.entity_name == name)
+        })
+        .map(|entity| root.world.get::<Coins>(*entity).cloned())
+        .collect()
+}
+
+#[test]
+fn save_slots_overlay_the_authored_scene() {
+    let mut root = headless_root("save_slots", vec![Arc::new(SavePlugin)]);
+    let path = test_folder("save_slots_files").join("level.json");
+    let coins = Coins::type_path();
+
+    std::fs::write(
+        &path,
+        json!({
+            "name": "level",
+            "entity_data": {
+                "Player": { coins: 0 },
+                "Chest": { coins: 10 },
+                "Rock": {}
+            },
+            "entity_ids": { "Player": 1, "Chest": 2, "Rock": 3 }
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    root.world
+        .resource_scope(|world, mut manager: Mut<SceneManager>| {
+            let scene_entity = manager.load_scene(world, path.clone())?;
+            let scene = world.get::<Scene>(scene_entity).unwrap();
+            let player = scene.get_entity(world, "Player".to_owned()).unwrap();
+            let chest = scene.get_entity(world, "Chest".to_owned()).unwrap();
+            let rock = scene.get_entity(world, "Rock".to_owned()).unwrap();
+
+            // renaming both to the same name can't mix them up, since they're saved by ID
+            world.get_mut::<Coins>(player).unwrap().0 = 5;
+            world.get_mut::<SceneData>(player).unwrap().entity_name = "Hero".to_owned();
+            world.get_mut::<SceneData>(chest).unwrap().entity_name = "Hero".to_owned();
+            world
+                .get_mut::<Scene>(scene_entity)
+                .unwrap()
+                .entities
+                .retain(|entity| *entity != rock);
+            world.despawn(rock);
+
+            manager.new_entity(world, "Coin".to_owned())?;
+            let coin = world
+                .get::<Scene>(scene_entity)
+                .unwrap()
+                .get_entity(world, "Coin".to_owned())
+                .unwrap();
+            world.entity_mut(coin).insert(Coins(1));
+
+            Ok::<_, crate::scene::SceneError>(())
+        })
+        .unwrap();
+
+    root.world.resource_mut::<Checkpoint>().0 = "cave".to_owned();
+    root.world.resource_mut::<PlayTime>().0 = Duration::from_secs(90);
+
+    let metadata = save::save_slot(&mut root.world, "first").unwrap();
+    assert_eq!(metadata.play_time, Duration::from_secs(90));
+
+    let slot_path = save::slot_path(&root.world, "first").unwrap();
+    assert!(slot_path.exists());
+    assert!(!slot_path.with_extension("json.tmp").exists());
+
+    // everything done after saving is undone by loading
+    for mut coins in root.world.query::<&mut Coins>().iter_mut(&mut root.world) {
+        coins.0 = 99;
+    }
+    root.world.resource_mut::<Checkpoint>().0 = "nowhere".to_owned();
+    root.world.resource_mut::<PlayTime>().0 = Duration::from_secs(1);
+
+    save::load_slot(&mut root.world, "first").unwrap();
+
+    assert_eq!(coins_named(&root, "level", "Player"), vec![Some(Coins(5))]);
+    assert_eq!(coins_named(&root, "level", "Chest"), vec![Some(Coins(10))]);
+    assert_eq!(coins_named(&root, "level", "Rock"), Vec::new());
+    assert_eq!(coins_named(&root, "level", "Coin"), vec![Some(Coins(1))]);
+    assert_eq!(root.world.resource::<Checkpoint>().0, "cave");
+    assert_eq!(root.world.resource::<PlayTime>().0, Duration::from_secs(90));
+
+    // saves from a newer version of the game are refused
+    let mut newer = save::read_slot(&root.world, "first").unwrap();
+    newer.metadata.version = SAVE_FORMAT_VERSION + 1;
+    std::fs::write(&slot_path, serde_json::to_string(&newer).unwrap()).unwrap();
+
+    assert!(matches!(
+        save::load_slot(&mut root.world, "first"),
+        Err(SaveError::UnsupportedVersion { found, supported })
+            if found == SAVE_FORMAT_VERSION + 1 && supported == SAVE_FORMAT_VERSION
+    ));
+
+    for slot in ["", "../first", ".hidden"] {
+        assert!(matches!(
+            save::save_slot(&mut root.world, slot),
+            Err(SaveError::InvalidSlotName(_))
+        ));
+    }
+}
+
+#[test]
+fn crash_timestamps_are_utc_dates() {
+    assert_eq!(crash::format_timestamp(0), "1970-01-01_00-00-00");
+    assert_eq!(crash::format_timestamp(951_782_400), "2000-02-29_00-00-00");
+    assert_eq!(
+        crash::format_timestamp(1_700_000_000),
+        "2023-11-14_22-13-20"
+    );
+    assert_eq!(
+        crash::format_timestamp(4_102_444_799),
+        "2099-12-31_23-59-59"
+    );
+}
+
+#[test]
+fn crash_recoveries_restore_live_scenes() {
+    let mut root = headless_root("crash_recovery", vec![Arc::new(SavePlugin)]);
+    let folder = test_folder("crash_recovery_files");
+    let config = CrashRecoveryConfig {
+        enabled: true,
+        folder: folder.join("recovery").to_string_lossy().into_owned(),
+        recent_log_lines: 10,
+    };
+
+    // both names end up as the same file name once sanitized
+    let mut paths = Vec::new();
+    for (file, name) in [("first.json", "Cave/1"), ("second.json", "Cave_1")] {
+        let path = folder.join(file);
+        std::fs::write(
+            &path,
+            json!({
+                "name": name,
+                "entity_data": { "Player": { Coins::type_path(): 0 } }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        paths.push(path);
+    }
+
+    let set_coins = |root: &mut GameRoot, scene: &str, coins: u32| {
+        let manager = root.world.resource::<SceneManager>();
+        let scene_entity = manager.current_scenes[scene];
+        let player = root
+            .world
+            .get::<Scene>(scene_entity)
+            .unwrap()
+            .get_entity(&root.world, "Player".to_owned())
+            .unwrap();
+        root.world.get_mut::<Coins>(player).unwrap().0 = coins;
+    };
+
+    root.world
+        .resource_scope(|world, mut manager: Mut<SceneManager>| {
+            for path in &paths {
+                manager.load_scene(world, path.clone()).unwrap();
+            }
+        });
+    set_coins(&mut root, "Cave/1", 7);
+
+    let report = PanicReport {
+        message: "test crash".to_owned(),
+        location: None,
+        thread: None,
+        backtrace: String::new(),
+    };
+    let recovery = crash::write_recovery(&mut root.world, &config, &report).unwrap();
+
+    let manifest = crash::read_manifest(&recovery).unwrap();
+    let mut files: Vec<_> = manifest
+        .scenes
+        .iter()
+        .map(|scene| scene.file.clone())
+        .collect();
+    // along with the scene the root started in
+    assert_eq!(files.len(), 3);
+    files.sort();
+    files.dedup();
+    assert_eq!(files.len(), 3);
+    assert!(recovery.join(crash::CRASH_REPORT_FILE_NAME).is_file());
+
+    // a crash in the same second still gets its own folder
+    let second = crash::write_recovery(&mut root.world, &config, &report).unwrap();
+    assert_eq!(
+        crash::pending_recoveries(&config),
+        vec![recovery.clone(), second.clone()]
+    );
+
+    crash::mark_handled(&second, "dismissed");
+    assert_eq!(crash::pending_recoveries(&config), vec![recovery.clone()]);
+
+    set_coins(&mut root, "Cave/1", 0);
+
+    let restored = crash::restore_recovery(&mut root.world, &recovery).unwrap();
+    assert!(restored.contains(&"Cave/1".to_owned()));
+    assert!(restored.contains(&"Cave_1".to_owned()));
+    assert!(crash::pending_recoveries(&config).is_empty());
+
+    let manager = root.world.resource::<SceneManager>();
+    let scene = root
+        .world
+        .get::<Scene>(manager.current_scenes["Cave/1"])
+        .unwrap();
+    let player = scene.get_entity(&root.world, "Player".to_owned()).unwrap();
+
+    // restored scenes still save back to where they were loaded from
+    assert_eq!(root.world.get::<Coins>(player), Some(&Coins(7)));
+    assert_eq!(scene.save_data_path(), Some(&paths[0]));
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Screen {
+    Title,
+    Playing,
+    Paused,
+    Loading,
+}
+
+impl StateType for Screen {
+    fn freeze(&self) -> Option<FreezeType> {
+        match self {
+            Screen::Paused => Some(FreezeType::PAUSED),
+            Screen::Loading => Some(FreezeType::LOADING),
+            _ => None,
+        }
+    }
+}
+
+/// Every [`OnEnter`] and [`OnExit`] schedule run for [`Screen`], in order.
+#[derive(Resource, Default)]
+struct TransitionLog(Vec<String>);
+
+struct ScreenPlugin;
+
+impl EnginePlugin for ScreenPlugin {
+    fn build(&self, world: &mut World) {
+        world.init_resource::<TransitionLog>();
+        world.init_resource::<TickCount>();
+        register_state(world, Screen::Title);
+
+        for screen in [
+            Screen::Title,
+            Screen::Playing,
+            Screen::Paused,
+            Screen::Loading,
+        ] {
+            let entered = format!("enter {:?}", screen);
+            let mut on_enter = Schedule::new(OnEnter(screen.clone()));
+            on_enter.add_systems(move |mut log: ResMut<TransitionLog>| log.0.push(entered.clone()));
+            world.add_schedule(on_enter);
+
+            let exited = format!("exit {:?}", screen);
+            let mut on_exit = Schedule::new(OnExit(screen));
+            on_exit.add_systems(move |mut log: ResMut<TransitionLog>| log.0.push(exited.clone()));
+            world.add_schedule(on_exit);
+        }
+
+        let mut builder = world.resource_mut::<ScheduleBuilder>();
+        builder.register_system_if(
+            "count_playing_ticks",
+            count_ticks,
+            in_state(Screen::Playing),
+        );
+        builder.add_to_set(
+            ScheduleTag::Tick,
+            EngineSet::GameLogic,
+            "count_playing_ticks",
+        );
+    }
+}
+
+#[test]
+fn queued_state_transitions_run_in_order() {
+    let mut root = headless_root("state_order", vec![Arc::new(ScreenPlugin)]);
+    let mut reader = root
+        .world
+        .resource::<Events<StateChanged<Screen>>>()
+        .get_reader();
+
+    root.step_ticks(1);
+    assert_eq!(
+        root.world.resource::<TransitionLog>().0,
+        vec!["enter Title"]
+    );
+
+    let mut state = root.world.resource_mut::<GameState<Screen>>();
+    state.set(Screen::Playing);
+    // already active by the time it's applied, so it's skipped
+    state.set(Screen::Playing);
+    state.set(Screen::Title);
+    root.step_ticks(1);
+
+    assert_eq!(
+        root.world.resource::<TransitionLog>().0,
+        vec![
+            "enter Title",
+            "exit Title",
+            "enter Playing",
+            "exit Playing",
+            "enter Title"
+        ]
+    );
+    assert!(root
+        .world
+        .resource::<GameState<Screen>>()
+        .is(&Screen::Title));
+
+    let changes: Vec<_> = reader
+        .read(root.world.resource::<Events<StateChanged<Screen>>>())
+        .cloned()
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            StateChanged {
+                previous: Screen::Title,
+                current: Screen::Playing
+            },
+            StateChanged {
+                previous: Screen::Playing,
+                current: Screen::Title
+            }
+        ]
+    );
+}
+
+#[test]
+fn in_state_gates_tick_systems() {
+    let mut root = headless_root("state_gates", vec![Arc::new(ScreenPlugin)]);
+
+    root.step_ticks(2);
+    assert_eq!(root.world.resource::<TickCount>().0, 0);
+
+    // transitions apply before the tick's schedules, so the tick they're applied on already runs in the new state
+    root.world
+        .resource_mut::<GameState<Screen>>()
+        .set(Screen::Playing);
+    root.step_ticks(3);
+    assert_eq!(root.world.resource::<TickCount>().0, 3);
+
+    root.world
+        .resource_mut::<GameState<Screen>>()
+        .set(Screen::Title);
+    root.step_ticks(2);
+    assert_eq!(root.world.resource::<TickCount>().0, 3);
+}
+
+#[test]
+fn state_transitions_are_capped_per_tick() {
+    let mut root = headless_root("state_cap", vec![Arc::new(ScreenPlugin)]);
+    root.step_ticks(1);
+
+    let mut state = root.world.resource_mut::<GameState<Screen>>();
+    for i in 0..MAX_TRANSITIONS_PER_TICK + 3 {
+        state.set(match i % 2 {
+            0 => Screen::Playing,
+            _ => Screen::Title,
+        });
+    }
+
+    root.step_ticks(1);
+    let state = root.world.resource::<GameState<Screen>>();
+    assert_eq!(state.queued().count(), 3);
+    assert!(state.is(&Screen::Title));
+    assert_eq!(
+        root.world.resource::<TransitionLog>().0.len(),
+        1 + MAX_TRANSITIONS_PER_TICK * 2
+    );
+
+    // the rest are left for the next tick
+    root.step_ticks(1);
+    let state = root.world.resource::<GameState<Screen>>();
+    assert_eq!(state.queued().count(), 0);
+    assert!(state.is(&Screen::Playing));
+}
+
+#[test]
+fn freezing_states_push_and_remove_their_freeze() {
+    let mut root = headless_root("state_freezes", vec![Arc::new(ScreenPlugin)]);
+    let freeze_mode =
+        |root: &GameRoot| root.world.resource::<GgezInterface>().freeze_mode().clone();
+
+    root.world
+        .resource_mut::<GameState<Screen>>()
+        .set(Screen::Playing);
+    root.step_ticks(1);
+    assert_eq!(freeze_mode(&root), FreezeType::NONE);
+
+    // the pausing tick is already frozen
+    root.world
+        .resource_mut::<GameState<Screen>>()
+        .set(Screen::Paused);
+    root.step_ticks(1);
+    assert_eq!(freeze_mode(&root), FreezeType::PAUSED);
+    assert_eq!(root.world.resource::<TickCount>().0, 1);
+
+    // going from one freezing state to another swaps their freezes
+    root.world
+        .resource_mut::<GameState<Screen>>()
+        .set(Screen::Loading);
+    root.step_ticks(1);
+    assert_eq!(freeze_mode(&root), FreezeType::LOADING);
+    assert!(!root
+        .world
+        .resource::<GgezInterface>()
+        .has_freeze(&FreezeType::PAUSED));
+
+    root.world
+        .resource_mut::<GameState<Screen>>()
+        .set(Screen::Playing);
+    root.step_ticks(1);
+    assert_eq!(freeze_mode(&root), FreezeType::NONE);
+    assert_eq!(root.world.resource::<TickCount>().0, 2);
+}
+
+struct BasePlugin;
+
+impl EnginePlugin for BasePlugin {
+    fn build(&self, _world: &mut World) {}
+}
+
+struct DependentPlugin;
+
+impl EnginePlugin for DependentPlugin {
+    fn dependencies(&self) -> Vec<&'static str> {
+        vec![plugin_name::<BasePlugin>()]
+    }
+
+    fn build(&self, _world: &mut World) {}
+}
+
+#[test]
+fn plugins_are_validated_before_building() {
+    let base: Arc<dyn EnginePlugin> = Arc::new(BasePlugin);
+    let dependent: Arc<dyn EnginePlugin> = Arc::new(DependentPlugin);
+
+    assert!(validate_plugins(&[base.clone(), dependent.clone()]).is_ok());
+
+    assert!(matches!(
+        validate_plugins(&[dependent.clone(), base.clone()]),
+        Err(EngineConfigError::PluginOutOfOrder { plugin, dependency })
+            if plugin == dependent.name() && dependency == base.name()
+    ));
+
+    assert!(matches!(
+        validate_plugins(std::slice::from_ref(&dependent)),
+        Err(EngineConfigError::MissingPluginDependency { plugin, dependency })
+            if plugin == dependent.name() && dependency == base.name()
+    ));
+
+    assert!(matches!(
+        validate_plugins(&[base.clone(), dependent.clone(), base.clone()]),
+        Err(EngineConfigError::DuplicatePlugin(plugin)) if plugin == base.name()
+    ));
+
+    // the config checks its plugins along with everything else
+    let config = headless_config("plugin_order", vec![dependent, base]);
+    assert!(matches!(
+        config.validate(),
+        Err(EngineConfigError::PluginOutOfOrder { .. })
+    ));
+    assert!(GameRoot::new_headless(config).is_err());
+}
+
+#[test]
+fn configs_load_from_every_format() {
+    let folder = test_folder("config_formats");
+    let files = [
+        (
+            "engine.ron",
+            r#"(
+                external_scene_paths: ["scenes/start.json"],
+                ticks_per_second: 30,
+                freeze_on_unfocus: true,
+            )"#,
+        ),
+        (
+            "engine.json",
+            r#"{
+                "external_scene_paths": ["scenes/start.json"],
+                "ticks_per_second": 30,
+                "freeze_on_unfocus": true
+            }"#,
+        ),
+        (
+            "engine.toml",
+            r#"
+                external_scene_paths = ["scenes/start.json"]
+                ticks_per_second = 30
+                freeze_on_unfocus = true
+            "#,
+        ),
+    ];
+
+    for (file, contents) in files {
+        let path = folder.join(file);
+        std::fs::write(&path, contents).unwrap();
+
+        let config =
+            EngineConfig::load(&path).unwrap_or_else(|err| panic!("{}: {}", file, err.to_string()));
+        assert_eq!(config.external_scene_paths, vec!["scenes/start.json"]);
+        assert_eq!(config.ticks_per_second, 30);
+        assert!(config.freeze_on_unfocus);
+        // everything left out falls back to its default
+        assert!(!config.freeze_on_minimize);
+        assert!(config.crash_recovery.enabled);
+        assert!(config.plugins.is_empty());
+    }
+
+    let path = folder.join("engine.yaml");
+    std::fs::write(&path, "ticks_per_second: 30").unwrap();
+    assert!(matches!(
+        EngineConfig::load(&path),
+        Err(EngineConfigError::UnsupportedFormat(extension)) if extension == "yaml"
+    ));
+
+    // loaded configs are validated too
+    let path = folder.join("broken.json");
+    std::fs::write(
+        &path,
+        r#"{ "external_scene_paths": ["scenes/start.json"], "ticks_per_second": 0 }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        EngineConfig::load(&path),
+        Err(EngineConfigError::InvalidTicksPerSecond)
+    ));
+}
+
+#[test]
+fn configs_are_validated() {
+    let config = || headless_config("config_validation", Vec::new());
+    assert!(config().validate().is_ok());
+
+    for ticks_per_second in [0, crate::MAX_TICKS_PER_SECOND + 1] {
+        let mut invalid = config();
+        invalid.ticks_per_second = ticks_per_second;
+        assert!(matches!(
+            invalid.validate(),
+            Err(EngineConfigError::InvalidTicksPerSecond)
+        ));
+    }
+
+    let mut fastest = config();
+    fastest.ticks_per_second = crate::MAX_TICKS_PER_SECOND;
+    assert!(fastest.validate().is_ok());
+
+    let mut blank_scene = config();
+    blank_scene.external_scene_paths.push("  ".to_owned());
+    assert!(matches!(
+        blank_scene.validate(),
+        Err(EngineConfigError::InvalidScenePath(path)) if path == "  "
+    ));
+
+    let mut no_scenes = config();
+    no_scenes.external_scene_paths.clear();
+    assert!(matches!(
+        no_scenes.validate(),
+        Err(EngineConfigError::NoScenePaths)
+    ));
+
+    let mut bad_filter = config();
+    bad_filter.logger.filters = "warn,engine=loud".to_owned();
+    assert!(matches!(
+        bad_filter.validate(),
+        Err(EngineConfigError::InvalidLogFilter(_))
+    ));
+
+    // a world without a Frame schedule can't be run
+    let mut world = World::new();
+    world.init_resource::<Schedules>();
+    world.add_schedule(Schedule::new(ScheduleTag::Tick));
+    assert!(matches!(
+        config().validate_world(&world),
+        Err(EngineConfigError::MissingSchedule(ScheduleTag::Frame))
+    ));
+
+    world.add_schedule(Schedule::new(ScheduleTag::Frame));
+    assert!(config().validate_world(&world).is_ok());
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+struct Ping(u32);
+
+struct PingPlugin;
+
+impl EnginePlugin for PingPlugin {
+    fn build(&self, world: &mut World) {
+        crate::events::register_event::<Ping>(world);
+    }
+}
+
+#[test]
+fn registered_events_last_two_ticks() {
+    let mut root = headless_root("event_lifetime", vec![Arc::new(PingPlugin)]);
+
+    crate::events::send_event(&mut root.world, Ping(1));
+    let mut reader = root.world.resource::<Events<Ping>>().get_reader();
+
+    // a system reading every tick sees the event on the tick after it was sent
+    root.step_ticks(1);
+    assert_eq!(
+        reader
+            .read(root.world.resource::<Events<Ping>>())
+            .cloned()
+            .collect::<Vec<_>>(),
+        vec![Ping(1)]
+    );
+
+    let mut late_reader = root.world.resource::<Events<Ping>>().get_reader();
+    root.step_ticks(1);
+    assert_eq!(root.world.resource::<Events<Ping>>().len(), 0);
+    assert_eq!(
+        late_reader
+            .read(root.world.resource::<Events<Ping>>())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn engine_events_are_sent_during_ticks() {
+    let mut root = headless_root("engine_events", Vec::new());
+    let mut pressed_reader = root.world.resource::<Events<ActionPressed>>().get_reader();
+    let mut freeze_reader = root.world.resource::<Events<FreezeChanged>>().get_reader();
+    let mut loaded_reader = root.world.resource::<Events<SceneLoaded>>().get_reader();
+
+    // the starting scene was loaded while building the root
+    let loaded: Vec<_> = loaded_reader
+        .read(root.world.resource::<Events<SceneLoaded>>())
+        .map(|event| event.name.clone())
+        .collect();
+    assert_eq!(loaded, vec!["engine_events"]);
+
+    let space = KeycodeType::Keyboard(ggez::input::keyboard::KeyCode::Space);
+    let mut input = root.world.resource_mut::<Input>();
+    ActionData::new(&mut input, "jump".to_owned(), vec![space]);
+    input.update_key_queue(space, true);
+
+    root.world
+        .resource_mut::<GgezInterface>()
+        .push_freeze(FreezeType::PAUSED);
+
+    root.step_ticks(1);
+
+    let pressed: Vec<_> = pressed_reader
+        .read(root.world.resource::<Events<ActionPressed>>())
+        .map(|event| event.name.clone())
+        .collect();
+    assert_eq!(pressed, vec!["jump"]);
+
+    let changes: Vec<_> = freeze_reader
+        .read(root.world.resource::<Events<FreezeChanged>>())
+        .cloned()
+        .collect();
+    assert_eq!(
+        changes,
+        vec![FreezeChanged {
+            previous: FreezeType::NONE,
+            current: FreezeType::PAUSED
+        }]
+    );
+
+    // holding the key doesn't send it again
+    root.step_ticks(1);
+    assert_eq!(
+        pressed_reader
+            .read(root.world.resource::<Events<ActionPressed>>())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn log_filters_pick_the_most_specific_target() {
+    // listed shortest first, to show order doesn't matter
+    let filter = LogFilter::from_str("warn,engine=info,engine::scene=trace").unwrap();
+
+    assert_eq!(
+        filter.level_for("engine::scene::loading"),
+        LevelFilter::Trace
+    );
+    assert_eq!(filter.level_for("engine::scene"), LevelFilter::Trace);
+    assert_eq!(filter.level_for("engine::space"), LevelFilter::Info);
+    assert_eq!(filter.level_for("engine"), LevelFilter::Info);
+    assert_eq!(filter.level_for("wgpu_core"), LevelFilter::Warn);
+    assert_eq!(filter.max_level(), LevelFilter::Trace);
+}
+
+#[test]
+fn log_filter_targets_only_match_whole_path_segments() {
+    let filter = LogFilter::from_str("error,engine=debug").unwrap();
+
+    assert_eq!(filter.level_for("engine::time"), LevelFilter::Debug);
+    assert_eq!(filter.level_for("engine_macros"), LevelFilter::Error);
+    assert_eq!(filter.level_for("engineer::tools"), LevelFilter::Error);
+    assert_eq!(filter.level_for("game::engine"), LevelFilter::Error);
+}
+
+#[test]
+fn log_filters_default_to_the_bare_level() {
+    let filter = LogFilter::from_str(" debug , ").unwrap();
+    assert_eq!(filter.level_for("anything::at::all"), LevelFilter::Debug);
+    assert_eq!(filter.max_level(), LevelFilter::Debug);
+
+    // without a bare level, every target that isn't listed gets info
+    let filter = LogFilter::from_str("game=off").unwrap();
+    assert_eq!(filter.level_for("game::player"), LevelFilter::Off);
+    assert_eq!(filter.level_for("engine"), LevelFilter::Info);
+    assert_eq!(LogFilter::from_str("").unwrap(), LogFilter::default());
+}
+
+#[test]
+fn log_filters_reject_invalid_levels() {
+    let err = LogFilter::from_str("warn,engine=loud").unwrap_err();
+    assert!(err.contains("engine=loud"), "{}", err);
+
+    assert!(LogFilter::from_str("verbose").is_err());
+    assert!(LogFilter::from_str("engine=").is_err());
+}
@@ -0,0 +1,156 @@
+//! Contains the [`Time`] resource, which tracks the fixed timestep that ticks run at.
+//!
+//! Ticks always run at the rate set by [`EngineConfig::ticks_per_second`](crate::EngineConfig), so game logic stays deterministic.
+//! Slow motion is done through [`Time::set_time_scale`], which shrinks how much game time each tick represents instead of running less ticks.
+
+use bevy_ecs::system::Resource;
+use log::warn;
+use std::time::Duration;
+
+/// The most ticks that can be run in a single update before the remaining backlog is thrown away.
+///
+/// Without a limit, a long enough stall would make the engine spend every update catching up, which only makes the stall worse.
+pub const MAX_TICKS_PER_UPDATE: u32 = 5;
+
+/// The fastest the game can be sped up to with [`Time::set_time_scale`].
+pub const MAX_TIME_SCALE: f32 = 100.0;
+
+/// Keeps track of the fixed timestep, the amount of ticks ran so far, and how much real time has passed.
+///
+/// Add it as a [`bevy_ecs::system::Res<Time>`] parameter to read it, or [`bevy_ecs::system::ResMut<Time>`] to change the time scale.
+///
+/// Values measured in "per tick" units, like [`Velocity`](crate::space::Velocity), should be multiplied by [`Time::time_scale`] when integrating,
+/// so that they slow down along with the time scale.
+#[derive(Debug, Clone, Resource)]
+pub struct Time {
+    /// The real amount of time between each tick
+    fixed_delta: Duration,
+    /// Multiplier for how much game time passes each tick
+    time_scale: f32,
+    /// The amount of ticks that have run since the engine started, including frozen ticks but not ticks paused by frame-advance
+    ticks: u64,
+    /// The real amount of time that has passed since the engine started
+    elapsed: Duration,
+    /// The amount of game time that has passed since the engine started, which is affected by the time scale
+    game_elapsed: Duration,
+    /// Real time that has passed but hasn't been used up by a tick yet
+    accumulator: Duration,
+}
+
+impl Time {
+    /// Creates a new [`Time`] resource where each tick lasts `1 / ticks_per_second` seconds.
+    ///
+    /// A `ticks_per_second` of 0 is treated as 1.
+    pub fn new(ticks_per_second: u32) -> Self {
+        Self {
+            fixed_delta: Duration::from_secs_f64(1.0 / ticks_per_second.max(1) as f64),
+            time_scale: 1.0,
+            ticks: 0,
+            elapsed: Duration::ZERO,
+            game_elapsed: Duration::ZERO,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// The real amount of time between each tick, unaffected by the time scale.
+    pub fn fixed_delta(&self) -> Duration {
+        self.fixed_delta
+    }
+
+    /// The amount of game time that passes each tick, which is the fixed delta multiplied by the time scale.
+    pub fn delta(&self) -> Duration {
+        self.fixed_delta.mul_f32(self.time_scale)
+    }
+
+    /// Same as [`Time::delta`], in seconds.
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta().as_secs_f32()
+    }
+
+    /// Returns the global time scale. `1.0` is normal speed, `0.5` is half speed.
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Sets the global time scale, clamped between `0.0` and [`MAX_TIME_SCALE`].
+    ///
+    /// Infinite and NaN scales are ignored, and the time scale is left unchanged.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        if !time_scale.is_finite() {
+            warn!(
+                "Ignored a time scale of {}, it has to be finite",
+                time_scale
+            );
+            return;
+        }
+
+        self.time_scale = time_scale.clamp(0.0, MAX_TIME_SCALE);
+    }
+
+    /// The amount of ticks that have run since the engine started, including ticks that ran while frozen.
+    ///
+    /// Ticks spent paused by [`FreezeType::DEBUG`](crate::freeze::FreezeType::DEBUG) aren't counted, so that frame-advance steps through one tick at a time.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// The real amount of time that has passed since the engine started.
+    ///
+    /// Headless roots don't advance real time, see [`GameRoot::step_ticks`](crate::GameRoot::step_ticks).
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The amount of game time that has passed, which is the sum of [`Time::delta`] for every tick ran so far.
+    pub fn game_elapsed(&self) -> Duration {
+        self.game_elapsed
+    }
+
+    /// How far between the last tick and the next one the current frame is, from `0.0` to `1.0`.
+    ///
+    /// Renderers can use this to interpolate between the previous and current tick's positions.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f32() / self.fixed_delta.as_secs_f32()).clamp(0.0, 1.0)
+    }
+
+    /// Adds real time that has passed since the last update.
+    pub(crate) fn advance_real(&mut self, delta: Duration) {
+        self.elapsed += delta;
+        self.accumulator += delta;
+    }
+
+    /// Returns true and uses up one tick's worth of time if enough real time has built up for another tick.
+    pub(crate) fn consume_tick(&mut self) -> bool {
+        if self.accumulator < self.fixed_delta {
+            return false;
+        }
+
+        self.accumulator -= self.fixed_delta;
+        true
+    }
+
+    /// Throws away any real time that hasn't been used by a tick yet, and returns how much was thrown away.
+    pub(crate) fn drop_backlog(&mut self) -> Duration {
+        let dropped = self.accumulator;
+        self.accumulator = Duration::ZERO;
+        dropped
+    }
+
+    /// Moves the tick counter and game time forward by one tick.
+    pub(crate) fn advance_tick(&mut self) {
+        self.ticks += 1;
+        self.game_elapsed += self.delta();
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new(60)
+    }
+}
+
+/// The total real time the player has spent in the game, carried over between sessions by save slots.
+///
+/// Unlike [`Time::elapsed`], loading a save slot sets this to the play time stored in the save, see [`crate::save`].
+#[derive(Debug, Clone, Copy, Default, Resource)]
+pub struct PlayTime(pub Duration);
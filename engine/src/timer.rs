@@ -0,0 +1,253 @@
+//! Contains the [`Timer`], [`Cooldown`] and [`DespawnAfter`] components, which count ticks so that gameplay code doesn't have to.
+//!
+//! Every duration is measured in ticks, not seconds, so they stay deterministic no matter the frame rate or [`Time::set_time_scale`](crate::Time::set_time_scale).
+//! The engine counts them down in [`EngineSet::GameLogic`](crate::schedule::EngineSet::GameLogic) of [`ScheduleTag::Tick`](crate::schedule::ScheduleTag::Tick),
+//! before any gameplay systems in that set, and they stop counting while the game is frozen like any other gameplay system.
+//!
+//! Each one sends an event when it completes: [`TimerFinished`], [`CooldownReady`] and [`DespawnAfterFinished`].
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::scene::{Scene, SceneData, SceneManager};
+
+/// Counts up to a duration in ticks, either once or over and over.
+#[derive(Debug, Component, Default, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[reflect(Component)]
+pub struct Timer {
+    /// How many ticks the timer lasts
+    pub duration: u32,
+    /// How many ticks have been counted so far
+    pub elapsed: u32,
+    /// Starts over once finished, instead of stopping
+    pub repeating: bool,
+    /// Stops counting without resetting
+    pub paused: bool,
+    #[serde(skip)]
+    #[reflect(ignore)]
+    just_finished: bool,
+}
+
+impl Timer {
+    /// Creates a timer that finishes once after `duration` ticks.
+    pub fn new(duration: u32) -> Self {
+        Self {
+            duration,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a timer that finishes every `duration` ticks.
+    pub fn repeating(duration: u32) -> Self {
+        Self {
+            duration,
+            repeating: true,
+            ..Default::default()
+        }
+    }
+
+    /// Counts one tick, returning true if the timer finished on this tick.
+    ///
+    /// A timer with a duration of zero finishes on its first tick.
+    /// Called by the engine every tick, so only call this by hand on timers that aren't components.
+    pub fn tick(&mut self) -> bool {
+        self.just_finished = false;
+
+        // a zero length timer counts as finished before it's ticked, but still has to report finishing once
+        if self.paused || (self.elapsed > 0 && self.is_finished()) {
+            return false;
+        }
+
+        self.elapsed += 1;
+
+        if self.elapsed >= self.duration {
+            self.just_finished = true;
+
+            if self.repeating {
+                self.elapsed = 0;
+            }
+        }
+
+        self.just_finished
+    }
+
+    /// Returns true if a non-repeating timer has run out. Repeating timers are never finished, see [`Timer::just_finished`].
+    pub fn is_finished(&self) -> bool {
+        !self.repeating && self.elapsed >= self.duration
+    }
+
+    /// Returns true if the timer finished on the most recent tick.
+    pub fn just_finished(&self) -> bool {
+        self.just_finished
+    }
+
+    /// How many ticks are left until the timer finishes.
+    pub fn remaining(&self) -> u32 {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    /// How far the timer is from `0.0` to `1.0`. A timer with no duration is always at `1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.duration == 0 {
+            return 1.0;
+        }
+
+        (self.elapsed as f32 / self.duration as f32).min(1.0)
+    }
+
+    /// Starts the timer over from zero, keeping its duration.
+    pub fn reset(&mut self) {
+        self.elapsed = 0;
+        self.just_finished = false;
+    }
+}
+
+/// Keeps an action from being used again until a number of ticks have passed.
+///
+/// Starts out ready. Use [`Cooldown::trigger`] when the action is used.
+#[derive(Debug, Component, Default, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[reflect(Component)]
+pub struct Cooldown {
+    /// How many ticks the cooldown lasts after being triggered
+    pub duration: u32,
+    /// How many ticks are left until the cooldown is ready again
+    pub remaining: u32,
+}
+
+impl Cooldown {
+    pub fn new(duration: u32) -> Self {
+        Self {
+            duration,
+            remaining: 0,
+        }
+    }
+
+    /// Returns true if the cooldown has run out.
+    pub fn is_ready(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Starts the cooldown if it's ready, returning true if it was.
+    ///
+    /// Gameplay code should only perform the action when this returns true.
+    pub fn trigger(&mut self) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+
+        self.remaining = self.duration;
+        true
+    }
+
+    /// Counts one tick, returning true if the cooldown became ready on this tick.
+    pub fn tick(&mut self) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+
+        self.remaining -= 1;
+        self.remaining == 0
+    }
+
+    /// Makes the cooldown ready immediately.
+    pub fn reset(&mut self) {
+        self.remaining = 0;
+    }
+}
+
+/// Despawns the entity it's attached to once the given number of ticks have passed, removing it from its scene.
+#[derive(Debug, Component, Default, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[reflect(Component)]
+pub struct DespawnAfter {
+    /// How many ticks are left until the entity is despawned
+    pub remaining: u32,
+}
+
+impl DespawnAfter {
+    pub fn new(ticks: u32) -> Self {
+        Self { remaining: ticks }
+    }
+}
+
+/// Sent when a [`Timer`] component finishes, including every time a repeating timer finishes.
+#[derive(Debug, Clone, Event)]
+pub struct TimerFinished {
+    pub entity: Entity,
+}
+
+/// Sent when a [`Cooldown`] component becomes ready again.
+#[derive(Debug, Clone, Event)]
+pub struct CooldownReady {
+    pub entity: Entity,
+}
+
+/// Sent when a [`DespawnAfter`] component runs out. The entity is despawned by the time this is read.
+#[derive(Debug, Clone, Event)]
+pub struct DespawnAfterFinished {
+    pub entity: Entity,
+    /// The entity's name from its [`SceneData`], if it had one
+    pub name: Option<String>,
+}
+
+/// Counts every [`Timer`] component, sending [`TimerFinished`] for each one that finishes.
+pub fn timer_update(
+    mut query: Query<(Entity, &mut Timer)>,
+    mut events: EventWriter<TimerFinished>,
+) {
+    for (entity, mut timer) in query.iter_mut() {
+        if timer.tick() {
+            events.send(TimerFinished { entity });
+        }
+    }
+}
+
+/// Counts down every [`Cooldown`] component, sending [`CooldownReady`] for each one that becomes ready.
+pub fn cooldown_update(
+    mut query: Query<(Entity, &mut Cooldown)>,
+    mut events: EventWriter<CooldownReady>,
+) {
+    for (entity, mut cooldown) in query.iter_mut() {
+        if cooldown.tick() {
+            events.send(CooldownReady { entity });
+        }
+    }
+}
+
+/// Counts down every [`DespawnAfter`] component, despawning each entity that runs out and removing it from its [`Scene`].
+pub fn despawn_after_update(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut DespawnAfter, Option<&SceneData>)>,
+    mut scenes: Query<&mut Scene>,
+    scene_manager: Option<Res<SceneManager>>,
+    mut events: EventWriter<DespawnAfterFinished>,
+) {
+    for (entity, mut despawn_after, scene_data) in query.iter_mut() {
+        despawn_after.remaining = despawn_after.remaining.saturating_sub(1);
+
+        if despawn_after.remaining > 0 {
+            continue;
+        }
+
+        let scene_entity = scene_data
+            .and_then(|scene_data| scene_data.scene_id)
+            .zip(scene_manager.as_ref())
+            .and_then(|(scene_id, scene_manager)| scene_manager.get_scene_by_id(scene_id));
+
+        if let Some(mut scene) =
+            scene_entity.and_then(|scene_entity| scenes.get_mut(scene_entity).ok())
+        {
+            scene
+                .entities
+                .retain(|scene_entity| *scene_entity != entity);
+        }
+
+        events.send(DespawnAfterFinished {
+            entity,
+            name: scene_data.map(|scene_data| scene_data.entity_name.clone()),
+        });
+
+        commands.entity(entity).despawn();
+    }
+}
@@ -32,8 +32,10 @@ fn main() -> ! {
         .build()
         .expect("aieee, could not create ggez context!");
 
-    let root = engine::GameRoot::new(&mut context, &game::ENGINE_CONFIG)
-        .expect("could not build game root");
+    let config = game::engine_config()
+        .unwrap_or_else(|err| panic!("could not load engine config! {}", err.to_string()));
+
+    let root = engine::GameRoot::new(&mut context, config).expect("could not build game root");
 
     ggez::event::run(context, event_loop, root);
 }
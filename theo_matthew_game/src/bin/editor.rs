@@ -1,12 +1,4 @@
-static ENGINE_CONFIG: engine::EngineConfig = engine::EngineConfig {
-    external_scene_paths: &["theo_matthew_game/assets/scenes/prototype.json"],
-    scenes_folder: Some("theo_matthew_game/assets/scenes"),
-    world_init: theo_matthew_game::init_world,
-    ticks_per_second: 60,
-    freeze_on_unfocus: false,
-    freeze_on_minimize: true,
-    run_debug_schedules: true,
-};
+static ENGINE_CONFIG_PATH: &str = "theo_matthew_game/assets/engine_config.toml";
 
 fn main() {
     let (mut context, event_loop) =
@@ -22,10 +14,14 @@ fn main() {
             .build()
             .expect("could not build context?!");
 
-    let mut game_root = engine::GameRoot::new(&mut context, &ENGINE_CONFIG)
-        .expect("expected no errors on game root initialization");
+    let config = engine::EngineConfig::load(ENGINE_CONFIG_PATH)
+        .unwrap_or_else(|err| panic!("could not load engine config! {}", err.to_string()))
+        .with_plugin(components::ComponentsPlugin)
+        .with_plugin(theo_matthew_game::TheoMatthewGamePlugin)
+        .with_plugin(editor::EditorPlugin);
 
-    editor::console::hook_emergency_panic_handler(&mut game_root);
+    let game_root = engine::GameRoot::new(&mut context, config)
+        .expect("expected no errors on game root initialization");
 
     ggez::event::run(context, event_loop, game_root);
 }
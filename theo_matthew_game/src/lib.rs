@@ -3,43 +3,40 @@ use std::path::{Path, PathBuf};
 
 use bevy_ecs::component::Component;
 use bevy_ecs::reflect::ReflectComponent;
-use bevy_ecs::schedule::{IntoSystemConfigs, Schedule};
 use bevy_ecs::system::Commands;
 use bevy_ecs::world::World;
 use bevy_reflect::Reflect;
-use components::schedules::{frame, tick};
+use components::ComponentsPlugin;
+use engine::plugin::plugin_name;
+use engine::schedule::{EngineSet, ScheduleBuilder, ScheduleTag};
+use engine::EnginePlugin;
 
 use engine::scene::SceneData;
 use serde::{Deserialize, Serialize};
 
-pub fn init_world(world: &mut World) {
-    let mut schedule = Schedule::new(engine::schedule::ScheduleTag::Tick);
-    schedule.add_systems((
-        tick::collider_update,
-        tick::protag_update,
-        tick::renderer_update,
-    ));
-    world.add_schedule(schedule);
-
-    let mut schedule = Schedule::new(engine::schedule::ScheduleTag::Frame);
-    schedule.add_systems(
-        (
-            frame::mesh_renderer_draw,
-            frame::render_text_renderers,
-            frame::renderer_draw,
-        )
-            .chain(),
-    );
-    world.add_schedule(schedule);
-
-    components::initialize_component_types(world);
-
-    editor::init_editor_resources(world);
-
-    world.add_schedule(editor::debug_tick_schedule());
-    world.add_schedule(editor::debug_frame_schedule());
-    world.add_schedule(editor::debug_init_schedule());
-    world.add_schedule(editor::debug_gui_schedule());
+/// Places the game's systems into the engine's schedules. Requires the [`ComponentsPlugin`] to have registered them first.
+pub struct TheoMatthewGamePlugin;
+
+impl EnginePlugin for TheoMatthewGamePlugin {
+    fn dependencies(&self) -> Vec<&'static str> {
+        vec![plugin_name::<ComponentsPlugin>()]
+    }
+
+    fn build(&self, world: &mut World) {
+        let mut builder = world.resource_mut::<ScheduleBuilder>();
+
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::GameLogic, "protag_update");
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::Physics, "collider_update");
+        builder.add_to_set(ScheduleTag::Tick, EngineSet::Render, "renderer_update");
+
+        builder.add_to_set(ScheduleTag::Frame, EngineSet::Render, "mesh_renderer_draw");
+        builder.add_to_set(
+            ScheduleTag::Frame,
+            EngineSet::Render,
+            "render_text_renderers",
+        );
+        builder.add_to_set(ScheduleTag::Frame, EngineSet::Render, "renderer_draw");
+    }
 }
 
 #[derive(Debug, Default, Component, Reflect, Serialize, Deserialize)]